@@ -0,0 +1,228 @@
+use std::io::Cursor;
+
+use rubato::{FftFixedIn, Resampler};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CodecParameters, DecoderOptions, CODEC_TYPE_NULL, CODEC_TYPE_OPUS};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+// Turns whatever the recorder produced into what Whisper wants: 16 kHz mono
+// f32 samples in [-1.0, 1.0]. Handles WAV (integer or float, any channel
+// count), WebM/Opus from the webview's MediaRecorder and M4A/AAC from phones.
+
+pub const SAMPLE_RATE: u32 = 16_000;
+
+// Opus always decodes at 48 kHz, whatever rate the encoder was fed.
+const OPUS_RATE: u32 = 48_000;
+// Largest Opus frame: 120 ms at 48 kHz.
+const OPUS_MAX_FRAME: usize = 5_760;
+const RESAMPLE_CHUNK: usize = 1_024;
+
+const UNSUPPORTED: &str = "Unsupported audio format; expected WAV, WebM/Opus or M4A/AAC";
+
+// File extension for a recording, judged from its first bytes.
+pub fn file_extension(bytes: &[u8]) -> &'static str {
+    if bytes.starts_with(b"RIFF") {
+        "wav"
+    } else if bytes.get(4..8) == Some(b"ftyp") {
+        "m4a"
+    } else {
+        "webm"
+    }
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Vec<f32>, String> {
+    if bytes.is_empty() {
+        return Err("Recording is empty".to_string());
+    }
+    let source = MediaSourceStream::new(Box::new(Cursor::new(bytes.to_vec())), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(
+            &Hint::new(),
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| match e {
+            SymphoniaError::Unsupported(_) => UNSUPPORTED.to_string(),
+            e => format!("Could not read recording: {}", e),
+        })?;
+    let mut format = probed.format;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or("Recording has no audio track")?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+
+    let (samples, rate) = if params.codec == CODEC_TYPE_OPUS {
+        (decode_opus(format.as_mut(), track_id, &params)?, OPUS_RATE)
+    } else {
+        let rate = params
+            .sample_rate
+            .ok_or("Recording does not state its sample rate")?;
+        (decode_packets(format.as_mut(), track_id, &params)?, rate)
+    };
+    if samples.is_empty() {
+        return Err("Recording contains no audio".to_string());
+    }
+    println!("DEBUG: Decoded {} samples at {} Hz", samples.len(), rate);
+    resample(&samples, rate)
+}
+
+// Next packet of `track_id`, or `None` at the end of the stream.
+fn next_packet(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+) -> Result<Option<symphonia::core::formats::Packet>, String> {
+    loop {
+        match format.next_packet() {
+            Ok(packet) if packet.track_id() == track_id => return Ok(Some(packet)),
+            Ok(_) => continue,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return Ok(None)
+            }
+            Err(e) => return Err(format!("Could not read recording: {}", e)),
+        }
+    }
+}
+
+fn decode_packets(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+    params: &CodecParameters,
+) -> Result<Vec<f32>, String> {
+    let mut decoder = symphonia::default::get_codecs()
+        .make(params, &DecoderOptions::default())
+        .map_err(|e| match e {
+            SymphoniaError::Unsupported(_) => UNSUPPORTED.to_string(),
+            e => format!("Could not decode recording: {}", e),
+        })?;
+    let mut mono = Vec::new();
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+    while let Some(packet) = next_packet(format, track_id)? {
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A damaged packet costs a few milliseconds; keep going.
+            Err(SymphoniaError::DecodeError(e)) => {
+                println!("WARNING: Skipping undecodable audio packet: {}", e);
+                continue;
+            }
+            Err(e) => return Err(format!("Could not decode recording: {}", e)),
+        };
+        let spec = *decoded.spec();
+        let channels = spec.channels.count();
+        let buffer = match &mut buffer {
+            Some(buffer) if buffer.capacity() >= decoded.capacity() * channels => buffer,
+            _ => buffer.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+        };
+        buffer.copy_interleaved_ref(decoded);
+        downmix(buffer.samples(), channels, &mut mono);
+    }
+    Ok(mono)
+}
+
+// Symphonia demuxes WebM but has no Opus decoder, so packets go to libopus.
+fn decode_opus(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+    params: &CodecParameters,
+) -> Result<Vec<f32>, String> {
+    // The OpusHead header carries the channel count (byte 9) and how many
+    // leading samples are encoder padding (bytes 10-11).
+    let head = params.extra_data.as_deref().unwrap_or_default();
+    let channels = params
+        .channels
+        .map(|c| c.count())
+        .or_else(|| head.get(9).map(|&c| c as usize))
+        .unwrap_or(1);
+    let mut pre_skip = match head {
+        [_, _, _, _, _, _, _, _, _, _, lo, hi, ..] => u16::from_le_bytes([*lo, *hi]) as usize,
+        _ => 0,
+    };
+    let layout = match channels {
+        1 => opus::Channels::Mono,
+        2 => opus::Channels::Stereo,
+        n => {
+            return Err(format!(
+                "Opus recordings with {} channels are not supported",
+                n
+            ))
+        }
+    };
+    let mut decoder = opus::Decoder::new(OPUS_RATE, layout)
+        .map_err(|e| format!("Could not decode recording: {}", e))?;
+
+    let mut frame = vec![0f32; OPUS_MAX_FRAME * channels];
+    let mut mono = Vec::new();
+    while let Some(packet) = next_packet(format, track_id)? {
+        let decoded = match decoder.decode_float(&packet.data, &mut frame, false) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                println!("WARNING: Skipping undecodable audio packet: {}", e);
+                continue;
+            }
+        };
+        let skip = pre_skip.min(decoded);
+        pre_skip -= skip;
+        downmix(
+            &frame[skip * channels..decoded * channels],
+            channels,
+            &mut mono,
+        );
+    }
+    Ok(mono)
+}
+
+// Averages interleaved frames into one channel.
+fn downmix(interleaved: &[f32], channels: usize, mono: &mut Vec<f32>) {
+    if channels <= 1 {
+        mono.extend_from_slice(interleaved);
+        return;
+    }
+    mono.extend(
+        interleaved
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+    );
+}
+
+pub fn resample(samples: &[f32], rate: u32) -> Result<Vec<f32>, String> {
+    if rate == SAMPLE_RATE {
+        return Ok(samples.to_vec());
+    }
+    let failed = |e: &dyn std::fmt::Display| format!("Could not resample recording: {}", e);
+    let mut resampler =
+        FftFixedIn::<f32>::new(rate as usize, SAMPLE_RATE as usize, RESAMPLE_CHUNK, 2, 1)
+            .map_err(|e| failed(&e))?;
+    let delay = resampler.output_delay();
+    let expected = (samples.len() as u64 * SAMPLE_RATE as u64 / rate as u64) as usize;
+
+    let mut output = Vec::with_capacity(expected + delay + RESAMPLE_CHUNK);
+    let mut chunks = samples.chunks_exact(RESAMPLE_CHUNK);
+    for chunk in &mut chunks {
+        let resampled = resampler.process(&[chunk], None).map_err(|e| failed(&e))?;
+        output.extend_from_slice(&resampled[0]);
+    }
+    let resampled = resampler
+        .process_partial(Some(&[chunks.remainder()]), None)
+        .map_err(|e| failed(&e))?;
+    output.extend_from_slice(&resampled[0]);
+    // Push out what the filter is still holding.
+    while output.len() < expected + delay {
+        let resampled = resampler
+            .process_partial::<&[f32]>(None, None)
+            .map_err(|e| failed(&e))?;
+        if resampled[0].is_empty() {
+            break;
+        }
+        output.extend_from_slice(&resampled[0]);
+    }
+    output.drain(..delay.min(output.len()));
+    output.truncate(expected);
+    Ok(output)
+}
@@ -0,0 +1,68 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use printpdf::image_crate::{self, DynamicImage, ImageOutputFormat};
+
+// Who "we" are on invoices and estimates.
+
+// Stored under "company" in `app_settings`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+pub struct CompanyProfile {
+    #[serde(default)]
+    pub name: String,
+    // Set through `save_logo`, which normalises the image.
+    #[serde(default)]
+    pub logo_path: Option<String>,
+    #[serde(default)]
+    pub licence_number: Option<String>,
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub tax_id: Option<String>,
+    // E.g. "Due within 14 days by bank transfer".
+    #[serde(default)]
+    pub payment_terms: Option<String>,
+    #[serde(default)]
+    pub footer: Option<String>,
+    // Template for clients without one of their own; the built-in default
+    // when unset.
+    #[serde(default)]
+    pub template_id: Option<String>,
+}
+
+// Logos are scaled down to this many pixels on the longer side, plenty for
+// the few centimetres they take up on a page.
+const MAX_LOGO_PX: u32 = 600;
+
+// Decodes an uploaded PNG/JPEG/GIF logo and writes it to `dir` as a PNG no
+// larger than it needs to be. Returns the new path.
+pub fn save_logo(data: &[u8], dir: &Path) -> Result<PathBuf, String> {
+    let image =
+        image_crate::load_from_memory(data).map_err(|e| format!("Unsupported logo: {}", e))?;
+    let image = if image.width() > MAX_LOGO_PX || image.height() > MAX_LOGO_PX {
+        image.thumbnail(MAX_LOGO_PX, MAX_LOGO_PX)
+    } else {
+        image
+    };
+    let mut png = Cursor::new(Vec::new());
+    image
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .map_err(|e| e.to_string())?;
+
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let path = dir.join("logo.png");
+    std::fs::write(&path, png.into_inner()).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+// The saved logo, or `None` when there is none or it cannot be read.
+pub fn load_logo(profile: &CompanyProfile) -> Option<DynamicImage> {
+    let path = profile.logo_path.as_deref()?;
+    match image_crate::open(path) {
+        Ok(image) => Some(image),
+        Err(e) => {
+            println!("WARNING: Could not read company logo {}: {}", path, e);
+            None
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, Local, NaiveDate};
+use rusqlite::{params, params_from_iter, Connection};
+
+use crate::money::Money;
+use crate::{item_totals, numbering, InvoiceItem};
+
+// Quotes for work not yet done. They are numbered from their own sequence
+// ("EST-0001"), stay valid until `valid_until`, and once accepted can be
+// converted into an invoice, which records the estimate it came from.
+
+pub const DRAFT: &str = "DRAFT";
+pub const SENT: &str = "SENT";
+pub const ACCEPTED: &str = "ACCEPTED";
+pub const DECLINED: &str = "DECLINED";
+const STATUSES: [&str; 4] = [DRAFT, SENT, ACCEPTED, DECLINED];
+
+// How long a quote holds when no expiry was given.
+pub const DEFAULT_VALID_DAYS: i64 = 30;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct Estimate {
+    pub id: String,
+    pub client: String,
+    #[serde(default)]
+    pub amount: Money,
+    #[serde(default = "default_status")]
+    pub status: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub client_phone: Option<String>,
+    #[serde(default)]
+    pub client_company: Option<String>,
+    #[serde(default)]
+    pub items: Vec<InvoiceItem>,
+    #[serde(default)]
+    pub tax_rate: f64,
+    #[serde(default)]
+    pub subtotal: Money,
+    #[serde(default)]
+    pub tax: Money,
+    #[serde(default)]
+    pub number: Option<String>,
+    // YYYY-MM-DD, the last day the price holds.
+    #[serde(default)]
+    pub valid_until: Option<String>,
+    // Past `valid_until` without being accepted or declined; not stored.
+    #[serde(default)]
+    pub expired: bool,
+    #[serde(default)]
+    pub decided_at: Option<String>,
+    // The invoice it was converted into.
+    #[serde(default)]
+    pub invoice_id: Option<String>,
+    #[serde(default)]
+    pub pdf_path: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+fn default_status() -> String {
+    DRAFT.to_string()
+}
+
+impl Estimate {
+    // Recomputes subtotal, tax and total (`amount`) from the line items, as
+    // for invoices.
+    pub fn apply_item_totals(&mut self) {
+        if self.items.is_empty() {
+            self.items
+                .push(InvoiceItem::single(&self.description, self.amount));
+        }
+        (self.subtotal, self.tax, self.amount) = item_totals(&self.items, self.tax_rate);
+    }
+}
+
+// Today plus the default validity, as YYYY-MM-DD.
+pub fn default_valid_until() -> String {
+    (Local::now().date_naive() + Duration::days(DEFAULT_VALID_DAYS))
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+fn check(estimate: &Estimate) -> Result<(), String> {
+    if !STATUSES.contains(&estimate.status.as_str()) {
+        return Err(format!("Unknown estimate status '{}'", estimate.status));
+    }
+    if let Some(date) = &estimate.valid_until {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| format!("Invalid expiry date '{}'", date))?;
+    }
+    Ok(())
+}
+
+// Allocates the estimate's number and stores it with its items. Call inside a
+// transaction so the number is only used if the insert succeeds.
+pub fn insert(conn: &Connection, estimate: &Estimate) -> Result<String, String> {
+    check(estimate)?;
+    let number = numbering::next_number(conn, "estimate", "estimates")?;
+    conn.execute(
+        "INSERT INTO estimates (id, number, client, client_phone, client_company, description, amount_cents, tax_rate, status, valid_until, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            estimate.id,
+            number,
+            estimate.client,
+            estimate.client_phone,
+            estimate.client_company,
+            estimate.description,
+            estimate.amount,
+            estimate.tax_rate,
+            estimate.status,
+            estimate.valid_until,
+            estimate.created_at.clone().unwrap_or_else(|| Local::now().to_rfc3339())
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    insert_items(conn, &estimate.id, &estimate.items)?;
+    Ok(number)
+}
+
+// Replaces the estimate's details and items. Converted estimates are final.
+pub fn update(conn: &Connection, estimate: &Estimate) -> Result<(), String> {
+    check(estimate)?;
+    if load(conn, &estimate.id)?.invoice_id.is_some() {
+        return Err("Converted estimates cannot be changed".to_string());
+    }
+    conn.execute(
+        "UPDATE estimates SET client = ?2, client_phone = ?3, client_company = ?4, description = ?5, amount_cents = ?6, tax_rate = ?7, status = ?8, valid_until = ?9 WHERE id = ?1",
+        params![
+            estimate.id,
+            estimate.client,
+            estimate.client_phone,
+            estimate.client_company,
+            estimate.description,
+            estimate.amount,
+            estimate.tax_rate,
+            estimate.status,
+            estimate.valid_until
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM estimate_items WHERE estimate_id = ?1",
+        [&estimate.id],
+    )
+    .map_err(|e| e.to_string())?;
+    insert_items(conn, &estimate.id, &estimate.items)
+}
+
+fn insert_items(conn: &Connection, estimate_id: &str, items: &[InvoiceItem]) -> Result<(), String> {
+    for (position, item) in items.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO estimate_items (estimate_id, position, description, quantity, unit, unit_price_cents, taxable) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![estimate_id, position as i64, item.description, item.quantity, item.unit, item.unit_price, item.taxable],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// Records the customer's answer. Only ACCEPTED and DECLINED are decisions;
+// moving back to DRAFT or SENT clears the decision time.
+pub fn set_status(conn: &Connection, id: &str, status: &str) -> Result<(), String> {
+    if !STATUSES.contains(&status) {
+        return Err(format!("Unknown estimate status '{}'", status));
+    }
+    let estimate = load(conn, id)?;
+    if estimate.invoice_id.is_some() && status != ACCEPTED {
+        return Err("Converted estimates stay accepted".to_string());
+    }
+    let decided_at = match status {
+        ACCEPTED | DECLINED if estimate.status == status => estimate.decided_at,
+        ACCEPTED | DECLINED => Some(Local::now().to_rfc3339()),
+        _ => None,
+    };
+    conn.execute(
+        "UPDATE estimates SET status = ?2, decided_at = ?3 WHERE id = ?1",
+        params![id, status, decided_at],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Marks the estimate accepted and links it to the invoice made from it.
+pub fn link_invoice(conn: &Connection, id: &str, invoice_id: &str) -> Result<(), String> {
+    set_status(conn, id, ACCEPTED)?;
+    conn.execute(
+        "UPDATE estimates SET invoice_id = ?2 WHERE id = ?1",
+        params![id, invoice_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Deletes the estimate and its items; an invoice made from it keeps its
+// items but loses the link.
+pub fn delete(conn: &Connection, id: &str) -> Result<usize, String> {
+    conn.execute("DELETE FROM estimate_items WHERE estimate_id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE invoices SET estimate_id = NULL WHERE estimate_id = ?1",
+        [id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM estimates WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())
+}
+
+pub fn load(conn: &Connection, id: &str) -> Result<Estimate, String> {
+    query(conn, Some(id))?
+        .pop()
+        .ok_or_else(|| format!("Estimate {} not found", id))
+}
+
+// Newest first.
+pub fn load_all(conn: &Connection) -> Result<Vec<Estimate>, String> {
+    query(conn, None)
+}
+
+// Every estimate, or just the one with `id`.
+fn query(conn: &Connection, id: Option<&str>) -> Result<Vec<Estimate>, String> {
+    let filter = if id.is_some() { "WHERE id = ?1" } else { "" };
+    let mut items_by_estimate = load_items(conn, id)?;
+    let mut stmt = conn
+        .prepare(&format!("SELECT id, number, client, client_phone, client_company, COALESCE(description, ''), amount_cents, tax_rate, status, valid_until, decided_at, invoice_id, created_at FROM estimates {} ORDER BY created_at DESC, rowid DESC", filter))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params_from_iter(id), |row| {
+            Ok(Estimate {
+                id: row.get(0)?,
+                number: row.get(1)?,
+                client: row.get(2)?,
+                client_phone: row.get(3)?,
+                client_company: row.get(4)?,
+                description: row.get(5)?,
+                amount: row.get(6)?,
+                tax_rate: row.get(7)?,
+                status: row.get(8)?,
+                valid_until: row.get(9)?,
+                decided_at: row.get(10)?,
+                invoice_id: row.get(11)?,
+                created_at: row.get(12)?,
+                items: Vec::new(),
+                subtotal: Money::ZERO,
+                tax: Money::ZERO,
+                expired: false,
+                pdf_path: None,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let today = Local::now().date_naive();
+    let pdf_dir = dirs::home_dir().map(|home| home.join(".construction-os").join("estimates"));
+    let mut estimates = Vec::new();
+    for row in rows {
+        let mut estimate = row.map_err(|e| e.to_string())?;
+        estimate.items = items_by_estimate.remove(&estimate.id).unwrap_or_default();
+        estimate.apply_item_totals();
+        estimate.expired = matches!(estimate.status.as_str(), DRAFT | SENT)
+            && estimate
+                .valid_until
+                .as_deref()
+                .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+                .is_some_and(|date| date < today);
+        estimate.pdf_path = pdf_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.pdf", estimate.id)))
+            .filter(|path| path.exists())
+            .map(|path| path.to_string_lossy().to_string());
+        estimates.push(estimate);
+    }
+    Ok(estimates)
+}
+
+fn load_items(
+    conn: &Connection,
+    estimate_id: Option<&str>,
+) -> Result<HashMap<String, Vec<InvoiceItem>>, String> {
+    let filter = if estimate_id.is_some() {
+        "WHERE estimate_id = ?1"
+    } else {
+        ""
+    };
+    let mut stmt = conn
+        .prepare(&format!("SELECT estimate_id, description, quantity, unit, unit_price_cents, taxable FROM estimate_items {} ORDER BY estimate_id, position", filter))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params_from_iter(estimate_id), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                InvoiceItem {
+                    description: row.get(1)?,
+                    quantity: row.get(2)?,
+                    unit: row.get(3)?,
+                    unit_price: row.get(4)?,
+                    taxable: row.get(5)?,
+                },
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    let mut items: HashMap<String, Vec<InvoiceItem>> = HashMap::new();
+    for row in rows {
+        let (estimate_id, item) = row.map_err(|e| e.to_string())?;
+        items.entry(estimate_id).or_default().push(item);
+    }
+    Ok(items)
+}
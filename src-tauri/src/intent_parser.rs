@@ -0,0 +1,1129 @@
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Weekday};
+use regex::Regex;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use crate::intents::ParsedIntent;
+use crate::money::Money;
+
+// Offline, rule-based counterpart to the cloud prompt in `analyze_audio`.
+// Takes a Whisper transcript and extracts the same fields the model would.
+// Fields it cannot find are left as `None` rather than guessed.
+
+macro_rules! regex {
+    ($pattern:expr) => {{
+        static RE: OnceLock<Regex> = OnceLock::new();
+        RE.get_or_init(|| Regex::new($pattern).unwrap())
+    }};
+}
+
+#[derive(Debug, PartialEq)]
+enum Intent {
+    Invoice,
+    Estimate,
+    Task,
+    Contact,
+    Calendar,
+    Unknown,
+}
+
+// `now` is local wall-clock time; relative dates and times resolve against it.
+pub fn parse(transcript: &str, now: NaiveDateTime) -> ParsedIntent {
+    let text = spell_out_numbers(&normalize(transcript));
+    // ASCII lowercasing keeps byte offsets identical to `text`, so spans
+    // found in `lower` can be cut out of `text`.
+    let lower = text.to_ascii_lowercase();
+    let today = now.date();
+    match classify(&text, &lower, today) {
+        Intent::Invoice => parse_invoice(&text),
+        Intent::Estimate => parse_estimate(&text),
+        Intent::Task => parse_task(&text, today),
+        Intent::Contact => parse_contact(&text),
+        Intent::Calendar => parse_calendar(&text, now),
+        Intent::Unknown => ParsedIntent::Unknown {
+            error: Some(format!("Could not understand \"{}\"", text)),
+        },
+    }
+}
+
+// Splits a note into separate requests ("Invoice Dave 400 for the deck, and
+// remind me to order lumber Friday") and parses each one.
+pub fn parse_all(transcript: &str, now: NaiveDateTime) -> Vec<ParsedIntent> {
+    split_requests(&normalize(transcript))
+        .into_iter()
+        .map(|request| parse(request, now))
+        .collect()
+}
+
+// Breaks at sentence ends, and at "and"/"then"/"also" or a comma when what
+// follows starts a new request. A bare "and" is not enough: "call the
+// inspector and the plumber" is one task.
+fn split_requests(text: &str) -> Vec<&str> {
+    let next_request = regex!(
+        r"(?i)(?:,\s*(?:(?:and|then|also)\s+)*|\s+(?:and|then|also)\s+(?:(?:then|also)\s+)?)(?P<start>(?:please\s+)?(?:invoice|bill|charge|quote|remind|schedule|book|set up|order|call|pick up|don't forget|remember to|make sure|i need to|we need to|need to|i have to)\b)"
+    );
+    let mut requests = Vec::new();
+    for sentence in regex!(r"[.!?;]+(?:\s+|$)").split(text) {
+        let mut start = 0;
+        for caps in next_request.captures_iter(sentence) {
+            let boundary = caps.get(0).unwrap();
+            requests.push(&sentence[start..boundary.start()]);
+            start = caps.name("start").unwrap().start();
+        }
+        requests.push(&sentence[start..]);
+    }
+    let joiner = regex!(r"(?i)^(?:(?:and|then|also|so)\b\s*)+");
+    requests
+        .into_iter()
+        .map(|r| {
+            let r = r.trim().trim_end_matches(',');
+            &r[joiner.find(r).map_or(0, |m| m.end())..]
+        })
+        .filter(|r| !r.is_empty())
+        .collect()
+}
+
+fn normalize(transcript: &str) -> String {
+    let text = transcript.split_whitespace().collect::<Vec<_>>().join(" ");
+    // "a.m." would otherwise read as a sentence end.
+    regex!(r"(?i)\b([ap])\.m\.")
+        .replace_all(&text, "${1}m")
+        .trim_end_matches(['.', '!', '?'])
+        .to_string()
+}
+
+fn classify(text: &str, lower: &str, today: NaiveDate) -> Intent {
+    // "Remind me to invoice Dave" is a task about an invoice, not an invoice.
+    if regex!(
+        r"^(?:please\s+)?(?:remind|reminder|add a task|task|to-?do|don't forget|remember to)\b"
+    )
+    .is_match(lower)
+    {
+        return Intent::Task;
+    }
+    if let Some(intent) = leading_command(lower) {
+        return intent;
+    }
+    // No command up front ("Dave Miller, kitchen quote, 4000"): the first
+    // document mentioned decides.
+    if let Some(caps) =
+        regex!(r"\b(?:(?P<invoice>invoice|bill|charge|billing)|quote|estimate|quotation|bid)\b")
+            .captures(lower)
+    {
+        return if caps.name("invoice").is_some() {
+            Intent::Invoice
+        } else {
+            Intent::Estimate
+        };
+    }
+    if regex!(r"\b(?:contact|phone number|number is|cell is|cell number)\b").is_match(lower)
+        || find_phone(text).is_some()
+    {
+        return Intent::Contact;
+    }
+    if regex!(r"\b(?:appointment|meeting|meet with|meet up|site visit|walkthrough|walk-through|consultation|book|schedule)\b")
+        .is_match(lower)
+    {
+        return Intent::Calendar;
+    }
+    // "Pick up lumber tomorrow morning" and "call the inspector at 3" are
+    // things to do at a time, not events.
+    if regex!(r"\b(?:remind|task|to-?do|need to|have to|don't forget|remember to|make sure|pick up|order|call)\b")
+        .is_match(lower)
+    {
+        return Intent::Task;
+    }
+    if find_time(lower).is_some() {
+        return Intent::Calendar;
+    }
+    if find_amount(text, false).is_some() {
+        return Intent::Invoice;
+    }
+    // Anything with a due date is most likely something to do.
+    if find_date(lower, today).is_some() {
+        return Intent::Task;
+    }
+    Intent::Unknown
+}
+
+// What the request opens with, for invoices and quotes: "invoice the
+// Hendersons 12k as per the estimate" is an invoice, "quote Dave 4000 for the
+// kitchen, payable on invoice" a quote, whatever else is mentioned later.
+fn leading_command(lower: &str) -> Option<Intent> {
+    let command = regex!(
+        r"^(?:(?:please|ok|okay|so|can you|could you|go ahead and|i want to|i'd like to|let's)\s+)*(?P<verb>invoice|bill|charge|quote|estimate|give|send|write|make|draw up|prepare|get)\b(?P<rest>.*)"
+    );
+    let caps = command.captures(lower)?;
+    match &caps["verb"] {
+        "invoice" | "bill" | "charge" => Some(Intent::Invoice),
+        "quote" | "estimate" => Some(Intent::Estimate),
+        // "give Dave a quote", "send the Hendersons an invoice"
+        _ => {
+            let document =
+                regex!(r"\ban?\s+(?:(?P<invoice>invoice|bill)|quote|estimate|quotation|bid)\b")
+                    .captures(&caps["rest"])?;
+            Some(if document.name("invoice").is_some() {
+                Intent::Invoice
+            } else {
+                Intent::Estimate
+            })
+        }
+    }
+}
+
+// --- INVOICE ---
+
+fn parse_invoice(text: &str) -> ParsedIntent {
+    let (amount, amount_span) = match find_amount(text, true) {
+        Some((amount, span)) => (Some(amount), Some(span)),
+        None => (None, None),
+    };
+    ParsedIntent::Invoice {
+        client: find_client(text),
+        amount,
+        description: find_work_description(text, amount_span),
+    }
+}
+
+// --- ESTIMATE ---
+
+// Same fields as an invoice: "Quote Dave 4000 for the kitchen", "give the
+// Hendersons an estimate of 12k for the roof".
+fn parse_estimate(text: &str) -> ParsedIntent {
+    let (amount, amount_span) = match find_amount(text, true) {
+        Some((amount, span)) => (Some(amount), Some(span)),
+        None => (None, None),
+    };
+    ParsedIntent::Estimate {
+        client: find_client(text),
+        amount,
+        description: find_work_description(text, amount_span),
+    }
+}
+
+fn find_client(text: &str) -> Option<String> {
+    let after_keyword = regex!(
+        r"(?i:\b(?:invoice|bill|charge|quote|estimate|bid)\b)\s+(?:(?i:to|for)\s+)?(?:(?i:the)\s+)?(?P<name>[A-Z][\w'.&-]*(?:\s+[A-Z][\w'.&-]*)*)"
+    );
+    // "give Dave a quote", "send the Hendersons an estimate"
+    let before_quote = regex!(
+        r"(?i:\b(?:give|send|get|write)\b)\s+(?:(?i:the)\s+)?(?P<name>[A-Z][\w'.&-]*(?:\s+[A-Z][\w'.&-]*)*)\s+(?i:an?\s+(?:quote|estimate|bid|quotation)\b)"
+    );
+    let after_for =
+        regex!(r"\b(?:for|to)\s+(?:the\s+)?(?P<name>[A-Z][\w'.&-]*(?:\s+[A-Z][\w'.&-]*)*)");
+    after_keyword
+        .captures(text)
+        .or_else(|| before_quote.captures(text))
+        .or_else(|| after_for.captures(text))
+        .map(|caps| clean_name(&caps["name"]))
+        .filter(|name| !name.is_empty() && name != "I")
+}
+
+// What the work was: the "for ..." phrase after the amount, or failing that
+// anywhere in the sentence after the client.
+fn find_work_description(text: &str, amount_span: Option<Range<usize>>) -> Option<String> {
+    let search_from = amount_span.map(|span| span.end).unwrap_or(0);
+    let tail = &text[search_from..];
+    let for_phrase = regex!(r"(?i)\bfor\s+(?:the\s+)?(?P<desc>[^,.;]+)");
+    for_phrase
+        .captures(tail)
+        .map(|caps| {
+            // "... for roofing to Bob" -> "roofing"
+            let desc = &caps["desc"];
+            let end = regex!(r"\s+(?:to|for)\s+[A-Z]")
+                .find(desc)
+                .map(|m| m.start())
+                .unwrap_or(desc.len());
+            desc[..end].trim().to_string()
+        })
+        .filter(|desc| !desc.is_empty() && !desc.chars().next().unwrap().is_uppercase())
+        .map(|desc| capitalize(&desc))
+}
+
+// --- TASK ---
+
+fn parse_task(text: &str, today: NaiveDate) -> ParsedIntent {
+    let lower = text.to_ascii_lowercase();
+    let due = find_date(&lower, today);
+    let mut description = text.to_string();
+    if let Some((_, span)) = &due {
+        description.replace_range(span.clone(), "");
+    }
+    let prefix = regex!(
+        r"(?i)^(?:please\s+)?(?:remind me to|remind me|reminder to|reminder|add a task to|add task|task:?|to-?do:?|i need to|we need to|need to|i have to|we have to|don't forget to|remember to|make sure to)\s+"
+    );
+    let description = prefix.replace(description.trim(), "");
+    let description = description
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches([',', ' '])
+        .to_string();
+    ParsedIntent::Task {
+        description: Some(capitalize(&description)).filter(|d| !d.is_empty()),
+        due_date: due.map(|(date, _)| date.format("%Y-%m-%d").to_string()),
+    }
+}
+
+// --- CALENDAR ---
+
+fn parse_calendar(text: &str, now: NaiveDateTime) -> ParsedIntent {
+    let lower = text.to_ascii_lowercase();
+    let date = find_date(&lower, now.date());
+    let time = find_time(&lower);
+    let duration = find_duration(&lower);
+
+    // A date without a time starts at 8am; a bare time that has already
+    // passed today means tomorrow.
+    let start = match (&date, &time) {
+        (Some((date, _)), Some((time, _))) => Some(date.and_time(*time)),
+        (Some((date, _)), None) => date.and_hms_opt(8, 0, 0),
+        (None, Some((time, _))) => {
+            let today = now.date().and_time(*time);
+            Some(if today < now {
+                today + Duration::days(1)
+            } else {
+                today
+            })
+        }
+        (None, None) => None,
+    };
+    let start_time = start
+        .and_then(|start| Local.from_local_datetime(&start).earliest())
+        .map(|start| start.to_rfc3339());
+
+    let spans: Vec<Range<usize>> = [
+        date.map(|(_, span)| span),
+        time.map(|(_, span)| span),
+        duration.as_ref().map(|(_, span)| span.clone()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let title = remove_spans(text, spans);
+    let prefix = regex!(
+        r"(?i)^(?:please\s+)?(?:schedule|book|set up|add|put|create|make)\s+(?:in\s+)?(?:an?\s+|the\s+)?"
+    );
+    let title = prefix.replace(title.trim(), "");
+    let title = regex!(r"(?i)\s+(?:on|in|to)\s+(?:my|the)\s+calendar\b").replace(&title, "");
+    let title = title
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches([',', ' '])
+        .to_string();
+
+    ParsedIntent::Calendar {
+        title: Some(capitalize(&title)).filter(|t| !t.is_empty()),
+        start_time,
+        duration_minutes: duration.map(|(minutes, _)| minutes),
+        contact: find_attendee(text),
+    }
+}
+
+// Clock times: "8am", "at 3:30 pm", "at 8", "noon", "Monday morning".
+// A bare "at N" follows working hours: 7-11 is morning, 1-6 afternoon.
+fn find_time(lower: &str) -> Option<(NaiveTime, Range<usize>)> {
+    let explicit = regex!(
+        r"\b(?:(?:at|for|by)\s+)?(?P<h>\d{1,2})(?:[:\s](?P<m>[0-5]\d))?\s*(?P<ampm>am|pm)\b"
+    );
+    if let Some(caps) = explicit.captures(lower) {
+        let mut hour: u32 = caps["h"].parse().ok()?;
+        let minute: u32 = caps
+            .name("m")
+            .map_or(Some(0), |m| m.as_str().parse().ok())?;
+        if hour == 0 || hour > 12 {
+            return None;
+        }
+        hour %= 12;
+        if &caps["ampm"] == "pm" {
+            hour += 12;
+        }
+        return Some((
+            NaiveTime::from_hms_opt(hour, minute, 0)?,
+            caps.get(0).unwrap().range(),
+        ));
+    }
+
+    let named = regex!(r"\b(?:at\s+)?(?P<word>noon|midday|midnight)\b");
+    if let Some(caps) = named.captures(lower) {
+        let hour = if &caps["word"] == "midnight" { 0 } else { 12 };
+        return Some((
+            NaiveTime::from_hms_opt(hour, 0, 0)?,
+            caps.get(0).unwrap().range(),
+        ));
+    }
+
+    let bare = regex!(
+        r"\bat\s+(?P<h>\d{1,2})(?:[:\s](?P<m>[0-5]\d))?(?:\s+o'clock)?(?:\s+in\s+the\s+(?P<part>morning|afternoon|evening))?\b"
+    );
+    if let Some(caps) = bare.captures(lower) {
+        let mut hour: u32 = caps["h"].parse().ok()?;
+        let minute: u32 = caps
+            .name("m")
+            .map_or(Some(0), |m| m.as_str().parse().ok())?;
+        if hour > 23 {
+            return None;
+        }
+        let afternoon = match caps.name("part").map(|m| m.as_str()) {
+            Some("morning") => false,
+            Some(_) => true,
+            None => (1..=6).contains(&hour),
+        };
+        if afternoon && hour < 12 {
+            hour += 12;
+        }
+        return Some((
+            NaiveTime::from_hms_opt(hour, minute, 0)?,
+            caps.get(0).unwrap().range(),
+        ));
+    }
+
+    let part_of_day =
+        regex!(r"\b(?:in\s+the\s+|this\s+)?(?P<part>morning|afternoon|evening|tonight)\b");
+    if let Some(caps) = part_of_day.captures(lower) {
+        let hour = match &caps["part"] {
+            "morning" => 8,
+            "afternoon" => 13,
+            _ => 18,
+        };
+        return Some((
+            NaiveTime::from_hms_opt(hour, 0, 0)?,
+            caps.get(0).unwrap().range(),
+        ));
+    }
+    None
+}
+
+// "for an hour", "for 45 minutes", "for 2 and a half hours", "for half an hour".
+fn find_duration(lower: &str) -> Option<(i64, Range<usize>)> {
+    let duration = regex!(
+        r"\bfor\s+(?:(?P<half>half an?)|(?P<n>\d+(?:\.\d+)?)|an?)\s+(?:and\s+a\s+half\s+)?(?P<unit>hours?|hrs?|minutes?|mins?)(?P<and_half>\s+and\s+a\s+half)?\b"
+    );
+    let caps = duration.captures(lower)?;
+    let n: f64 = if caps.name("half").is_some() {
+        0.5
+    } else {
+        caps.name("n")
+            .map_or(Some(1.0), |n| n.as_str().parse().ok())?
+    };
+    let whole = caps.get(0).unwrap();
+    let n = if whole.as_str().contains("and a half") {
+        n + 0.5
+    } else {
+        n
+    };
+    let minutes = if caps["unit"].starts_with('h') {
+        n * 60.0
+    } else {
+        n
+    };
+    Some((minutes.round() as i64, whole.range()))
+}
+
+// The person the appointment is with: "meeting with Dave Miller".
+fn find_attendee(text: &str) -> Option<String> {
+    let with = regex!(r"\bwith\s+(?:the\s+)?(?P<name>[A-Z][\w'.-]*(?:\s+[A-Z][\w'.-]*){0,3})");
+    let caps = with.captures(text)?;
+    // Stop at a capitalized date word ("with Dave Monday").
+    let name: Vec<&str> = caps["name"]
+        .split_whitespace()
+        .take_while(|word| {
+            let lower = word.to_ascii_lowercase();
+            parse_weekday(&lower).is_none()
+                && parse_month(&lower).is_none()
+                && !matches!(
+                    lower.as_str(),
+                    "tomorrow" | "today" | "tonight" | "next" | "at" | "on"
+                )
+        })
+        .collect();
+    Some(clean_name(&name.join(" "))).filter(|name| !name.is_empty())
+}
+
+// --- CONTACT ---
+
+fn parse_contact(text: &str) -> ParsedIntent {
+    let name_after_verb = regex!(
+        r"(?i:\b(?:contact|add|save|new|number for|phone for|call)\b)\s+(?:(?i:contact|a contact|new contact)\s+)?(?:(?i:for)\s+)?(?P<name>[A-Z][\w'.-]*(?:\s+[A-Z][\w'.-]*){0,3})"
+    );
+    let possessive = regex!(
+        r"(?P<name>[A-Z][\w'.-]*(?:\s+[A-Z][\w'.-]*){0,3})(?:'s|s')\s+(?:(?i)number|phone|cell)"
+    );
+    let name = name_after_verb
+        .captures(text)
+        .or_else(|| possessive.captures(text))
+        .map(|caps| clean_name(&caps["name"]));
+    let company = regex!(
+        r"\b(?:from|at|with|of)\s+(?:the\s+)?(?P<company>[A-Z][\w&'.-]*(?:\s+(?:[A-Z][\w&'.-]*|&))*)"
+    )
+    .captures(text)
+    .map(|caps| clean_name(&caps["company"]))
+    .filter(|company| Some(company) != name.as_ref());
+    ParsedIntent::Contact {
+        name,
+        phone: find_phone(text).map(|(phone, _)| phone),
+        company,
+    }
+}
+
+// --- FIELD EXTRACTORS ---
+
+// Finds a money amount. With `loose`, a bare number also counts (e.g.
+// "Invoice Dave 400 for the deck"), unless it looks like a date or time.
+fn find_amount(text: &str, loose: bool) -> Option<(Money, Range<usize>)> {
+    let explicit = regex!(
+        r"(?i)\$\s?(?P<value>\d[\d,]*(?:\.\d{1,2})?)\s*(?P<scale>k\b|thousand|grand)?|(?P<value2>\d[\d,]*(?:\.\d{1,2})?)\s*(?P<scale2>k\b|thousand|grand)?\s*(?:dollars|bucks|usd)\b"
+    );
+    if let Some(caps) = explicit.captures(text) {
+        let value = caps.name("value").or_else(|| caps.name("value2"))?;
+        let scale = caps.name("scale").or_else(|| caps.name("scale2"));
+        let span = caps.get(0).unwrap().range();
+        return scaled_amount(value.as_str(), scale.map(|m| m.as_str()))
+            .map(|amount| (amount, span));
+    }
+    if !loose {
+        return None;
+    }
+    let bare = regex!(
+        r"(?i)\b(?P<value>\d[\d,]*(?:\.\d{1,2})?)\s*(?P<scale>k\b|thousand|grand)?(?P<after>\s*(?:st|nd|rd|th|am|pm|a\.m\.|p\.m\.|o'clock|:\d\d|%|percent|days?|weeks?|hours?|minutes?)\b)?"
+    );
+    // Digits that belong to a phone number, or sit against '-', '(' or ')'
+    // like its parts do ("555-123-4567", "(555)"), are not amounts.
+    let phones: Vec<Range<usize>> = phones(text).map(|(_, span)| span).collect();
+    let joined = |c: char| matches!(c, '-' | '(' | ')');
+    bare.captures_iter(text)
+        .filter(|caps| caps.name("after").is_none())
+        .filter(|caps| {
+            let value = caps.name("value").unwrap();
+            let digits = value
+                .as_str()
+                .chars()
+                .filter(|c| c.is_ascii_digit())
+                .count();
+            digits < 7
+                && !phones
+                    .iter()
+                    .any(|phone| phone.start < value.end() && value.start() < phone.end)
+                && !text[..value.start()].ends_with(joined)
+                && !text[value.end()..].starts_with(joined)
+        })
+        .find_map(|caps| {
+            let span = caps.get(0).unwrap().range();
+            scaled_amount(&caps["value"], caps.name("scale").map(|m| m.as_str()))
+                .map(|amount| (amount, span))
+        })
+}
+
+fn scaled_amount(value: &str, scale: Option<&str>) -> Option<Money> {
+    let amount = Money::parse(value).ok()?;
+    match scale.map(|s| s.to_lowercase()) {
+        Some(_) => Some(amount.times(1000.0)),
+        None => Some(amount),
+    }
+}
+
+fn find_phone(text: &str) -> Option<(String, Range<usize>)> {
+    phones(text).next()
+}
+
+// Phone numbers in `text`, formatted, with their spans. ISO dates
+// ("2026-10-20") are masked out first so they are not read as one.
+fn phones(text: &str) -> impl Iterator<Item = (String, Range<usize>)> + '_ {
+    let masked = regex!(r"\b\d{4}-\d{2}-\d{2}\b")
+        .replace_all(text, |caps: &regex::Captures| "x".repeat(caps[0].len()));
+    let candidate = regex!(r"\+?\(?\d[\d\s().-]{5,}\d");
+    let spans: Vec<Range<usize>> = candidate.find_iter(&masked).map(|m| m.range()).collect();
+    spans.into_iter().filter_map(move |span| {
+        let raw = &text[span.clone()];
+        let digits: String = raw.chars().filter(|c| c.is_ascii_digit()).collect();
+        if !(7..=15).contains(&digits.len()) {
+            return None;
+        }
+        let formatted = match digits.len() {
+            7 => format!("{}-{}", &digits[..3], &digits[3..]),
+            10 => format!("{}-{}-{}", &digits[..3], &digits[3..6], &digits[6..]),
+            _ if raw.starts_with('+') => format!("+{}", digits),
+            _ => digits,
+        };
+        Some((formatted, span))
+    })
+}
+
+// Finds a due date in lowercase text, returning it with the matched span so
+// callers can cut the phrase out of a description.
+pub fn find_date(lower: &str, today: NaiveDate) -> Option<(NaiveDate, Range<usize>)> {
+    let relative_day = regex!(
+        r"\b(?:(?:on|by|for|before|until)\s+)?(?:the\s+)?(?P<word>day after tomorrow|tomorrow|today|tonight)\b"
+    );
+    if let Some(caps) = relative_day.captures(lower) {
+        let days = match &caps["word"] {
+            "day after tomorrow" => 2,
+            "tomorrow" => 1,
+            _ => 0,
+        };
+        return Some((today + Duration::days(days), caps.get(0).unwrap().range()));
+    }
+
+    let in_n =
+        regex!(r"\b(?:in|within)\s+(?P<n>\d+|a|an)\s+(?P<unit>day|days|week|weeks|month|months)\b");
+    if let Some(caps) = in_n.captures(lower) {
+        let n: i64 = caps["n"].parse().unwrap_or(1);
+        let date = match &caps["unit"] {
+            "day" | "days" => today + Duration::days(n),
+            "week" | "weeks" => today + Duration::weeks(n),
+            _ => add_months(today, n as u32),
+        };
+        return Some((date, caps.get(0).unwrap().range()));
+    }
+
+    let end_of_week = regex!(r"\b(?:(?:by|before|at)\s+)?(?:the\s+)?end of (?:the|this)\s+week\b");
+    if let Some(m) = end_of_week.find(lower) {
+        return Some((upcoming(today, Weekday::Fri, true), m.range()));
+    }
+
+    let next_week = regex!(r"\b(?:(?:by|for)\s+)?next week\b");
+    if let Some(m) = next_week.find(lower) {
+        return Some((start_of_next_week(today), m.range()));
+    }
+
+    let weekday = regex!(
+        r"\b(?:(?:on|by|for|before|until)\s+)?(?P<which>next|this|coming)?\s*(?P<day>monday|tuesday|wednesday|thursday|friday|saturday|sunday)\b"
+    );
+    if let Some(caps) = weekday.captures(lower) {
+        let day = parse_weekday(&caps["day"])?;
+        let date = match caps.name("which").map(|m| m.as_str()) {
+            Some("next") => {
+                start_of_next_week(today) + Duration::days(day.num_days_from_monday() as i64)
+            }
+            Some(_) => upcoming(today, day, true),
+            None => upcoming(today, day, false),
+        };
+        return Some((date, caps.get(0).unwrap().range()));
+    }
+
+    let iso = regex!(r"\b(?P<y>\d{4})-(?P<m>\d{2})-(?P<d>\d{2})\b");
+    if let Some(caps) = iso.captures(lower) {
+        let date = NaiveDate::from_ymd_opt(
+            caps["y"].parse().ok()?,
+            caps["m"].parse().ok()?,
+            caps["d"].parse().ok()?,
+        )?;
+        return Some((date, caps.get(0).unwrap().range()));
+    }
+
+    let month_day = regex!(
+        r"\b(?:(?:on|by|for|before|until)\s+)?(?:(?P<month>jan(?:uary)?|feb(?:ruary)?|mar(?:ch)?|apr(?:il)?|may|june?|july?|aug(?:ust)?|sept?(?:ember)?|oct(?:ober)?|nov(?:ember)?|dec(?:ember)?)\s+(?:the\s+)?(?P<day>\d{1,2})(?:st|nd|rd|th)?|(?:the\s+)?(?P<day2>\d{1,2})(?:st|nd|rd|th)?\s+of\s+(?P<month2>jan(?:uary)?|feb(?:ruary)?|mar(?:ch)?|apr(?:il)?|may|june?|july?|aug(?:ust)?|sept?(?:ember)?|oct(?:ober)?|nov(?:ember)?|dec(?:ember)?))\b"
+    );
+    if let Some(caps) = month_day.captures(lower) {
+        let month = caps.name("month").or_else(|| caps.name("month2"))?;
+        let day = caps.name("day").or_else(|| caps.name("day2"))?;
+        let month = parse_month(month.as_str())?;
+        let day: u32 = day.as_str().parse().ok()?;
+        let mut date = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+        if date < today {
+            date = NaiveDate::from_ymd_opt(today.year() + 1, month, day)?;
+        }
+        return Some((date, caps.get(0).unwrap().range()));
+    }
+
+    None
+}
+
+fn parse_month(name: &str) -> Option<u32> {
+    let months = [
+        "january",
+        "february",
+        "march",
+        "april",
+        "may",
+        "june",
+        "july",
+        "august",
+        "september",
+        "october",
+        "november",
+        "december",
+    ];
+    months
+        .iter()
+        .position(|m| name.len() >= 3 && m.starts_with(name))
+        .map(|i| i as u32 + 1)
+}
+
+fn parse_weekday(name: &str) -> Option<Weekday> {
+    name.parse().ok()
+}
+
+// Next occurrence of `day`; `include_today` decides whether today counts.
+fn upcoming(today: NaiveDate, day: Weekday, include_today: bool) -> NaiveDate {
+    let ahead = (day.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64)
+        .rem_euclid(7);
+    let ahead = if ahead == 0 && !include_today {
+        7
+    } else {
+        ahead
+    };
+    today + Duration::days(ahead)
+}
+
+fn start_of_next_week(today: NaiveDate) -> NaiveDate {
+    today + Duration::days(7 - today.weekday().num_days_from_monday() as i64)
+}
+
+fn add_months(date: NaiveDate, months: u32) -> NaiveDate {
+    date.checked_add_months(chrono::Months::new(months))
+        .unwrap_or(date)
+}
+
+// --- TEXT HELPERS ---
+
+// Cuts possibly overlapping byte ranges out of `text`.
+fn remove_spans(text: &str, mut spans: Vec<Range<usize>>) -> String {
+    spans.sort_by_key(|span| span.start);
+    let mut out = String::new();
+    let mut pos = 0;
+    for span in spans {
+        if span.start > pos {
+            out.push_str(&text[pos..span.start]);
+        }
+        pos = pos.max(span.end);
+    }
+    out.push_str(&text[pos.min(text.len())..]);
+    out
+}
+
+fn clean_name(raw: &str) -> String {
+    const TRAILING: [&str; 6] = ["'s", "s'", ".", ",", "'", "-"];
+    let mut name = raw.trim().to_string();
+    while let Some(suffix) = TRAILING.iter().find(|s| name.ends_with(*s)) {
+        name.truncate(name.len() - suffix.len());
+    }
+    name
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// Rewrites spelled-out numbers as digits ("four hundred and fifty" -> "450")
+// so the extractors above only need to handle digits. Runs of single digits
+// ("five five five one two") become separate numbers, which phone detection
+// then joins back up.
+pub fn spell_out_numbers(text: &str) -> String {
+    let words: Vec<&str> = text.split(' ').collect();
+    let mut out: Vec<String> = Vec::new();
+    let mut current: Option<SpokenNumber> = None;
+    let mut i = 0;
+
+    while i < words.len() {
+        let word = words[i];
+        let core = word.trim_end_matches([',', '.', '?', '!', ';', ':']);
+        let punctuation = &word[core.len()..];
+        let lw = core.to_lowercase();
+        let next = words.get(i + 1).map(|w| w.to_lowercase());
+
+        let value = number_word(&lw)
+            .filter(|_| lw != "one" || current.is_some() || one_is_number(next.as_deref()));
+        let consumed = if let Some(value) = value {
+            match current.as_mut() {
+                Some(n) if n.accepts(value) => n.push(value),
+                _ => {
+                    flush(&mut out, &mut current);
+                    current = Some(SpokenNumber::new(value));
+                }
+            }
+            true
+        } else if lw == "hundred" || lw == "thousand" {
+            match current.as_mut() {
+                Some(n) => n.scale(&lw),
+                None => {
+                    let mut n = SpokenNumber::new(1);
+                    n.scale(&lw);
+                    current = Some(n);
+                }
+            }
+            true
+        } else if lw == "a" && matches!(next.as_deref(), Some("hundred") | Some("thousand")) {
+            flush(&mut out, &mut current);
+            current = Some(SpokenNumber::new(1));
+            true
+        } else {
+            // "four hundred and fifty": the "and" belongs to the number.
+            lw == "and"
+                && current.as_ref().is_some_and(|n| n.after_scale)
+                && next.as_deref().and_then(number_word).is_some()
+        };
+
+        if consumed {
+            if !punctuation.is_empty() {
+                flush(&mut out, &mut current);
+                if let Some(last) = out.last_mut() {
+                    last.push_str(punctuation);
+                }
+            }
+        } else {
+            flush(&mut out, &mut current);
+            out.push(word.to_string());
+        }
+        i += 1;
+    }
+    flush(&mut out, &mut current);
+    out.join(" ")
+}
+
+struct SpokenNumber {
+    total: i64,
+    group: i64,
+    last: i64,
+    after_scale: bool,
+}
+
+impl SpokenNumber {
+    fn new(value: i64) -> Self {
+        SpokenNumber {
+            total: 0,
+            group: value,
+            last: value,
+            after_scale: false,
+        }
+    }
+
+    // "twenty" + "five" and "hundred" + "six" combine; "five" + "five" does not.
+    fn accepts(&self, value: i64) -> bool {
+        if self.after_scale {
+            return true;
+        }
+        value < 10 && self.last >= 20 && self.last % 10 == 0 && self.last < 100
+    }
+
+    fn push(&mut self, value: i64) {
+        self.group += value;
+        self.last = value;
+        self.after_scale = false;
+    }
+
+    fn scale(&mut self, word: &str) {
+        if word == "hundred" {
+            self.group *= 100;
+            self.last = 100;
+        } else {
+            self.total += self.group.max(1) * 1000;
+            self.group = 0;
+            self.last = 1000;
+        }
+        self.after_scale = true;
+    }
+
+    fn value(&self) -> i64 {
+        self.total + self.group
+    }
+}
+
+fn flush(out: &mut Vec<String>, current: &mut Option<SpokenNumber>) {
+    if let Some(n) = current.take() {
+        out.push(n.value().to_string());
+    }
+}
+
+// "one" is usually a pronoun ("one of the cabinets"), so on its own it only
+// counts before a magnitude, a currency or another number ("one hundred",
+// "one grand", "one dollar", "one five five five ...").
+fn one_is_number(next: Option<&str>) -> bool {
+    let Some(next) = next.map(|word| word.trim_end_matches([',', '.', '?', '!', ';', ':'])) else {
+        return false;
+    };
+    matches!(
+        next,
+        "hundred" | "thousand" | "grand" | "k" | "dollar" | "dollars" | "buck" | "bucks"
+    ) || number_word(next).is_some()
+}
+
+fn number_word(word: &str) -> Option<i64> {
+    let value = match word {
+        "zero" => 0,
+        "one" => 1,
+        "two" => 2,
+        "three" => 3,
+        "four" => 4,
+        "five" => 5,
+        "six" => 6,
+        "seven" => 7,
+        "eight" => 8,
+        "nine" => 9,
+        "ten" => 10,
+        "eleven" => 11,
+        "twelve" => 12,
+        "thirteen" => 13,
+        "fourteen" => 14,
+        "fifteen" => 15,
+        "sixteen" => 16,
+        "seventeen" => 17,
+        "eighteen" => 18,
+        "nineteen" => 19,
+        "twenty" => 20,
+        "thirty" => 30,
+        "forty" => 40,
+        "fifty" => 50,
+        "sixty" => 60,
+        "seventy" => 70,
+        "eighty" => 80,
+        "ninety" => 90,
+        _ => return None,
+    };
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Saturday.
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 17)
+            .unwrap()
+            .and_hms_opt(9, 30, 0)
+            .unwrap()
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn invoice(client: &str, cents: i64, description: &str) -> ParsedIntent {
+        ParsedIntent::Invoice {
+            client: Some(client.to_string()),
+            amount: Some(Money::from_cents(cents)),
+            description: Some(description.to_string()),
+        }
+    }
+
+    fn amount(transcript: &str) -> Option<Money> {
+        match parse(transcript, now()) {
+            ParsedIntent::Invoice { amount, .. } | ParsedIntent::Estimate { amount, .. } => amount,
+            other => panic!("{:?} parsed as {:?}", transcript, other),
+        }
+    }
+
+    #[test]
+    fn amounts() {
+        assert_eq!(
+            parse("Invoice Dave 400 for the deck", now()),
+            invoice("Dave", 40000, "Deck")
+        );
+        assert_eq!(
+            amount("Bill the Hendersons $12,500.50 for the roof"),
+            Some(Money::from_cents(1250050))
+        );
+        assert_eq!(
+            amount("Invoice Sarah 12k for the kitchen"),
+            Some(Money::from_cents(1200000))
+        );
+        assert_eq!(
+            amount("Invoice Sarah four hundred and fifty dollars for paint"),
+            Some(Money::from_cents(45000))
+        );
+        assert_eq!(
+            amount("Invoice Dave two grand for framing"),
+            Some(Money::from_cents(200000))
+        );
+        assert_eq!(amount("Invoice Dave for the deck"), None);
+    }
+
+    #[test]
+    fn one_and_oh_are_not_amounts() {
+        assert_eq!(
+            amount("Invoice Dave for one of the cabinets and 400 for paint"),
+            Some(Money::from_cents(40000))
+        );
+        assert_eq!(
+            amount("Invoice Dave, oh and 400 for paint"),
+            Some(Money::from_cents(40000))
+        );
+        assert_eq!(
+            amount("Invoice Dave one hundred for the gate"),
+            Some(Money::from_cents(10000))
+        );
+        assert_eq!(
+            spell_out_numbers("one of the cabinets"),
+            "one of the cabinets"
+        );
+        assert_eq!(spell_out_numbers("twenty one dollars"), "21 dollars");
+        assert_eq!(spell_out_numbers("one dollar"), "1 dollar");
+    }
+
+    #[test]
+    fn dates() {
+        let today = now().date();
+        let due = |text: &str| find_date(text, today).map(|(date, _)| date);
+        assert_eq!(due("by next tuesday"), Some(date(2026, 10, 20)));
+        assert_eq!(due("on tuesday"), Some(date(2026, 10, 20)));
+        assert_eq!(due("saturday"), Some(date(2026, 10, 24)));
+        assert_eq!(due("this saturday"), Some(date(2026, 10, 17)));
+        assert_eq!(due("tomorrow"), Some(date(2026, 10, 18)));
+        assert_eq!(due("in 2 weeks"), Some(date(2026, 10, 31)));
+        assert_eq!(due("by the end of the week"), Some(date(2026, 10, 23)));
+        assert_eq!(due("march 3rd"), Some(date(2027, 3, 3)));
+        assert_eq!(due("the 5th of november"), Some(date(2026, 11, 5)));
+        assert_eq!(due("whenever"), None);
+
+        assert_eq!(
+            parse("Remind me to order lumber next Tuesday", now()),
+            ParsedIntent::Task {
+                description: Some("Order lumber".to_string()),
+                due_date: Some("2026-10-20".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn phone_numbers() {
+        assert_eq!(
+            parse("Add contact Mike Ross 555 123 4567", now()),
+            ParsedIntent::Contact {
+                name: Some("Mike Ross".to_string()),
+                phone: Some("555-123-4567".to_string()),
+                company: None,
+            }
+        );
+        let spoken = parse(
+            "Save Mike's number five five five one two three four five six seven",
+            now(),
+        );
+        assert!(
+            matches!(&spoken, ParsedIntent::Contact { phone: Some(phone), .. } if phone == "555-123-4567"),
+            "{:?}",
+            spoken
+        );
+        assert_eq!(
+            find_phone("+44 20 7946 0958").map(|(phone, _)| phone),
+            Some("+442079460958".to_string())
+        );
+        assert_eq!(find_phone("400 for the deck"), None);
+        assert_eq!(find_phone("pour the slab 2026-10-20"), None);
+        assert_eq!(
+            find_phone("due 2026-10-20, call 555-123-4567").map(|(phone, _)| phone),
+            Some("555-123-4567".to_string())
+        );
+        assert_eq!(intent("Pour the slab 2026-10-20"), Intent::Task);
+    }
+
+    #[test]
+    fn phone_digits_are_not_amounts() {
+        assert_eq!(
+            parse(
+                "Invoice Dave, call him on 555-123-4567, 400 for the deck",
+                now()
+            ),
+            ParsedIntent::Invoice {
+                client: Some("Dave".to_string()),
+                amount: Some(Money::from_cents(40000)),
+                description: Some("Deck".to_string()),
+            }
+        );
+        assert_eq!(find_amount("call (555) 123-4567", true), None);
+        assert_eq!(
+            find_amount("ring 555-1234 about the 250", true).map(|(amount, _)| amount),
+            Some(Money::from_cents(25000))
+        );
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(
+            find_client("Invoice the Hendersons 12k for the roof"),
+            Some("Hendersons".to_string())
+        );
+        assert_eq!(
+            find_client("give Dave Miller a quote for 3000"),
+            Some("Dave Miller".to_string())
+        );
+        assert_eq!(
+            find_client("send 400 to Acme Builders for the deck"),
+            Some("Acme Builders".to_string())
+        );
+        assert_eq!(find_client("invoice 400 for the deck"), None);
+        assert_eq!(
+            find_attendee("Meeting with Dave Miller Monday at 3"),
+            Some("Dave Miller".to_string())
+        );
+    }
+
+    #[test]
+    fn splits_multiple_requests() {
+        let text = "Invoice Dave 400 for the deck, and remind me to order lumber Friday. Call the inspector and the plumber";
+        assert_eq!(
+            split_requests(text),
+            vec![
+                "Invoice Dave 400 for the deck",
+                "remind me to order lumber Friday",
+                "Call the inspector and the plumber",
+            ]
+        );
+        let parsed = parse_all(text, now());
+        assert_eq!(parsed[0], invoice("Dave", 40000, "Deck"));
+        assert_eq!(
+            parsed[1],
+            ParsedIntent::Task {
+                description: Some("Order lumber".to_string()),
+                due_date: Some("2026-10-23".to_string()),
+            }
+        );
+        assert!(
+            matches!(parsed[2], ParsedIntent::Task { .. }),
+            "{:?}",
+            parsed[2]
+        );
+    }
+
+    fn intent(transcript: &str) -> Intent {
+        let text = spell_out_numbers(&normalize(transcript));
+        classify(&text, &text.to_ascii_lowercase(), now().date())
+    }
+
+    #[test]
+    fn the_leading_command_decides_between_invoice_and_quote() {
+        assert_eq!(
+            intent("Invoice the Hendersons 12k as per the estimate"),
+            Intent::Invoice
+        );
+        assert_eq!(intent("Bill Dave for the kitchen bid"), Intent::Invoice);
+        assert_eq!(
+            intent("Please send the Hendersons an invoice for 500"),
+            Intent::Invoice
+        );
+        assert_eq!(
+            intent("Quote Dave 4000 for the kitchen, payable on invoice"),
+            Intent::Estimate
+        );
+        assert_eq!(
+            intent("Give Dave Miller a quote for 3000"),
+            Intent::Estimate
+        );
+        assert_eq!(intent("Dave Miller kitchen quote 4000"), Intent::Estimate);
+        assert_eq!(intent("Remind me to invoice Dave"), Intent::Task);
+
+        assert_eq!(
+            parse("Invoice the Hendersons 12k as per the estimate", now()),
+            ParsedIntent::Invoice {
+                client: Some("Hendersons".to_string()),
+                amount: Some(Money::from_cents(1200000)),
+                description: None,
+            }
+        );
+    }
+
+    #[test]
+    fn a_time_alone_does_not_make_an_event() {
+        assert_eq!(intent("Pick up lumber tomorrow morning"), Intent::Task);
+        assert_eq!(intent("Call the inspector at 3"), Intent::Task);
+        assert_eq!(intent("Order drywall at 9am"), Intent::Task);
+        assert_eq!(intent("Dave Miller tomorrow at 3pm"), Intent::Calendar);
+        assert_eq!(intent("Site visit with Dave at 3"), Intent::Calendar);
+        assert_eq!(intent("Schedule a call with Dave at 3"), Intent::Calendar);
+    }
+}
@@ -0,0 +1,357 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use serde_json::{json, Value};
+
+use crate::money::Money;
+
+// Typed form of what the model (or `intent_parser`) extracted from a
+// recording, photo or transcript. Fields are optional so a partial answer
+// still deserializes; `problems` lists what is missing or unusable.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "intent", rename_all = "UPPERCASE")]
+pub enum ParsedIntent {
+    Invoice {
+        client: Option<String>,
+        amount: Option<Money>,
+        description: Option<String>,
+    },
+    // A quote for work not yet done.
+    #[serde(alias = "QUOTE")]
+    Estimate {
+        client: Option<String>,
+        amount: Option<Money>,
+        description: Option<String>,
+    },
+    Task {
+        description: Option<String>,
+        due_date: Option<String>,
+    },
+    Contact {
+        name: Option<String>,
+        phone: Option<String>,
+        company: Option<String>,
+    },
+    Expense {
+        merchant: Option<String>,
+        amount: Option<Money>,
+        date: Option<String>,
+        category: Option<String>,
+    },
+    #[serde(alias = "APPOINTMENT")]
+    Calendar {
+        title: Option<String>,
+        // Local time, RFC 3339 (a missing offset is read as local).
+        start_time: Option<String>,
+        duration_minutes: Option<i64>,
+        // Name of the person the appointment is with.
+        contact: Option<String>,
+    },
+    Unknown {
+        error: Option<String>,
+    },
+    // The recording held no speech; produced locally, never by the model.
+    #[serde(rename = "NO_SPEECH")]
+    NoSpeech,
+}
+
+impl ParsedIntent {
+    // Required fields that are missing, blank or malformed.
+    pub fn problems(&self) -> Vec<&'static str> {
+        let mut problems = Vec::new();
+        let mut require = |ok: bool, field: &'static str| {
+            if !ok {
+                problems.push(field);
+            }
+        };
+        match self {
+            ParsedIntent::Invoice {
+                client,
+                amount,
+                description,
+            }
+            | ParsedIntent::Estimate {
+                client,
+                amount,
+                description,
+            } => {
+                require(present(client), "client");
+                require(amount.is_some_and(|a| a > Money::ZERO), "amount");
+                require(present(description), "description");
+            }
+            ParsedIntent::Task {
+                description,
+                due_date,
+            } => {
+                require(present(description), "description");
+                require(valid_date(due_date), "due_date");
+            }
+            ParsedIntent::Contact { name, phone, .. } => {
+                require(present(name), "name");
+                require(present(phone), "phone");
+            }
+            ParsedIntent::Expense {
+                merchant,
+                amount,
+                date,
+                ..
+            } => {
+                require(present(merchant), "merchant");
+                require(amount.is_some_and(|a| a > Money::ZERO), "amount");
+                require(present(date) && valid_date(date), "date");
+            }
+            ParsedIntent::Calendar {
+                title,
+                start_time,
+                duration_minutes,
+                ..
+            } => {
+                require(present(title), "title");
+                require(
+                    start_time
+                        .as_deref()
+                        .is_some_and(|t| parse_local_time(t).is_some()),
+                    "start_time",
+                );
+                require(duration_minutes.is_none_or(|d| d > 0), "duration_minutes");
+            }
+            ParsedIntent::Unknown { .. } | ParsedIntent::NoSpeech => {}
+        }
+        problems
+    }
+}
+
+fn present(value: &Option<String>) -> bool {
+    value.as_deref().is_some_and(|v| !v.trim().is_empty())
+}
+
+// Absent dates are fine; present ones must be YYYY-MM-DD.
+fn valid_date(value: &Option<String>) -> bool {
+    value
+        .as_deref()
+        .is_none_or(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").is_ok())
+}
+
+// Accepts "2026-10-20T08:00:00-05:00" as well as offset-less
+// "2026-10-20T08:00[:00]", which models often return; the latter is read as
+// local time.
+pub fn parse_local_time(value: &str) -> Option<DateTime<Local>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Local));
+    }
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .and_then(|time| Local.from_local_datetime(&time).earliest())
+}
+
+// A parsed intent plus the required fields that had to be filled with
+// defaults, so the review screen can highlight them.
+pub struct Analysis {
+    pub intent: ParsedIntent,
+    pub guessed: Vec<&'static str>,
+}
+
+impl Analysis {
+    pub fn new(intent: ParsedIntent) -> Self {
+        let guessed = intent.problems();
+        Analysis { intent, guessed }
+    }
+}
+
+// JSON schema for structured output: `{"intents": [..]}`, one entry per
+// request in the recording. Each entry is kept flat (every field optional
+// except `intent`) because Gemini and OpenAI-style servers accept different
+// subsets of JSON schema, and both accept this one.
+pub fn response_schema(intents: &[&str]) -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "intents": { "type": "array", "items": intent_schema(intents) }
+        },
+        "required": ["intents"]
+    })
+}
+
+fn intent_schema(intents: &[&str]) -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "intent": { "type": "string", "enum": intents },
+            "client": { "type": "string" },
+            "amount": { "type": "number" },
+            "description": { "type": "string" },
+            "due_date": { "type": "string", "description": "YYYY-MM-DD" },
+            "name": { "type": "string" },
+            "phone": { "type": "string" },
+            "company": { "type": "string" },
+            "merchant": { "type": "string" },
+            "date": { "type": "string", "description": "YYYY-MM-DD" },
+            "category": { "type": "string" },
+            "title": { "type": "string" },
+            "start_time": { "type": "string", "description": "YYYY-MM-DDTHH:MM:SS with local UTC offset" },
+            "duration_minutes": { "type": "integer" },
+            "contact": { "type": "string" },
+            "error": { "type": "string" }
+        },
+        "required": ["intent"]
+    })
+}
+
+// Reads a model reply, tolerating ```json fences or prose around the JSON.
+// Accepts `{"intents": [..]}`, a bare array or a single intent object.
+pub fn decode(reply: &str) -> Result<Vec<ParsedIntent>, String> {
+    let start = reply
+        .find(['{', '['])
+        .ok_or("reply contains no JSON object")?;
+    let end = reply
+        .rfind(['}', ']'])
+        .ok_or("reply contains no JSON object")?;
+    if end < start {
+        return Err("reply contains no JSON object".to_string());
+    }
+    let value: Value =
+        serde_json::from_str(&reply[start..=end]).map_err(|e| format!("invalid JSON: {}", e))?;
+    let items = match value {
+        Value::Array(items) => items,
+        Value::Object(mut object) if object.contains_key("intents") => {
+            match object.remove("intents") {
+                Some(Value::Array(items)) => items,
+                _ => return Err("\"intents\" is not a list".to_string()),
+            }
+        }
+        single => vec![single],
+    };
+    items.into_iter().map(decode_one).collect()
+}
+
+fn decode_one(mut value: Value) -> Result<ParsedIntent, String> {
+    // Older prompts answered a non-receipt with a bare {"error": ".."}.
+    if value.get("intent").is_none() && value.get("error").is_some() {
+        value["intent"] = json!("UNKNOWN");
+    }
+    if let Some(intent) = value["intent"].as_str() {
+        value["intent"] = json!(intent.to_uppercase());
+    }
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+// Why a decoded reply needs a repair round, or `None` if it is usable as is.
+pub fn issue(reply: &Result<Vec<ParsedIntent>, String>) -> Option<String> {
+    let parsed = match reply {
+        Ok(parsed) => parsed,
+        Err(e) => return Some(format!("could not be used ({})", e)),
+    };
+    if parsed.is_empty() {
+        return Some("contained no intents".to_string());
+    }
+    let missing: Vec<String> = parsed
+        .iter()
+        .enumerate()
+        .filter_map(|(i, intent)| {
+            let problems = intent.problems();
+            (!problems.is_empty())
+                .then(|| format!("item {} was missing {}", i + 1, problems.join(", ")))
+        })
+        .collect();
+    (!missing.is_empty()).then(|| missing.join("; "))
+}
+
+// Lower is better; used to pick between the original and repaired reply.
+pub fn problem_count(parsed: &[ParsedIntent]) -> usize {
+    if parsed.is_empty() {
+        return usize::MAX;
+    }
+    parsed.iter().map(|intent| intent.problems().len()).sum()
+}
+
+pub fn repair_prompt(system_prompt: &str, previous_reply: &str, issue: &str) -> String {
+    format!(
+        "{}\n\nYour previous reply {}:\n{}\nReply again with corrected JSON only. Use null for anything you genuinely cannot determine.",
+        system_prompt, issue, previous_reply
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoice(client: &str, amount: i64, description: &str) -> ParsedIntent {
+        ParsedIntent::Invoice {
+            client: Some(client.to_string()),
+            amount: Some(Money::from_cents(amount)),
+            description: Some(description.to_string()),
+        }
+    }
+
+    #[test]
+    fn decodes_fenced_wrapped_reply() {
+        let reply = "Sure!\n```json\n{\"intents\": [{\"intent\": \"INVOICE\", \"client\": \"Dave\", \"amount\": \"$1,200\", \"description\": \"Deck\"}]}\n```";
+        assert_eq!(
+            decode(reply).unwrap(),
+            vec![invoice("Dave", 120000, "Deck")]
+        );
+    }
+
+    #[test]
+    fn decodes_bare_array_and_single_object() {
+        let array = r#"[{"intent": "task", "description": "Order lumber"}, {"intent": "quote", "client": "Ann"}]"#;
+        let parsed = decode(array).unwrap();
+        assert_eq!(
+            parsed[0],
+            ParsedIntent::Task {
+                description: Some("Order lumber".to_string()),
+                due_date: None,
+            }
+        );
+        assert!(matches!(parsed[1], ParsedIntent::Estimate { .. }));
+
+        let single =
+            r#"{"intent": "APPOINTMENT", "title": "Site visit", "start_time": "2026-10-20T08:00"}"#;
+        assert!(matches!(
+            decode(single).unwrap()[0],
+            ParsedIntent::Calendar { .. }
+        ));
+    }
+
+    #[test]
+    fn bare_error_becomes_unknown() {
+        assert_eq!(
+            decode(r#"{"error": "not a receipt"}"#).unwrap(),
+            vec![ParsedIntent::Unknown {
+                error: Some("not a receipt".to_string())
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_replies_without_json() {
+        assert!(decode("I could not understand that").is_err());
+        assert!(decode("} nope {").is_err());
+        assert!(decode(r#"{"intents": "INVOICE"}"#).is_err());
+        assert!(decode(r#"{"intent": "PAYROLL"}"#).is_err());
+    }
+
+    #[test]
+    fn reports_missing_fields() {
+        let partial = ParsedIntent::Invoice {
+            client: Some(" ".to_string()),
+            amount: Some(Money::ZERO),
+            description: Some("Deck".to_string()),
+        };
+        assert_eq!(partial.problems(), vec!["client", "amount"]);
+        assert_eq!(
+            issue(&Ok(vec![invoice("Dave", 100, "Deck"), partial.clone()])),
+            Some("item 2 was missing client, amount".to_string())
+        );
+        assert_eq!(issue(&Ok(vec![invoice("Dave", 100, "Deck")])), None);
+        assert!(issue(&Ok(Vec::new())).is_some());
+        assert_eq!(problem_count(&[partial]), 2);
+        assert_eq!(problem_count(&[]), usize::MAX);
+    }
+
+    #[test]
+    fn reads_offsetless_times_as_local() {
+        assert!(parse_local_time("2026-10-20T08:00:00-05:00").is_some());
+        assert!(parse_local_time("2026-10-20T08:00").is_some());
+        assert!(parse_local_time("next Tuesday").is_none());
+    }
+}
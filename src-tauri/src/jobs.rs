@@ -0,0 +1,600 @@
+use std::collections::HashMap;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{new_id, AppState};
+
+// Durable background work. Jobs live in the `jobs` table so they survive
+// restarts; one worker thread runs them oldest first, retries failures with
+// exponential backoff and holds back jobs whose server cannot be reached.
+// Progress goes out as "job-progress" events and connectivity changes as
+// "connectivity-changed".
+
+const PROGRESS_EVENT: &str = "job-progress";
+const CONNECTIVITY_EVENT: &str = "connectivity-changed";
+
+// Transcribe and analyse a recording into drafts: {"recording_id"}.
+pub const ANALYZE: &str = "ANALYZE";
+// Only transcribe a recording: {"recording_id"}.
+pub const TRANSCRIBE: &str = "TRANSCRIBE";
+// Send an invoice to the bookkeeping webhook: {"invoice_id"}.
+pub const SYNC: &str = "SYNC";
+const KINDS: &[&str] = &[ANALYZE, TRANSCRIBE, SYNC];
+
+const PENDING: &str = "PENDING";
+const RUNNING: &str = "RUNNING";
+const DONE: &str = "DONE";
+const FAILED: &str = "FAILED";
+
+// After this many failures a job stays FAILED until retried by hand.
+const MAX_ATTEMPTS: i64 = 8;
+// 30 s, 1 min, 2 min, ... capped at an hour.
+const BASE_DELAY: Duration = Duration::from_secs(30);
+const MAX_DELAY: Duration = Duration::from_secs(60 * 60);
+// How often the worker looks for due jobs when nobody wakes it.
+const IDLE_POLL: Duration = Duration::from_secs(15);
+// A probe result is trusted this long before probing again.
+const CONNECTIVITY_TTL: Duration = Duration::from_secs(30);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Stored under "sync" in `app_settings`. Confirmed invoices are queued for
+// the webhook while one is set.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+pub struct SyncSettings {
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct Job {
+    pub id: String,
+    pub kind: String,
+    pub payload: Value,
+    pub status: String,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub result: Option<Value>,
+    // Unix milliseconds.
+    pub run_after: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Job {
+    // A string field of the payload, e.g. "recording_id".
+    pub fn payload_str(&self, key: &str) -> Result<&str, String> {
+        self.payload[key]
+            .as_str()
+            .ok_or_else(|| format!("{} job {} has no {}", self.kind, self.id, key))
+    }
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+struct Progress<'a> {
+    id: &'a str,
+    kind: &'a str,
+    // "queued", "running", "done", "retrying" or "failed".
+    state: &'a str,
+    attempts: i64,
+    error: Option<&'a str>,
+    result: Option<&'a Value>,
+    // Unix milliseconds of the next attempt, when retrying.
+    retry_at: Option<i64>,
+}
+
+// What the worker calls to do the work. `endpoint` gives the URL a job has to
+// reach, if any; the job waits until that server answers.
+pub struct Handler {
+    pub run: fn(&AppHandle, &Job) -> Result<Value, String>,
+    pub endpoint: fn(&AppHandle, &Job) -> Option<String>,
+}
+
+#[derive(Default)]
+pub struct Jobs {
+    woken: Mutex<bool>,
+    wake: Condvar,
+    // Keyed by "host:port".
+    probes: Mutex<HashMap<String, Probe>>,
+}
+
+struct Probe {
+    reachable: bool,
+    checked_at: Option<Instant>,
+}
+
+impl Jobs {
+    // Makes the worker look for due jobs now instead of at its next poll.
+    pub fn wake(&self) {
+        if let Ok(mut woken) = self.woken.lock() {
+            *woken = true;
+            self.wake.notify_one();
+        }
+    }
+
+    // Whether the server behind `url` accepts connections, probing if the
+    // last answer is stale. "connectivity-changed" reports whether every
+    // server probed so far is reachable, whenever that changes.
+    pub fn is_reachable(&self, app_handle: &AppHandle, url: &str) -> bool {
+        // A malformed URL is left for the job itself to report.
+        let Some(address) = probe_address(url) else {
+            return true;
+        };
+        let mut probes = self.probes.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(Probe {
+            reachable,
+            checked_at: Some(checked_at),
+        }) = probes.get(&address)
+        {
+            if checked_at.elapsed() < CONNECTIVITY_TTL {
+                return *reachable;
+            }
+        }
+        let was_online = probes.values().all(|probe| probe.reachable);
+        let reachable = probe(&address);
+        if probes.get(&address).map(|probe| probe.reachable) != Some(reachable) {
+            println!(
+                "DEBUG: {} is {}",
+                address,
+                if reachable {
+                    "reachable"
+                } else {
+                    "unreachable"
+                }
+            );
+        }
+        probes.insert(
+            address,
+            Probe {
+                reachable,
+                checked_at: Some(Instant::now()),
+            },
+        );
+        let online = probes.values().all(|probe| probe.reachable);
+        if online != was_online {
+            let _ = app_handle.emit(CONNECTIVITY_EVENT, serde_json::json!({ "online": online }));
+        }
+        reachable
+    }
+
+    // Forgets the last probe of `url`, e.g. after a job talking to it failed.
+    fn recheck(&self, url: &str) {
+        let Some(address) = probe_address(url) else {
+            return;
+        };
+        if let Ok(mut probes) = self.probes.lock() {
+            if let Some(probe) = probes.get_mut(&address) {
+                probe.checked_at = None;
+            }
+        }
+    }
+
+    fn wait(&self) {
+        let woken = self.woken.lock().unwrap_or_else(|e| e.into_inner());
+        let (mut woken, _) = self
+            .wake
+            .wait_timeout_while(woken, IDLE_POLL, |woken| !*woken)
+            .unwrap_or_else(|e| e.into_inner());
+        *woken = false;
+    }
+}
+
+// "host:port" of an http(s) URL, with the scheme's default port.
+fn probe_address(url: &str) -> Option<String> {
+    let (scheme, rest) = url.trim().split_once("://")?;
+    let default_port = match scheme.to_ascii_lowercase().as_str() {
+        "http" => 80,
+        "https" => 443,
+        _ => return None,
+    };
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    if host.is_empty() {
+        return None;
+    }
+    let has_port = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.contains("]:"),
+        None => host.contains(':'),
+    };
+    if has_port {
+        Some(host.to_string())
+    } else {
+        Some(format!("{}:{}", host, default_port))
+    }
+}
+
+// A failed DNS lookup counts as unreachable too.
+fn probe(address: &str) -> bool {
+    address.to_socket_addrs().is_ok_and(|mut addresses| {
+        addresses.any(|address| TcpStream::connect_timeout(&address, PROBE_TIMEOUT).is_ok())
+    })
+}
+
+// Delay before attempt `attempts + 1`, doubling from `BASE_DELAY`.
+fn backoff(attempts: i64) -> Duration {
+    let exponent = attempts.clamp(1, 16) as u32 - 1;
+    BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(exponent))
+        .min(MAX_DELAY)
+}
+
+// Adds a job that is due straight away. Call `Jobs::wake` afterwards.
+pub fn enqueue(
+    conn: &Connection,
+    app_handle: &AppHandle,
+    kind: &str,
+    payload: &Value,
+) -> Result<Job, String> {
+    let job = insert(conn, kind, payload)?;
+    emit(app_handle, &job, "queued", None);
+    Ok(job)
+}
+
+fn insert(conn: &Connection, kind: &str, payload: &Value) -> Result<Job, String> {
+    if !KINDS.contains(&kind) {
+        return Err(format!("Unknown job kind '{}'", kind));
+    }
+    let id = new_id();
+    let now = Local::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO jobs (id, kind, payload, status, run_after, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+        params![id, kind, payload.to_string(), PENDING, now_ms(), now],
+    )
+    .map_err(|e| e.to_string())?;
+    get(conn, &id)
+}
+
+const JOB_COLUMNS: &str =
+    "id, kind, payload, status, attempts, last_error, result, run_after, created_at, updated_at";
+
+fn job_from_row(row: &rusqlite::Row) -> rusqlite::Result<Job> {
+    let payload: String = row.get(2)?;
+    let result: Option<String> = row.get(6)?;
+    Ok(Job {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        payload: serde_json::from_str(&payload).unwrap_or(Value::Null),
+        status: row.get(3)?,
+        attempts: row.get(4)?,
+        last_error: row.get(5)?,
+        result: result.and_then(|r| serde_json::from_str(&r).ok()),
+        run_after: row.get(7)?,
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+    })
+}
+
+pub fn get(conn: &Connection, id: &str) -> Result<Job, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM jobs WHERE id = ?1", JOB_COLUMNS),
+        [id],
+        job_from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Job {} not found", id))
+}
+
+// Newest first; finished jobs only when asked for.
+pub fn list(conn: &Connection, include_done: bool) -> Result<Vec<Job>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM jobs WHERE ?1 OR status != ?2 ORDER BY created_at DESC",
+            JOB_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![include_done, DONE], job_from_row)
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+// Puts a failed or waiting job back at the front with a fresh set of attempts.
+pub fn retry(conn: &Connection, id: &str) -> Result<(), String> {
+    let affected = conn
+        .execute(
+            "UPDATE jobs SET status = ?2, attempts = 0, last_error = NULL, run_after = ?3, updated_at = ?4
+             WHERE id = ?1 AND status IN (?2, ?5)",
+            params![id, PENDING, now_ms(), Local::now().to_rfc3339(), FAILED],
+        )
+        .map_err(|e| e.to_string())?;
+    if affected == 0 {
+        return Err(format!("Job {} is not waiting or failed", id));
+    }
+    Ok(())
+}
+
+// Removes a job that is not running.
+pub fn cancel(conn: &Connection, id: &str) -> Result<(), String> {
+    let affected = conn
+        .execute(
+            "DELETE FROM jobs WHERE id = ?1 AND status != ?2",
+            params![id, RUNNING],
+        )
+        .map_err(|e| e.to_string())?;
+    if affected == 0 {
+        return Err(format!("Job {} is running or does not exist", id));
+    }
+    Ok(())
+}
+
+// Starts the worker thread. It idles until `init_db` has set the database up.
+pub fn spawn_worker(app_handle: AppHandle, handler: Handler) {
+    thread::spawn(move || {
+        let jobs = app_handle.state::<Jobs>();
+        let mut recovered = false;
+        loop {
+            jobs.wait();
+            let Some(conn) = open(&app_handle) else {
+                continue;
+            };
+            if !recovered {
+                // Whatever was running when the app closed starts over.
+                match requeue_running(&conn) {
+                    Ok(0) => {}
+                    Ok(n) => println!("DEBUG: Requeued {} interrupted job(s)", n),
+                    Err(e) => println!("WARNING: Failed to requeue interrupted jobs: {}", e),
+                }
+                recovered = true;
+            }
+            if let Err(e) = run_due(&app_handle, &jobs, &conn, &handler) {
+                println!("ERROR: Job queue: {}", e);
+            }
+        }
+    });
+}
+
+// Runs due jobs until none are left that can run now.
+fn run_due(
+    app_handle: &AppHandle,
+    jobs: &Jobs,
+    conn: &Connection,
+    handler: &Handler,
+) -> Result<(), String> {
+    loop {
+        let mut reachable = HashMap::new();
+        let next = due(conn)?.into_iter().find(|job| {
+            (handler.endpoint)(app_handle, job).is_none_or(|url| {
+                *reachable
+                    .entry(url)
+                    .or_insert_with_key(|url| jobs.is_reachable(app_handle, url))
+            })
+        });
+        let Some(job) = next else {
+            return Ok(());
+        };
+        if !claim(conn, &job.id)? {
+            continue;
+        }
+        println!("DEBUG: Running {} job {}", job.kind, job.id);
+        emit(app_handle, &job, "running", None);
+        match (handler.run)(app_handle, &job) {
+            Ok(result) => {
+                finish(conn, &job.id, &result)?;
+                emit(app_handle, &get(conn, &job.id)?, "done", None);
+            }
+            Err(e) => {
+                println!("WARNING: {} job {} failed: {}", job.kind, job.id, e);
+                if let Some(url) = (handler.endpoint)(app_handle, &job) {
+                    jobs.recheck(&url);
+                }
+                let failed = fail(conn, &job, &e)?;
+                let state = if failed.status == FAILED {
+                    "failed"
+                } else {
+                    "retrying"
+                };
+                emit(app_handle, &failed, state, Some(&e));
+            }
+        }
+    }
+}
+
+fn open(app_handle: &AppHandle) -> Option<Connection> {
+    let state = app_handle.state::<AppState>();
+    let db_path = state.db_path.lock().ok()?.clone();
+    if db_path.is_empty() {
+        return None;
+    }
+    crate::open_db_at(&db_path)
+        .map_err(|e| println!("WARNING: Job queue cannot open database: {}", e))
+        .ok()
+}
+
+fn due(conn: &Connection) -> Result<Vec<Job>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM jobs WHERE status = ?1 AND run_after <= ?2 ORDER BY run_after, created_at",
+            JOB_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![PENDING, now_ms()], job_from_row)
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+// Marks a pending job RUNNING; false if it was cancelled in the meantime.
+fn claim(conn: &Connection, id: &str) -> Result<bool, String> {
+    let affected = conn
+        .execute(
+            "UPDATE jobs SET status = ?2, updated_at = ?3 WHERE id = ?1 AND status = ?4",
+            params![id, RUNNING, Local::now().to_rfc3339(), PENDING],
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(affected == 1)
+}
+
+fn finish(conn: &Connection, id: &str, result: &Value) -> Result<(), String> {
+    conn.execute(
+        "UPDATE jobs SET status = ?2, result = ?3, last_error = NULL, updated_at = ?4 WHERE id = ?1",
+        params![id, DONE, result.to_string(), Local::now().to_rfc3339()],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Records a failed attempt and schedules the next one, or gives up.
+fn fail(conn: &Connection, job: &Job, error: &str) -> Result<Job, String> {
+    let attempts = job.attempts + 1;
+    let status = if attempts >= MAX_ATTEMPTS {
+        FAILED
+    } else {
+        PENDING
+    };
+    let run_after = now_ms() + backoff(attempts).as_millis() as i64;
+    conn.execute(
+        "UPDATE jobs SET status = ?2, attempts = ?3, last_error = ?4, run_after = ?5, updated_at = ?6
+         WHERE id = ?1",
+        params![
+            job.id,
+            status,
+            attempts,
+            error,
+            run_after,
+            Local::now().to_rfc3339()
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    get(conn, &job.id)
+}
+
+fn requeue_running(conn: &Connection) -> Result<usize, String> {
+    conn.execute(
+        "UPDATE jobs SET status = ?1 WHERE status = ?2",
+        params![PENDING, RUNNING],
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn emit(app_handle: &AppHandle, job: &Job, state: &str, error: Option<&str>) {
+    let progress = Progress {
+        id: &job.id,
+        kind: &job.kind,
+        state,
+        attempts: job.attempts,
+        error,
+        result: job.result.as_ref(),
+        retry_at: (state == "retrying").then_some(job.run_after),
+    };
+    let _ = app_handle.emit(PROGRESS_EVENT, progress);
+}
+
+fn now_ms() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        assert_eq!(backoff(0), BASE_DELAY);
+        assert_eq!(backoff(1), BASE_DELAY);
+        assert_eq!(backoff(2), BASE_DELAY * 2);
+        assert_eq!(backoff(4), BASE_DELAY * 8);
+        assert_eq!(backoff(7), BASE_DELAY * 64);
+        assert_eq!(backoff(8), MAX_DELAY);
+        assert_eq!(backoff(i64::MAX), MAX_DELAY);
+        assert_eq!(backoff(-3), BASE_DELAY);
+    }
+
+    #[test]
+    fn failed_jobs_back_off_then_give_up_until_retried() {
+        let conn = crate::migrations::test_db();
+        assert!(insert(&conn, "PRINT", &Value::Null).is_err());
+        let job = insert(&conn, SYNC, &serde_json::json!({ "invoice_id": "i1" })).unwrap();
+        assert_eq!(job.payload_str("invoice_id"), Ok("i1"));
+        assert_eq!(due(&conn).unwrap().len(), 1);
+
+        assert!(claim(&conn, &job.id).unwrap());
+        assert!(!claim(&conn, &job.id).unwrap());
+        assert!(cancel(&conn, &job.id).is_err());
+        let before = now_ms();
+        let mut job = fail(&conn, &get(&conn, &job.id).unwrap(), "timed out").unwrap();
+        assert_eq!(job.status, PENDING);
+        assert_eq!(job.attempts, 1);
+        assert_eq!(job.last_error.as_deref(), Some("timed out"));
+        assert!(job.run_after >= before + BASE_DELAY.as_millis() as i64);
+        assert!(due(&conn).unwrap().is_empty());
+
+        while job.status == PENDING {
+            job = fail(&conn, &job, "timed out").unwrap();
+        }
+        assert_eq!(job.status, FAILED);
+        assert_eq!(job.attempts, MAX_ATTEMPTS);
+
+        retry(&conn, &job.id).unwrap();
+        let job = get(&conn, &job.id).unwrap();
+        assert_eq!((job.status.as_str(), job.attempts), (PENDING, 0));
+        assert_eq!(job.last_error, None);
+        assert_eq!(due(&conn).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn interrupted_jobs_are_requeued_and_finished_jobs_hidden() {
+        let conn = crate::migrations::test_db();
+        let job = insert(&conn, ANALYZE, &serde_json::json!({ "recording_id": "r1" })).unwrap();
+        assert!(claim(&conn, &job.id).unwrap());
+        assert!(due(&conn).unwrap().is_empty());
+        assert!(retry(&conn, &job.id).is_err());
+
+        assert_eq!(requeue_running(&conn).unwrap(), 1);
+        assert!(claim(&conn, &job.id).unwrap());
+        finish(&conn, &job.id, &serde_json::json!(["draft"])).unwrap();
+        let job = get(&conn, &job.id).unwrap();
+        assert_eq!(job.status, DONE);
+        assert_eq!(job.result, Some(serde_json::json!(["draft"])));
+        assert!(list(&conn, false).unwrap().is_empty());
+        assert_eq!(list(&conn, true).unwrap().len(), 1);
+
+        cancel(&conn, &job.id).unwrap();
+        assert!(get(&conn, &job.id).is_err());
+    }
+
+    #[test]
+    fn probes_the_host_and_port_of_the_url() {
+        assert_eq!(
+            probe_address("https://generativelanguage.googleapis.com/v1beta").as_deref(),
+            Some("generativelanguage.googleapis.com:443")
+        );
+        assert_eq!(
+            probe_address("http://localhost:11434/v1").as_deref(),
+            Some("localhost:11434")
+        );
+        assert_eq!(
+            probe_address("https://user:pw@hooks.example.com?token=1").as_deref(),
+            Some("hooks.example.com:443")
+        );
+        assert_eq!(
+            probe_address("http://[::1]/v1").as_deref(),
+            Some("[::1]:80")
+        );
+        assert_eq!(
+            probe_address("http://[::1]:8080").as_deref(),
+            Some("[::1]:8080")
+        );
+        assert_eq!(probe_address("ftp://example.com"), None);
+        assert_eq!(probe_address("example.com"), None);
+        assert_eq!(probe_address("https://"), None);
+    }
+
+    #[test]
+    fn probe_connects_to_the_server() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        assert!(probe(&address));
+        drop(listener);
+        assert!(!probe(&address));
+    }
+}
@@ -1,32 +1,142 @@
 use base64::{engine::general_purpose, Engine as _};
 use chrono::{Local, Utc};
-use opener;
-use rusqlite::Connection;
+use rusqlite::{params, Connection, TransactionBehavior};
 use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::fs;
+use std::path::PathBuf;
 use std::sync::Mutex;
 use tauri::State;
 
-use std::io::Cursor;
-use tauri::AppHandle;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext};
+use tauri::{AppHandle, Manager};
 
+mod audio;
+mod company;
+mod estimates;
+mod intent_parser;
+mod intents;
+mod jobs;
+mod llm;
+mod migrations;
 mod model_manager;
+mod money;
+mod numbering;
+mod pdf;
+mod recorder;
+mod recordings;
+mod settings;
+mod streaming;
+mod templates;
+mod vad;
+mod vocabulary;
+mod whisper_engine;
+
+use company::CompanyProfile;
+use estimates::Estimate;
+use intents::{Analysis, ParsedIntent};
+use jobs::{Job, Jobs, SyncSettings};
+use llm::{AiSettings, LlmInput, LlmProvider, VoiceMode};
+use model_manager::{ImportOutcome, ImportedModel};
+use money::Money;
+use recorder::Recorder;
+use recordings::{DraftLink, Recording, RetentionSettings};
+use streaming::Streaming;
+use templates::{Document, Template};
+use vocabulary::{Vocabulary, VocabularySettings};
+use whisper_engine::{Transcription, TranscriptionSettings, WhisperEngine};
 
 // REMOVED HARDCODED KEY
 use dotenv::dotenv;
-use std::env;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+struct InvoiceItem {
+    description: String,
+    #[serde(default = "default_quantity")]
+    quantity: f64,
+    #[serde(default)]
+    unit: Option<String>,
+    #[serde(default)]
+    unit_price: Money,
+    #[serde(default)]
+    taxable: bool,
+}
+
+fn default_quantity() -> f64 {
+    1.0
+}
+
+impl InvoiceItem {
+    fn line_total(&self) -> Money {
+        self.unit_price.times(self.quantity)
+    }
+
+    // The one item of a document that only has a description and an amount
+    // (e.g. straight from voice).
+    fn single(description: &str, amount: Money) -> Self {
+        InvoiceItem {
+            description: if description.is_empty() {
+                "Services".to_string()
+            } else {
+                description.to_string()
+            },
+            quantity: 1.0,
+            unit: None,
+            unit_price: amount,
+            taxable: false,
+        }
+    }
+}
+
+// Subtotal, tax and total of `items`, with tax at `tax_rate` percent on the
+// taxable ones.
+fn item_totals(items: &[InvoiceItem], tax_rate: f64) -> (Money, Money, Money) {
+    let subtotal: Money = items.iter().map(InvoiceItem::line_total).sum();
+    let taxable: Money = items
+        .iter()
+        .filter(|item| item.taxable)
+        .map(InvoiceItem::line_total)
+        .sum();
+    let tax = taxable.percent(tax_rate);
+    (subtotal, tax, subtotal + tax)
+}
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 struct Invoice {
     id: String,
     client: String,
-    amount: f64,
+    amount: Money,
     status: String,
     description: String,
     client_phone: Option<String>,
     client_company: Option<String>,
     pdf_path: Option<String>,
+    #[serde(default)]
+    items: Vec<InvoiceItem>,
+    #[serde(default)]
+    tax_rate: f64,
+    #[serde(default)]
+    subtotal: Money,
+    #[serde(default)]
+    tax: Money,
+    #[serde(default)]
+    number: Option<String>,
+    #[serde(default)]
+    created_at: Option<String>,
+    // The estimate this invoice was converted from.
+    #[serde(default)]
+    estimate_id: Option<String>,
+}
+
+impl Invoice {
+    // Recomputes subtotal, tax and total (`amount`) from the line items.
+    // A draft without items (e.g. straight from voice) becomes a single item.
+    fn apply_item_totals(&mut self) {
+        if self.items.is_empty() {
+            self.items
+                .push(InvoiceItem::single(&self.description, self.amount));
+        }
+        (self.subtotal, self.tax, self.amount) = item_totals(&self.items, self.tax_rate);
+    }
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
@@ -45,24 +155,29 @@ struct Contact {
     phone: String,
     company: Option<String>,
     created_at: String,
+    // Document template for this client; the company default when unset.
+    #[serde(default)]
+    template_id: Option<String>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 struct Expense {
     id: String,
     merchant: String,
-    amount: f64,
+    amount: Money,
     category: String,
     date: String,
     image_path: Option<String>,
     status: String,
+    #[serde(default)]
+    created_at: Option<String>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 struct FinancialSummary {
-    revenue: f64,
-    expenses: f64,
-    profit: f64,
+    revenue: Money,
+    expenses: Money,
+    profit: Money,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
@@ -70,7 +185,7 @@ struct ActivityItem {
     id: String,
     intent: String,
     description: String,
-    amount: f64,
+    amount: Money,
     date: String,
     timestamp: i64,
     file_path: Option<String>,
@@ -90,79 +205,291 @@ struct CalendarEvent {
     title: String,
     start_time: String,
     duration_minutes: i64,
+    #[serde(default)]
+    contact_id: Option<String>,
 }
 
 struct AppState {
     db_path: Mutex<String>,
 }
 
-// --- SYNCHRONOUS ENGINE (ureq) ---
-fn ask_gemini(system_prompt: &str, mime_type: &str, data: &str) -> Result<Value, String> {
-    println!("--- STARTING ANALYSIS ({}) ---", mime_type);
-
-    let prompt = json!({
-        "contents": [{
-            "parts": [
-                { "text": system_prompt },
-                { "inline_data": { "mime_type": mime_type, "data": data } }
-            ]
-        }]
-    });
+// Opens the app database. Every connection goes through here so foreign keys
+// are enforced: SQLite leaves them off per connection, which would make the
+// schema's ON DELETE CASCADE / SET NULL clauses do nothing.
+fn open_db(state: &AppState) -> Result<Connection, String> {
+    let path = state.db_path.lock().unwrap().clone();
+    open_db_at(&path)
+}
+
+fn open_db_at(path: impl AsRef<std::path::Path>) -> Result<Connection, String> {
+    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "foreign_keys", true)
+        .map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+// Internal primary keys. Customer-facing invoice numbers come from `numbering`.
+fn new_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
 
-    println!("DEBUG: Sending to Google...");
+// Records are keyed by UUID; a client-made ID ("INV-48213") could collide
+// with an existing record.
+fn check_record_id(id: &str) -> Result<(), String> {
+    uuid::Uuid::parse_str(id)
+        .map(|_| ())
+        .map_err(|_| format!("Record ID '{}' is not a UUID", id))
+}
 
-    // Use the provided API key
-    let api_key =
-        env::var("VITE_GROQ_API_KEY").map_err(|_| "API Key not found in env".to_string())?;
-    let url = format!("https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash:generateContent?key={}", api_key);
+// --- SYNCHRONOUS ENGINE (ureq) ---
+// Asks the provider for a list of `intents`. A reply that does not decode or
+// lacks required fields gets one retry with a repair prompt; whatever is still
+// missing after that is reported in `Analysis::guessed`.
+fn ask_ai(
+    provider: &dyn LlmProvider,
+    system_prompt: &str,
+    input: &LlmInput,
+    intents: &[&str],
+) -> Result<Vec<Analysis>, String> {
+    let label = match input {
+        LlmInput::Media { mime_type, .. } => mime_type,
+        LlmInput::Text(_) => "text",
+    };
+    println!(
+        "--- STARTING ANALYSIS ({}) via {} ---",
+        label,
+        provider.name()
+    );
+    let schema = intents::response_schema(intents);
 
-    let response = match ureq::post(&url).send_json(prompt) {
-        Ok(res) => res,
-        Err(ureq::Error::Status(code, response)) => {
-            let body = response
-                .into_string()
-                .unwrap_or_else(|_| "No error body".to_string());
-            let err = format!("API Error {}: {}", code, body);
+    let raw_text = provider
+        .complete(system_prompt, input, &schema)
+        .map_err(|err| {
             println!("ERROR: {}", err);
-            return Err(err);
+            err
+        })?;
+    println!("DEBUG: {} Responded!", provider.name());
+
+    let first = intents::decode(&raw_text);
+    let issue = match intents::issue(&first) {
+        Some(issue) => issue,
+        None => {
+            let parsed = first?;
+            println!("SUCCESS: Parsed intents: {:?}", parsed);
+            return Ok(parsed.into_iter().map(Analysis::new).collect());
         }
-        Err(e) => {
-            let err = format!("Network Error: {}", e);
+    };
+
+    println!("DEBUG: AI reply {}, retrying with repair prompt", issue);
+    let repair_prompt = intents::repair_prompt(system_prompt, &raw_text, &issue);
+    let second = provider
+        .complete(&repair_prompt, input, &schema)
+        .and_then(|reply| intents::decode(&reply));
+
+    let parsed = match (first, second) {
+        (Ok(first), Ok(second))
+            if intents::problem_count(&first) < intents::problem_count(&second) =>
+        {
+            first
+        }
+        (_, Ok(second)) => second,
+        (Ok(first), Err(_)) => first,
+        (Err(e), Err(_)) => {
+            let err = format!("AI returned bad JSON: {}", e);
             println!("ERROR: {}", err);
             return Err(err);
         }
     };
+    let analyses: Vec<Analysis> = parsed.into_iter().map(Analysis::new).collect();
+    for analysis in &analyses {
+        println!(
+            "SUCCESS: Parsed intent: {:?} (guessed: {:?})",
+            analysis.intent, analysis.guessed
+        );
+    }
+    Ok(analyses)
+}
 
-    println!("DEBUG: Google Responded!");
+fn load_ai_settings(state: &State<'_, AppState>) -> Result<AiSettings, String> {
+    let conn = open_db(state)?;
+    settings::load(&conn, "ai")
+}
 
-    let response_body: Value = response.into_json().map_err(|e| {
-        let err = format!("JSON Error: {}", e);
-        println!("ERROR: {}", err);
-        err
-    })?;
+// Falls back to defaults before `init_db` has run, so recording works even if
+// the database is not ready yet.
+fn load_setting_or_default<T: serde::de::DeserializeOwned + Default>(
+    app: &AppHandle,
+    key: &str,
+) -> T {
+    let state = app.state::<AppState>();
+    if state.db_path.lock().unwrap().is_empty() {
+        return T::default();
+    }
+    open_db(&state)
+        .and_then(|conn| settings::load(&conn, key))
+        .unwrap_or_else(|e| {
+            println!("WARNING: Using default {} settings: {}", key, e);
+            T::default()
+        })
+}
 
-    let raw_text = response_body["candidates"][0]["content"]["parts"][0]["text"]
-        .as_str()
-        .ok_or_else(|| {
-            let err = "AI returned no text".to_string();
-            println!("ERROR: {}", err);
-            err
-        })?;
+fn load_transcription_settings(app: &AppHandle) -> TranscriptionSettings {
+    load_setting_or_default(app, "transcription")
+}
+
+fn load_imported_models(app: &AppHandle) -> Vec<ImportedModel> {
+    load_setting_or_default(app, "imported_models")
+}
+
+// Contact names and companies plus the trade glossary. Like the settings
+// loaders above, falls back to the defaults rather than failing a recording.
+fn load_vocabulary(app: &AppHandle) -> Vocabulary {
+    let glossary = load_setting_or_default::<VocabularySettings>(app, "vocabulary").glossary;
+    let state = app.state::<AppState>();
+    let contacts = if state.db_path.lock().unwrap().is_empty() {
+        Ok(Vec::new())
+    } else {
+        load_contact_vocabulary(&state)
+    };
+    let contacts = contacts.unwrap_or_else(|e| {
+        println!("WARNING: Transcribing without contact names: {}", e);
+        Vec::new()
+    });
+    let mut names = Vec::new();
+    let mut terms = Vec::new();
+    for (name, company) in contacts {
+        names.push(name);
+        terms.extend(company);
+    }
+    terms.extend(glossary);
+    Vocabulary {
+        names: vocabulary::clean_terms(names),
+        terms: vocabulary::clean_terms(terms),
+    }
+}
+
+fn load_contact_vocabulary(state: &AppState) -> Result<Vec<(String, Option<String>)>, String> {
+    let conn = open_db(state)?;
+    let mut stmt = conn
+        .prepare("SELECT name, company FROM contacts ORDER BY rowid DESC")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+fn load_ai_provider(state: &State<'_, AppState>) -> Result<Box<dyn LlmProvider>, String> {
+    llm::provider_from_settings(&load_ai_settings(state)?)
+}
 
-    let clean_json = raw_text
-        .replace("```json", "")
-        .replace("```", "")
-        .trim()
-        .to_string();
-    let parsed: Value = serde_json::from_str(&clean_json).map_err(|_| {
-        let err = "AI returned bad JSON".to_string();
-        println!("ERROR: {}", err);
-        err
-    })?;
+// --- VOICE PIPELINE ---
 
-    println!("SUCCESS: Parsed JSON content: {:?}", parsed);
+const VOICE_INTENTS: &[&str] = &[
+    "INVOICE", "ESTIMATE", "TASK", "CONTACT", "CALENDAR", "UNKNOWN",
+];
 
-    Ok(parsed)
+fn voice_prompt(source: &str) -> String {
+    let now = Local::now();
+    let current_date = now.format("%Y-%m-%d").to_string();
+    format!(
+        "Today is [{}] and the local time is {}. {} One note may contain several requests (e.g. an invoice and a reminder).
+        Return {{ \"intents\": [...] }} with one object per request, in the order spoken. Classify each INTENT as 'INVOICE', 'ESTIMATE', 'TASK', 'CONTACT', or 'CALENDAR'. Classify by what the speaker asks for, not by words they mention: \"invoice the Hendersons as per the estimate\" is an INVOICE, \"quote Dave for the kitchen, payable on invoice\" an ESTIMATE. 
+        1. INVOICE: {{ \"intent\": \"INVOICE\", \"client\": \"Name\", \"amount\": 100, \"description\": \"Short summary of work\" }}
+        2. TASK: {{ \"intent\": \"TASK\", \"description\": \"Action item\", \"due_date\": \"YYYY-MM-DD\" (Calculate based on 'today', or null if none) }}
+        3. CONTACT: {{ \"intent\": \"CONTACT\", \"name\": \"Name\", \"phone\": \"Phone#\", \"company\": \"Company or null\" }}
+        4. CALENDAR (appointments, meetings, site visits): {{ \"intent\": \"CALENDAR\", \"title\": \"Short title\", \"start_time\": \"YYYY-MM-DDTHH:MM:SS+HH:MM\" (Resolve 'tomorrow at 8', 'Monday morning' against the local time above, keeping its UTC offset), \"duration_minutes\": 60, \"contact\": \"Name of the person it is with, or null\" }}
+        5. ESTIMATE (quotes and bids for work not done yet): {{ \"intent\": \"ESTIMATE\", \"client\": \"Name\", \"amount\": 4000, \"description\": \"Short summary of the proposed work\" }}
+        Use null for anything that was not said. Return ONLY valid JSON.", 
+        current_date,
+        now.to_rfc3339(),
+        source
+    )
+}
+
+fn analyze_audio_in_cloud(
+    ai_settings: &AiSettings,
+    audio_data: &[u8],
+) -> Result<Vec<Analysis>, String> {
+    let provider = llm::provider_from_settings(ai_settings)?;
+    // The recorder now produces WAV; older inbox files are webm, and phone
+    // recordings M4A.
+    let mime_type = if audio_data.starts_with(b"RIFF") {
+        "audio/wav"
+    } else if audio_data.get(4..8) == Some(b"ftyp") {
+        "audio/mp4"
+    } else {
+        "audio/webm"
+    };
+    if !provider.accepts(mime_type) {
+        return Err(format!(
+            "The {} AI provider cannot listen to recordings",
+            provider.name()
+        ));
+    }
+    let base64_audio = general_purpose::STANDARD.encode(audio_data);
+    ask_ai(
+        provider.as_ref(),
+        &voice_prompt("Listen to audio."),
+        &LlmInput::Media {
+            mime_type,
+            data: &base64_audio,
+        },
+        VOICE_INTENTS,
+    )
+}
+
+// Runs the offline parser, asking the cloud provider only when the voice mode
+// allows it and the parser could not tell what was meant.
+fn parse_transcript(ai_settings: &AiSettings, transcript: &str) -> Result<Vec<Analysis>, String> {
+    let parsed = intent_parser::parse_all(transcript, Local::now().naive_local());
+    let understood = parsed
+        .iter()
+        .any(|intent| !matches!(intent, ParsedIntent::Unknown { .. }));
+    if understood || ai_settings.voice_mode == VoiceMode::Local {
+        return Ok(parsed.into_iter().map(Analysis::new).collect());
+    }
+    println!("DEBUG: Local parser could not classify transcript, asking cloud provider");
+    let provider = llm::provider_from_settings(ai_settings)?;
+    ask_ai(
+        provider.as_ref(),
+        &voice_prompt("Read this voice note transcript."),
+        &LlmInput::Text(transcript),
+        VOICE_INTENTS,
+    )
+}
+
+// `recording` is the inbox file the audio came from; a local transcript is
+// stored against it. `transcribe` produces that transcript and only runs when
+// the voice mode allows local transcription.
+fn analyze_voice(
+    app: &AppHandle,
+    ai_settings: &AiSettings,
+    recording: &str,
+    audio_data: &[u8],
+    transcribe: impl FnOnce() -> Result<Transcription, String>,
+) -> Result<Vec<Analysis>, String> {
+    if ai_settings.voice_mode == VoiceMode::Cloud {
+        return analyze_audio_in_cloud(ai_settings, audio_data);
+    }
+    let transcript = transcribe();
+    if let Ok(transcription) = &transcript {
+        if let Err(e) = save_transcript(app, recording, transcription) {
+            println!("WARNING: Failed to save transcript: {}", e);
+        }
+    }
+    let transcript = match transcript {
+        Ok(Transcription::Speech { text, .. }) => text,
+        // Nothing for the parser or the cloud to work with.
+        Ok(Transcription::NoSpeech) => return Ok(vec![Analysis::new(ParsedIntent::NoSpeech)]),
+        Err(e) if ai_settings.voice_mode == VoiceMode::LocalWithCloudFallback => {
+            println!("WARNING: Local transcription failed ({}), using cloud", e);
+            return analyze_audio_in_cloud(ai_settings, audio_data);
+        }
+        Err(e) => return Err(e),
+    };
+    parse_transcript(ai_settings, &transcript)
 }
 
 // --- COMMANDS ---
@@ -178,11 +505,20 @@ fn open_system_link(url: String) -> Result<String, String> {
 
 #[tauri::command]
 fn open_invoice_pdf(id: String) -> Result<String, String> {
+    open_document_pdf("invoices", &id)
+}
+
+#[tauri::command]
+fn open_estimate_pdf(id: String) -> Result<String, String> {
+    open_document_pdf("estimates", &id)
+}
+
+fn open_document_pdf(folder: &str, id: &str) -> Result<String, String> {
     let home = dirs::home_dir().ok_or("No Home directory found")?;
-    // Reconstruct path: ~/.construction-os/invoices/{id}.pdf
+    // Reconstruct path: ~/.construction-os/{folder}/{id}.pdf
     let path = home
         .join(".construction-os")
-        .join("invoices")
+        .join(folder)
         .join(format!("{}.pdf", id));
 
     println!("DEBUG: Attempting to open PDF at: {:?}", path);
@@ -197,128 +533,324 @@ fn open_invoice_pdf(id: String) -> Result<String, String> {
     }
 }
 
+// Async so transcription runs on a blocking worker instead of the main thread.
 #[tauri::command]
-fn analyze_audio(path: String, state: State<'_, AppState>) -> Result<Value, String> {
-    let audio_path = std::path::PathBuf::from(&path);
-    let audio_data = fs::read(&audio_path).map_err(|e| e.to_string())?;
-    let base64_audio = general_purpose::STANDARD.encode(audio_data);
-
-    let current_date = Local::now().format("%Y-%m-%d").to_string();
-    let system_prompt = format!(
-        "Today is [{}]. Listen to audio. Classify INTENT as 'INVOICE', 'TASK', or 'CONTACT'. 
-        1. INVOICE: {{ \"intent\": \"INVOICE\", \"client\": \"Name\", \"amount\": 100, \"description\": \"Short summary of work\" }}
-        2. TASK: {{ \"intent\": \"TASK\", \"description\": \"Action item\", \"due_date\": \"YYYY-MM-DD\" (Calculate based on 'today', or null if none) }}
-        3. CONTACT: {{ \"intent\": \"CONTACT\", \"name\": \"Name\", \"phone\": \"Phone#\", \"company\": \"Company or null\" }}
-        Return ONLY valid JSON.", 
-        current_date
-    );
+async fn analyze_audio(app: AppHandle, path: String) -> Result<Vec<Value>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let audio_data = fs::read(&path).map_err(|e| e.to_string())?;
+        process_recording(&app, &path, &audio_data, || {
+            transcribe_recording(&app, &path, &audio_data)
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
 
-    let ai_result = ask_gemini(&system_prompt, "audio/webm", &base64_audio)?;
-    let intent = ai_result["intent"].as_str().unwrap_or("UNKNOWN");
+// Runs the voice pipeline for the recording at `path` and keeps its row in
+// `recordings` up to date: PROCESSING while it runs, then PROCESSED with
+// links to the drafts it produced, or FAILED with the error.
+fn process_recording(
+    app: &AppHandle,
+    path: &str,
+    audio_data: &[u8],
+    transcribe: impl FnOnce() -> Result<Transcription, String>,
+) -> Result<Vec<Value>, String> {
+    let state = app.state::<AppState>();
+    let id = {
+        let conn = open_db(&state)?;
+        let id = recordings::register(&conn, path)?;
+        recordings::set_status(&conn, &id, recordings::PROCESSING, None)?;
+        id
+    };
+    let drafts = load_ai_settings(&state)
+        .and_then(|ai_settings| analyze_voice(app, &ai_settings, path, audio_data, transcribe))
+        .and_then(|analyses| voice_drafts(analyses, &state));
+    record_outcome(&state, &id, &drafts)?;
+    drafts
+}
 
-    if intent == "INVOICE" {
-        let new_id = format!(
-            "INV-{}",
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs()
-        );
-        let client = ai_result["client"].as_str().unwrap_or("Unknown");
-        let amount = ai_result["amount"].as_f64().unwrap_or(0.0);
-        let description = ai_result["description"]
-            .as_str()
-            .unwrap_or("General Services");
-        let status = "DRAFT";
-
-        // Suggestion Logic
-        let mut suggested_contacts = Vec::new();
-        let mut all_contacts = Vec::new();
-        if let Ok(path_guard) = state.db_path.lock() {
-            if let Ok(conn) = Connection::open(path_guard.as_str()) {
-                let search_pattern = format!("%{}%", client);
-                let mut stmt = conn.prepare("SELECT id, name, phone, company FROM contacts WHERE lower(name) LIKE lower(?1) LIMIT 5").unwrap();
-                let rows = stmt.query_map([&search_pattern], |row| {
-                    Ok(ContactSuggestion {
-                        id: row.get(0)?,
-                        name: row.get(1)?,
-                        phone: row.get(2)?,
-                        company: row.get(3).ok(),
-                    })
-                });
-                if let Ok(mapped) = rows {
-                    for r in mapped {
-                        if let Ok(c) = r {
-                            suggested_contacts.push(c);
-                        }
-                    }
-                }
-
-                let mut stmt_all = conn
-                    .prepare("SELECT id, name, phone, company FROM contacts ORDER BY name ASC")
-                    .unwrap();
-                let rows_all = stmt_all.query_map([], |row| {
-                    Ok(ContactSuggestion {
-                        id: row.get(0)?,
-                        name: row.get(1)?,
-                        phone: row.get(2)?,
-                        company: row.get(3).ok(),
+fn record_outcome(
+    state: &State<'_, AppState>,
+    id: &str,
+    drafts: &Result<Vec<Value>, String>,
+) -> Result<(), String> {
+    let mut conn = open_db(state)?;
+    match drafts {
+        Ok(drafts) => {
+            let links: Vec<DraftLink> = drafts
+                .iter()
+                .filter_map(|draft| {
+                    Some(DraftLink {
+                        draft_id: draft["id"].as_str()?.to_string(),
+                        intent: draft["intent"].as_str()?.to_string(),
                     })
-                });
-                if let Ok(mapped_all) = rows_all {
-                    for r in mapped_all {
-                        if let Ok(c) = r {
-                            all_contacts.push(c);
-                        }
-                    }
-                }
-            }
+                })
+                .collect();
+            recordings::set_processed(&mut conn, id, &links)
         }
+        Err(e) => recordings::set_status(&conn, id, recordings::FAILED, Some(e)),
+    }
+}
 
-        return Ok(json!({
-            "intent": "INVOICE", "id": new_id, "client": client, "amount": amount, "description": description, "status": status,
-            "client_phone": null, "client_company": null, "suggested_contacts": suggested_contacts, "all_contacts": all_contacts
-        }));
-    } else if intent == "TASK" {
-        let new_id = format!(
-            "TSK-{}",
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs()
-        );
-        let description = ai_result["description"]
-            .as_str()
-            .unwrap_or("No description");
-        let status = "DRAFT";
-        let created_at = Local::now().to_rfc3339();
-        let due_date = ai_result["due_date"].as_str().map(|s| s.to_string());
-
-        return Ok(
-            json!({ "intent": "TASK", "id": new_id, "description": description, "status": status, "created_at": created_at, "due_date": due_date }),
-        );
-    } else if intent == "CONTACT" {
-        let new_id = format!(
-            "CON-{}",
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs()
-        );
-        let name = ai_result["name"].as_str().unwrap_or("Unknown");
-        let phone = ai_result["phone"].as_str().unwrap_or("");
-        let company = ai_result["company"].as_str().map(|s| s.to_string());
-        let created_at = Local::now().to_rfc3339();
+#[tauri::command]
+fn list_input_devices() -> Result<Vec<recorder::InputDevice>, String> {
+    recorder::list_devices()
+}
 
-        return Ok(
-            json!({ "intent": "CONTACT", "id": new_id, "name": name, "phone": phone, "company": company, "created_at": created_at }),
-        );
+// Level meter readings arrive as "recording-level" events until stopped.
+// With `live`, text also arrives as "transcript-partial" and
+// "transcript-final" events while the user is still speaking.
+#[tauri::command]
+fn start_recording(
+    app: AppHandle,
+    device: Option<String>,
+    live: Option<bool>,
+    recorder: State<'_, Recorder>,
+    streaming: State<'_, Streaming>,
+) -> Result<String, String> {
+    recorder.start(app.clone(), device)?;
+    if live.unwrap_or(false) {
+        if let Some(audio) = recorder.live_audio() {
+            let transcriber = app.clone();
+            streaming.start(app, audio, move |samples| {
+                transcribe_samples(&transcriber, samples)
+            });
+        }
     }
-    Ok(ai_result)
+    Ok("Recording".to_string())
+}
+
+// Saves the recording to the inbox and turns it into drafts, like
+// `analyze_audio` does for webview recordings.
+#[tauri::command]
+async fn stop_recording(app: AppHandle) -> Result<Vec<Value>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let live = app.state::<Streaming>().take();
+        let samples = app.state::<Recorder>().stop()?;
+        let wav = recorder::encode_wav(&samples)?;
+        let path = inbox_path("wav")?;
+        fs::write(&path, &wav).map_err(|e| e.to_string())?;
+        println!("DEBUG: Saved native recording to {:?}", path);
+        let path = path.to_string_lossy();
+        record_duration(&app, &path, samples.len());
+
+        // A live transcript only has the tail left to do.
+        process_recording(&app, &path, &wav, || match live {
+            Some(live) => live.finish(samples.clone()).or_else(|e| {
+                println!("WARNING: Live transcription failed ({}), starting over", e);
+                transcribe_samples(&app, &samples)
+            }),
+            None => transcribe_samples(&app, &samples),
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
+// For transcripts produced elsewhere (e.g. `transcribe_audio`). With
+// `recording_id` the recording is marked processed and linked to the drafts.
 #[tauri::command]
-fn analyze_image(image_data: String) -> Result<Value, String> {
+fn analyze_transcript(
+    transcript: String,
+    recording_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Value>, String> {
+    let ai_settings = load_ai_settings(&state)?;
+    let drafts = parse_transcript(&ai_settings, &transcript)
+        .and_then(|analyses| voice_drafts(analyses, &state));
+    if let Some(id) = recording_id {
+        record_outcome(&state, &id, &drafts)?;
+    }
+    drafts
+}
+
+// One draft per request in the note, each with its own ID. Parts that could
+// not be understood are dropped unless nothing else was.
+fn voice_drafts(
+    analyses: Vec<Analysis>,
+    state: &State<'_, AppState>,
+) -> Result<Vec<Value>, String> {
+    let (unknown, understood): (Vec<Analysis>, Vec<Analysis>) = analyses
+        .into_iter()
+        .partition(|a| matches!(a.intent, ParsedIntent::Unknown { .. }));
+    if understood.is_empty() {
+        return unknown
+            .into_iter()
+            .take(1)
+            .map(|a| voice_draft(a, state))
+            .collect();
+    }
+    understood
+        .into_iter()
+        .map(|a| voice_draft(a, state))
+        .collect()
+}
+
+// Turns an INVOICE/ESTIMATE/TASK/CONTACT result into a draft for the review screen.
+// Missing fields are listed in "guessed_fields"; on invoices and estimates
+// they are left empty (a null amount) for the user to fill in.
+fn voice_draft(analysis: Analysis, state: &State<'_, AppState>) -> Result<Value, String> {
+    let guessed = analysis.guessed;
+    match analysis.intent {
+        ParsedIntent::Invoice {
+            client,
+            amount,
+            description,
+        } => {
+            let new_id = new_id();
+            let client = client.unwrap_or_default();
+            let description = description.unwrap_or_default();
+            let status = "DRAFT";
+
+            // Suggestion Logic
+            let suggested_contacts = client_suggestions(state, &client);
+            let all_contacts = all_contacts(state);
+
+            Ok(json!({
+                "intent": "INVOICE", "id": new_id, "client": client, "amount": amount, "description": description, "status": status,
+                "client_phone": null, "client_company": null, "suggested_contacts": suggested_contacts, "all_contacts": all_contacts,
+                "guessed_fields": guessed
+            }))
+        }
+        ParsedIntent::Estimate {
+            client,
+            amount,
+            description,
+        } => {
+            let new_id = new_id();
+            let client = client.unwrap_or_default();
+            let description = description.unwrap_or_default();
+            let valid_until = estimates::default_valid_until();
+            let suggested_contacts = client_suggestions(state, &client);
+            let all_contacts = all_contacts(state);
+
+            Ok(json!({
+                "intent": "ESTIMATE", "id": new_id, "client": client, "amount": amount, "description": description, "status": estimates::DRAFT,
+                "valid_until": valid_until, "client_phone": null, "client_company": null, "suggested_contacts": suggested_contacts, "all_contacts": all_contacts,
+                "guessed_fields": guessed
+            }))
+        }
+        ParsedIntent::Task {
+            description,
+            due_date,
+        } => {
+            let new_id = new_id();
+            let description = description.unwrap_or_else(|| "No description".to_string());
+            let status = "DRAFT";
+            let created_at = Local::now().to_rfc3339();
+            // A malformed date is dropped rather than stored.
+            let due_date = due_date.filter(|_| !guessed.contains(&"due_date"));
+
+            Ok(
+                json!({ "intent": "TASK", "id": new_id, "description": description, "status": status, "created_at": created_at, "due_date": due_date, "guessed_fields": guessed }),
+            )
+        }
+        ParsedIntent::Contact {
+            name,
+            phone,
+            company,
+        } => {
+            let new_id = new_id();
+            let name = name.unwrap_or_else(|| "Unknown".to_string());
+            let phone = phone.unwrap_or_default();
+            let created_at = Local::now().to_rfc3339();
+
+            Ok(
+                json!({ "intent": "CONTACT", "id": new_id, "name": name, "phone": phone, "company": company, "created_at": created_at, "guessed_fields": guessed }),
+            )
+        }
+        ParsedIntent::Calendar {
+            title,
+            start_time,
+            duration_minutes,
+            contact,
+        } => {
+            let new_id = new_id();
+            let title = title.unwrap_or_else(|| "New Meeting".to_string());
+            let start_time = start_time
+                .as_deref()
+                .and_then(intents::parse_local_time)
+                .unwrap_or_else(Local::now)
+                .to_rfc3339();
+            let duration_minutes = duration_minutes.filter(|d| *d > 0).unwrap_or(60);
+            // Link to the best matching contact; the rest are offered in review.
+            let suggested_contacts = contact
+                .as_deref()
+                .map(|name| suggest_contacts(state, name))
+                .unwrap_or_default();
+            let contact_id = suggested_contacts.first().map(|c| c.id.clone());
+
+            Ok(json!({
+                "intent": "CALENDAR", "id": new_id, "title": title, "start_time": start_time, "duration_minutes": duration_minutes,
+                "contact": contact, "contact_id": contact_id, "suggested_contacts": suggested_contacts, "guessed_fields": guessed
+            }))
+        }
+        other => serde_json::to_value(other).map_err(|e| e.to_string()),
+    }
+}
+
+// As `suggest_contacts`, but nothing when no client was heard.
+fn client_suggestions(state: &State<'_, AppState>, client: &str) -> Vec<ContactSuggestion> {
+    if client.trim().is_empty() {
+        return Vec::new();
+    }
+    suggest_contacts(state, client)
+}
+
+// Contacts whose name contains `name`, best first.
+fn suggest_contacts(state: &State<'_, AppState>, name: &str) -> Vec<ContactSuggestion> {
+    let Ok(conn) = open_db(state) else {
+        return Vec::new();
+    };
+    let search_pattern = format!("%{}%", name);
+    let mut stmt = match conn.prepare(
+        "SELECT id, name, phone, company FROM contacts WHERE lower(name) LIKE lower(?1)
+         ORDER BY lower(name) = lower(?2) DESC, length(name) ASC LIMIT 5",
+    ) {
+        Ok(stmt) => stmt,
+        Err(_) => return Vec::new(),
+    };
+    let rows = stmt.query_map(params![search_pattern, name], |row| {
+        Ok(ContactSuggestion {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            phone: row.get(2)?,
+            company: row.get(3).ok(),
+        })
+    });
+    match rows {
+        Ok(mapped) => mapped.filter_map(Result::ok).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+// Every contact by name, for picking a client by hand.
+fn all_contacts(state: &State<'_, AppState>) -> Vec<ContactSuggestion> {
+    let Ok(conn) = open_db(state) else {
+        return Vec::new();
+    };
+    let mut stmt =
+        match conn.prepare("SELECT id, name, phone, company FROM contacts ORDER BY name ASC") {
+            Ok(stmt) => stmt,
+            Err(_) => return Vec::new(),
+        };
+    let rows = stmt.query_map([], |row| {
+        Ok(ContactSuggestion {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            phone: row.get(2)?,
+            company: row.get(3).ok(),
+        })
+    });
+    match rows {
+        Ok(mapped) => mapped.filter_map(Result::ok).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+#[tauri::command]
+fn analyze_image(image_data: String, state: State<'_, AppState>) -> Result<Value, String> {
+    let provider = load_ai_provider(&state)?;
     let clean_base64 = if let Some(index) = image_data.find(',') {
         &image_data[index + 1..]
     } else {
@@ -327,44 +859,64 @@ fn analyze_image(image_data: String) -> Result<Value, String> {
     let current_date = Local::now().format("%Y-%m-%d").to_string();
     let system_prompt = format!(
         "Today is [{}]. Analyze this image. Is it a RECEIPT? 
-        If YES, return JSON: {{ \"intent\": \"EXPENSE\", \"merchant\": \"Name\", \"amount\": 0.00, \"date\": \"YYYY-MM-DD\", \"category\": \"Category (Materials, Fuel, Tools, Other)\" }} 
-        If NO, return JSON: {{ \"error\": \"Not a receipt\" }}",
+        Return {{ \"intents\": [...] }} containing exactly one object.
+        If YES: {{ \"intent\": \"EXPENSE\", \"merchant\": \"Name\", \"amount\": 0.00, \"date\": \"YYYY-MM-DD\", \"category\": \"Category (Materials, Fuel, Tools, Other)\" }} 
+        If NO: {{ \"intent\": \"UNKNOWN\", \"error\": \"Not a receipt\" }}",
         current_date
     );
-    let ai_result = ask_gemini(&system_prompt, "image/jpeg", clean_base64)?;
-    if let Some(err) = ai_result.get("error") {
-        return Err(err.as_str().unwrap_or("Unknown AI error").to_string());
-    }
-
-    let intent = ai_result["intent"].as_str().unwrap_or("UNKNOWN");
-    if intent == "EXPENSE" {
-        let new_id = format!(
-            "EXP-{}",
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs()
-        );
-        let merchant = ai_result["merchant"].as_str().unwrap_or("Unknown");
-        let amount = ai_result["amount"].as_f64().unwrap_or(0.0);
-        let category = ai_result["category"].as_str().unwrap_or("Other");
-        let date = ai_result["date"].as_str().unwrap_or(&current_date);
-        let status = "DRAFT";
-        return Ok(
-            json!({ "intent": "EXPENSE", "id": new_id, "merchant": merchant, "amount": amount, "category": category, "date": date, "status": status }),
-        );
+    let analysis = ask_ai(
+        provider.as_ref(),
+        &system_prompt,
+        &LlmInput::Media {
+            mime_type: "image/jpeg",
+            data: clean_base64,
+        },
+        &["EXPENSE", "UNKNOWN"],
+    )?
+    .into_iter()
+    .next()
+    .ok_or("AI returned no result")?;
+    let guessed = analysis.guessed;
+
+    match analysis.intent {
+        ParsedIntent::Expense {
+            merchant,
+            amount,
+            date,
+            category,
+        } => {
+            let new_id = new_id();
+            let merchant = merchant.unwrap_or_else(|| "Unknown".to_string());
+            let amount = amount.unwrap_or(Money::ZERO);
+            let category = category.unwrap_or_else(|| "Other".to_string());
+            let date = date
+                .filter(|_| !guessed.contains(&"date"))
+                .unwrap_or(current_date);
+            let status = "DRAFT";
+            Ok(
+                json!({ "intent": "EXPENSE", "id": new_id, "merchant": merchant, "amount": amount, "category": category, "date": date, "status": status, "guessed_fields": guessed }),
+            )
+        }
+        ParsedIntent::Unknown { error } => {
+            Err(error.unwrap_or_else(|| "Not a receipt".to_string()))
+        }
+        other => Err(format!("Expected a receipt, got {:?}", other)),
     }
-    Ok(ai_result)
 }
 
 #[tauri::command]
-fn confirm_invoice(invoice: Invoice, state: State<'_, AppState>) -> Result<String, String> {
+fn confirm_invoice(
+    app: AppHandle,
+    mut invoice: Invoice,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
     println!(
         "DEBUG: Attempting to confirm invoice for client: {}",
         invoice.client
     );
-    let path_guard = state.db_path.lock().unwrap();
-    let conn = Connection::open(path_guard.as_str()).map_err(|e| e.to_string())?;
+    check_record_id(&invoice.id)?;
+    invoice.apply_item_totals();
+    let mut conn = open_db(&state)?;
     let status = if invoice.status == "DRAFT" {
         "GENERATED"
     } else {
@@ -372,62 +924,123 @@ fn confirm_invoice(invoice: Invoice, state: State<'_, AppState>) -> Result<Strin
     };
 
     // Smart Link
-    let mut phone = invoice.client_phone.clone();
-    let mut company = invoice.client_company.clone();
+    link_client_details(
+        &conn,
+        &invoice.client,
+        &mut invoice.client_phone,
+        &mut invoice.client_company,
+    );
+    invoice
+        .created_at
+        .get_or_insert_with(|| Local::now().to_rfc3339());
 
-    if phone.is_none() || company.is_none() {
-        let search_name = format!("%{}%", invoice.client);
-        if let Ok(mut stmt) = conn.prepare("SELECT phone, company FROM contacts WHERE name LIKE ?1")
-        {
-            let rows = stmt.query_map([&search_name], |row| {
-                Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
-            });
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| e.to_string())?;
+    let number = insert_invoice(&tx, &invoice, status)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    println!("DEBUG: Invoice {} saved as {}", invoice.id, number);
 
-            if let Ok(mapped_rows) = rows {
-                for r in mapped_rows {
-                    if let Ok((p, c)) = r {
-                        if phone.is_none() {
-                            phone = Some(p);
-                        }
-                        if company.is_none() {
-                            company = c;
-                        }
-                        break;
-                    }
-                }
-            }
-        }
-    }
+    queue_invoice_sync(&conn, &app, &invoice.id)?;
+    Ok(number)
+}
 
+// Allocates the invoice's number and stores it with its items. Call inside a
+// transaction so the number is only used if the insert succeeds.
+fn insert_invoice(conn: &Connection, invoice: &Invoice, status: &str) -> Result<String, String> {
+    let number = numbering::next_number(conn, "invoice", "invoices")?;
     conn.execute(
-        "INSERT INTO invoices (id, client, amount, status, description, client_phone, client_company) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        &[&invoice.id, &invoice.client, &invoice.amount.to_string(), status, &invoice.description, &phone.unwrap_or_default(), &company.unwrap_or_default()],
+        "INSERT INTO invoices (id, number, client, amount_cents, status, description, client_phone, client_company, tax_rate, created_at, estimate_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![invoice.id, number, invoice.client, invoice.amount, status, invoice.description, invoice.client_phone.as_deref().unwrap_or_default(), invoice.client_company.as_deref().unwrap_or_default(), invoice.tax_rate, invoice.created_at, invoice.estimate_id],
     ).map_err(|e| e.to_string())?;
-    Ok("Saved".to_string())
+    insert_invoice_items(conn, &invoice.id, &invoice.items)?;
+    Ok(number)
+}
+
+// Sends the invoice to the bookkeeping webhook in the background, if one is
+// set.
+fn queue_invoice_sync(conn: &Connection, app: &AppHandle, invoice_id: &str) -> Result<(), String> {
+    let sync: SyncSettings = settings::load(conn, "sync")?;
+    if sync.webhook_url.is_some() {
+        jobs::enqueue(conn, app, jobs::SYNC, &json!({ "invoice_id": invoice_id }))?;
+        app.state::<Jobs>().wake();
+    }
+    Ok(())
+}
+
+// Fills a missing phone or company from the client's contact.
+fn link_client_details(
+    conn: &Connection,
+    client: &str,
+    phone: &mut Option<String>,
+    company: &mut Option<String>,
+) {
+    if phone.is_some() && company.is_some() {
+        return;
+    }
+    if let Some((p, c)) = find_contact_details(conn, client) {
+        if phone.is_none() {
+            *phone = Some(p);
+        }
+        if company.is_none() {
+            *company = c;
+        }
+    }
+}
+
+// Phone and company of the first contact whose name contains `client`.
+fn find_contact_details(conn: &Connection, client: &str) -> Option<(String, Option<String>)> {
+    let search_name = format!("%{}%", client);
+    let mut stmt = conn
+        .prepare("SELECT phone, company FROM contacts WHERE name LIKE ?1")
+        .ok()?;
+    let mut rows = stmt
+        .query_map([&search_name], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+        })
+        .ok()?;
+    rows.find_map(|row| row.ok())
+}
+
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.filter(|v| !v.trim().is_empty())
+}
+
+fn insert_invoice_items(
+    conn: &Connection,
+    invoice_id: &str,
+    items: &[InvoiceItem],
+) -> Result<(), String> {
+    for (position, item) in items.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO invoice_items (invoice_id, position, description, quantity, unit, unit_price_cents, taxable) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![invoice_id, position as i64, item.description, item.quantity, item.unit, item.unit_price, item.taxable],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
 }
 
 #[tauri::command]
 fn confirm_task(task: Task, state: State<'_, AppState>) -> Result<String, String> {
-    let path_guard = state.db_path.lock().unwrap();
-    let conn = Connection::open(path_guard.as_str()).map_err(|e| e.to_string())?;
+    let conn = open_db(&state)?;
     let status = if task.status == "DRAFT" {
         "TODO"
     } else {
         &task.status
     };
     conn.execute("INSERT INTO tasks (id, description, status, created_at, due_date) VALUES (?1, ?2, ?3, ?4, ?5)", 
-        &[&task.id, &task.description, status, &task.created_at, &task.due_date.unwrap_or_default()]
+        [&task.id, &task.description, status, &task.created_at, &task.due_date.unwrap_or_default()]
     ).map_err(|e| e.to_string())?;
     Ok("Saved".to_string())
 }
 
 #[tauri::command]
 fn confirm_contact(contact: Contact, state: State<'_, AppState>) -> Result<String, String> {
-    let path_guard = state.db_path.lock().unwrap();
-    let conn = Connection::open(path_guard.as_str()).map_err(|e| e.to_string())?;
+    let conn = open_db(&state)?;
     conn.execute(
         "INSERT INTO contacts (id, name, phone, company, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
-        &[
+        [
             &contact.id,
             &contact.name,
             &contact.phone,
@@ -441,69 +1054,582 @@ fn confirm_contact(contact: Contact, state: State<'_, AppState>) -> Result<Strin
 
 #[tauri::command]
 fn confirm_expense(expense: Expense, state: State<'_, AppState>) -> Result<String, String> {
-    let path_guard = state.db_path.lock().unwrap();
-    let conn = Connection::open(path_guard.as_str()).map_err(|e| e.to_string())?;
+    let conn = open_db(&state)?;
     let status = if expense.status == "DRAFT" {
         "PENDING"
     } else {
         &expense.status
     };
-    conn.execute("INSERT INTO expenses (id, merchant, amount, category, date, image_path, status) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)", 
-        &[&expense.id, &expense.merchant, &expense.amount.to_string(), &expense.category, &expense.date, &expense.image_path.unwrap_or_default(), status]
+    let created_at = Local::now().to_rfc3339();
+    conn.execute("INSERT INTO expenses (id, merchant, amount_cents, category, date, image_path, status, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)", 
+        params![expense.id, expense.merchant, expense.amount, expense.category, expense.date, expense.image_path.unwrap_or_default(), status, created_at]
     ).map_err(|e| e.to_string())?;
     Ok("Saved".to_string())
 }
 
-#[tauri::command]
-fn confirm_calendar_event(event: CalendarEvent, state: State<'_, AppState>) -> Result<String, String> {
-    let path_guard = state.db_path.lock().unwrap();
-    let conn = Connection::open(path_guard.as_str()).map_err(|e| e.to_string())?;
-    conn.execute(
-        "INSERT INTO calendar_events (id, title, start_time, duration_minutes) VALUES (?1, ?2, ?3, ?4)",
-        &[&event.id, &event.title, &event.start_time, &event.duration_minutes.to_string()],
-    )
-    .map_err(|e| e.to_string())?;
-    Ok("Saved".to_string())
+#[tauri::command]
+fn confirm_calendar_event(event: CalendarEvent, state: State<'_, AppState>) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    conn.execute(
+        "INSERT INTO calendar_events (id, title, start_time, duration_minutes, contact_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![event.id, event.title, event.start_time, event.duration_minutes, event.contact_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok("Saved".to_string())
+}
+
+// --- UPDATE & DELETE ---
+
+fn ensure_found(affected: usize, kind: &str, id: &str) -> Result<(), String> {
+    if affected == 0 {
+        Err(format!("{} {} not found", kind, id))
+    } else {
+        Ok(())
+    }
+}
+
+#[tauri::command]
+fn update_invoice(mut invoice: Invoice, state: State<'_, AppState>) -> Result<String, String> {
+    invoice.apply_item_totals();
+    let mut conn = open_db(&state)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let affected = tx
+        .execute(
+            "UPDATE invoices SET client = ?2, amount_cents = ?3, status = ?4, description = ?5, client_phone = ?6, client_company = ?7, tax_rate = ?8 WHERE id = ?1",
+            params![invoice.id, invoice.client, invoice.amount, invoice.status, invoice.description, invoice.client_phone.unwrap_or_default(), invoice.client_company.unwrap_or_default(), invoice.tax_rate],
+        )
+        .map_err(|e| e.to_string())?;
+    ensure_found(affected, "Invoice", &invoice.id)?;
+    tx.execute(
+        "DELETE FROM invoice_items WHERE invoice_id = ?1",
+        [&invoice.id],
+    )
+    .map_err(|e| e.to_string())?;
+    insert_invoice_items(&tx, &invoice.id, &invoice.items)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok("Updated".to_string())
+}
+
+#[tauri::command]
+fn delete_invoice(id: String, state: State<'_, AppState>) -> Result<String, String> {
+    let mut conn = open_db(&state)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM invoice_items WHERE invoice_id = ?1", [&id])
+        .map_err(|e| e.to_string())?;
+    let affected = tx
+        .execute("DELETE FROM invoices WHERE id = ?1", [&id])
+        .map_err(|e| e.to_string())?;
+    ensure_found(affected, "Invoice", &id)?;
+    // The estimate it came from can be invoiced again.
+    tx.execute(
+        "UPDATE estimates SET invoice_id = NULL WHERE invoice_id = ?1",
+        [&id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok("Deleted".to_string())
+}
+
+#[tauri::command]
+fn update_task(task: Task, state: State<'_, AppState>) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    let affected = conn
+        .execute(
+            "UPDATE tasks SET description = ?2, status = ?3, due_date = ?4 WHERE id = ?1",
+            params![task.id, task.description, task.status, task.due_date],
+        )
+        .map_err(|e| e.to_string())?;
+    ensure_found(affected, "Task", &task.id)?;
+    Ok("Updated".to_string())
+}
+
+#[tauri::command]
+fn delete_task(id: String, state: State<'_, AppState>) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    let affected = conn
+        .execute("DELETE FROM tasks WHERE id = ?1", [&id])
+        .map_err(|e| e.to_string())?;
+    ensure_found(affected, "Task", &id)?;
+    Ok("Deleted".to_string())
+}
+
+#[tauri::command]
+fn update_contact(contact: Contact, state: State<'_, AppState>) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    let affected = conn
+        .execute(
+            "UPDATE contacts SET name = ?2, phone = ?3, company = ?4 WHERE id = ?1",
+            params![contact.id, contact.name, contact.phone, contact.company],
+        )
+        .map_err(|e| e.to_string())?;
+    ensure_found(affected, "Contact", &contact.id)?;
+    Ok("Updated".to_string())
+}
+
+#[tauri::command]
+fn delete_contact(id: String, state: State<'_, AppState>) -> Result<String, String> {
+    let mut conn = open_db(&state)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    // Events with the contact stay on the calendar, just without the link.
+    tx.execute(
+        "UPDATE calendar_events SET contact_id = NULL WHERE contact_id = ?1",
+        [&id],
+    )
+    .map_err(|e| e.to_string())?;
+    let affected = tx
+        .execute("DELETE FROM contacts WHERE id = ?1", [&id])
+        .map_err(|e| e.to_string())?;
+    ensure_found(affected, "Contact", &id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok("Deleted".to_string())
+}
+
+#[tauri::command]
+fn update_expense(expense: Expense, state: State<'_, AppState>) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    let affected = conn
+        .execute(
+            "UPDATE expenses SET merchant = ?2, amount_cents = ?3, category = ?4, date = ?5, image_path = ?6, status = ?7 WHERE id = ?1",
+            params![expense.id, expense.merchant, expense.amount, expense.category, expense.date, expense.image_path, expense.status],
+        )
+        .map_err(|e| e.to_string())?;
+    ensure_found(affected, "Expense", &expense.id)?;
+    Ok("Updated".to_string())
+}
+
+#[tauri::command]
+fn delete_expense(id: String, state: State<'_, AppState>) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    let affected = conn
+        .execute("DELETE FROM expenses WHERE id = ?1", [&id])
+        .map_err(|e| e.to_string())?;
+    ensure_found(affected, "Expense", &id)?;
+    Ok("Deleted".to_string())
+}
+
+#[tauri::command]
+fn update_calendar_event(
+    event: CalendarEvent,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    let affected = conn
+        .execute(
+            "UPDATE calendar_events SET title = ?2, start_time = ?3, duration_minutes = ?4, contact_id = ?5 WHERE id = ?1",
+            params![
+                event.id,
+                event.title,
+                event.start_time,
+                event.duration_minutes,
+                event.contact_id
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    ensure_found(affected, "Calendar event", &event.id)?;
+    Ok("Updated".to_string())
+}
+
+#[tauri::command]
+fn delete_calendar_event(id: String, state: State<'_, AppState>) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    let affected = conn
+        .execute("DELETE FROM calendar_events WHERE id = ?1", [&id])
+        .map_err(|e| e.to_string())?;
+    ensure_found(affected, "Calendar event", &id)?;
+    Ok("Deleted".to_string())
+}
+
+#[tauri::command]
+fn get_ai_settings(state: State<'_, AppState>) -> Result<AiSettingsView, String> {
+    let conn = open_db(&state)?;
+    let mut settings: AiSettings = settings::load(&conn, "ai")?;
+    let has_api_key = settings
+        .api_key
+        .take()
+        .is_some_and(|key| !key.trim().is_empty());
+    Ok(AiSettingsView {
+        settings,
+        has_api_key,
+    })
+}
+
+// The API key never goes back to the webview; it only learns whether one is
+// stored.
+#[derive(serde::Serialize)]
+struct AiSettingsView {
+    #[serde(flatten)]
+    settings: AiSettings,
+    has_api_key: bool,
+}
+
+// Without `api_key` the stored key is kept; an empty one removes it.
+#[tauri::command]
+fn update_ai_settings(mut ai: AiSettings, state: State<'_, AppState>) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    if ai.api_key.is_none() {
+        let stored: AiSettings = settings::load(&conn, "ai")?;
+        ai.api_key = stored.api_key;
+    }
+    ai.api_key = ai.api_key.filter(|key| !key.trim().is_empty());
+    // Fail early on an unusable combination instead of at the next recording.
+    llm::provider_from_settings(&ai)?;
+    settings::save(&conn, "ai", &ai)?;
+    Ok("Saved".to_string())
+}
+
+#[tauri::command]
+fn list_models(app: AppHandle) -> Result<Vec<model_manager::ModelStatus>, String> {
+    let transcription = load_transcription_settings(&app);
+    model_manager::list(&app, &load_imported_models(&app), &transcription.model)
+}
+
+#[tauri::command]
+fn select_model(model_id: String, state: State<'_, AppState>) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    let imported: Vec<ImportedModel> = settings::load(&conn, "imported_models")?;
+    model_manager::validate(&model_id, &imported)?;
+    let mut transcription: TranscriptionSettings = settings::load(&conn, "transcription")?;
+    transcription.model = model_id;
+    settings::save(&conn, "transcription", &transcription)?;
+    Ok("Saved".to_string())
+}
+
+#[tauri::command]
+async fn download_model(app: AppHandle, model_id: String) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        model_manager::ensure_model_exists(&app, &model_id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn delete_model(
+    app: AppHandle,
+    model_id: String,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    let transcription: TranscriptionSettings = settings::load(&conn, "transcription")?;
+    if transcription.model == model_id {
+        return Err("Select another model before deleting the active one".to_string());
+    }
+    let mut imported: Vec<ImportedModel> = settings::load(&conn, "imported_models")?;
+    model_manager::delete(&app, &model_id, &imported)?;
+    // Imported models have nowhere to be downloaded from again, so forget them.
+    if imported.iter().any(|m| m.id == model_id) {
+        imported.retain(|m| m.id != model_id);
+        settings::save(&conn, "imported_models", &imported)?;
+    }
+    Ok("Deleted".to_string())
+}
+
+// Installs a ggml/gguf model file from disk, e.g. one copied over on a USB
+// stick for a site without internet. Returns the model id to select.
+#[tauri::command]
+async fn import_model(app: AppHandle, path: String) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = open_db(&app.state::<AppState>())?;
+        let mut imported: Vec<ImportedModel> = settings::load(&conn, "imported_models")?;
+        match model_manager::import(&app, std::path::Path::new(&path), &imported)? {
+            ImportOutcome::Catalogue(model) => Ok(model.id.to_string()),
+            ImportOutcome::Existing(id) => Ok(id),
+            ImportOutcome::New(model) => {
+                let id = model.id.clone();
+                imported.push(model);
+                settings::save(&conn, "imported_models", &imported)?;
+                Ok(id)
+            }
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn get_transcription_settings(state: State<'_, AppState>) -> Result<TranscriptionSettings, String> {
+    let conn = open_db(&state)?;
+    settings::load(&conn, "transcription")
+}
+
+#[tauri::command]
+fn update_transcription_settings(
+    mut transcription: TranscriptionSettings,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    let imported: Vec<ImportedModel> = settings::load(&conn, "imported_models")?;
+    model_manager::validate(&transcription.model, &imported)?;
+    // "auto" and "" both mean detect.
+    transcription.language = transcription
+        .language
+        .map(|l| l.trim().to_lowercase())
+        .filter(|l| !l.is_empty() && l != "auto");
+    if let Some(language) = &transcription.language {
+        whisper_engine::validate_language(language)?;
+    }
+    settings::save(&conn, "transcription", &transcription)?;
+    Ok("Saved".to_string())
+}
+
+#[tauri::command]
+fn get_vocabulary(state: State<'_, AppState>) -> Result<VocabularySettings, String> {
+    let conn = open_db(&state)?;
+    settings::load(&conn, "vocabulary")
+}
+
+// Contacts are picked up automatically; only the glossary is stored.
+#[tauri::command]
+fn update_vocabulary(
+    vocabulary: VocabularySettings,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    let vocabulary = VocabularySettings {
+        glossary: vocabulary::clean_terms(vocabulary.glossary),
+    };
+    settings::save(&conn, "vocabulary", &vocabulary)?;
+    Ok("Saved".to_string())
+}
+
+#[tauri::command]
+fn init_db(state: State<'_, AppState>, jobs: State<'_, Jobs>) -> Result<String, String> {
+    let home = dirs::home_dir().ok_or("No Home")?;
+    let db_path = home.join(".construction-os").join("construction.db");
+    if let Some(parent) = db_path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let mut conn = open_db_at(&db_path)?;
+    let version = migrations::run(&mut conn)?;
+    println!("DEBUG: Database ready at schema version {}", version);
+    if let Err(e) = tidy_recordings(&conn) {
+        println!("WARNING: Recording housekeeping failed: {}", e);
+    }
+    *state.db_path.lock().unwrap() = db_path.to_string_lossy().to_string();
+    // Start on jobs left from the last session.
+    jobs.wake();
+    Ok("Ready".to_string())
+}
+
+// --- JOB QUEUE ---
+
+// Saves a recording to the inbox and queues it for analysis, so it is
+// processed once a model or the network is available. The drafts arrive with
+// the "done" job-progress event.
+#[tauri::command]
+fn queue_recording(
+    app: AppHandle,
+    audio_data: Vec<u8>,
+    state: State<'_, AppState>,
+    jobs: State<'_, Jobs>,
+) -> Result<Job, String> {
+    let path = inbox_path(audio::file_extension(&audio_data))?;
+    fs::write(&path, &audio_data).map_err(|e| e.to_string())?;
+    let conn = open_db(&state)?;
+    let recording_id = recordings::register(&conn, &path.to_string_lossy())?;
+    let job = jobs::enqueue(
+        &conn,
+        &app,
+        jobs::ANALYZE,
+        &json!({ "recording_id": recording_id }),
+    )?;
+    jobs.wake();
+    Ok(job)
+}
+
+// Queues an ANALYZE or TRANSCRIBE job for a recording, or a SYNC job for an
+// invoice; `payload` names it as "recording_id" or "invoice_id".
+#[tauri::command]
+fn enqueue_job(
+    app: AppHandle,
+    kind: String,
+    payload: Value,
+    state: State<'_, AppState>,
+    jobs: State<'_, Jobs>,
+) -> Result<Job, String> {
+    let conn = open_db(&state)?;
+    let job = jobs::enqueue(&conn, &app, &kind, &payload)?;
+    jobs.wake();
+    Ok(job)
+}
+
+#[tauri::command]
+fn get_jobs(include_done: Option<bool>, state: State<'_, AppState>) -> Result<Vec<Job>, String> {
+    let conn = open_db(&state)?;
+    jobs::list(&conn, include_done.unwrap_or(false))
+}
+
+#[tauri::command]
+fn retry_job(
+    id: String,
+    state: State<'_, AppState>,
+    jobs: State<'_, Jobs>,
+) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    jobs::retry(&conn, &id)?;
+    jobs.wake();
+    Ok("Queued".to_string())
+}
+
+#[tauri::command]
+fn cancel_job(id: String, state: State<'_, AppState>) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    jobs::cancel(&conn, &id)?;
+    Ok("Deleted".to_string())
+}
+
+// Whether the configured cloud provider and webhook answer; changes also
+// arrive as "connectivity-changed" events.
+#[tauri::command]
+async fn get_connectivity(app: AppHandle) -> Result<bool, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let ai_settings: AiSettings = load_setting_or_default(&app, "ai");
+        let sync: SyncSettings = load_setting_or_default(&app, "sync");
+        let endpoints = [
+            ai_settings
+                .endpoint()
+                .filter(|_| ai_settings.voice_mode != VoiceMode::Local),
+            sync.webhook_url,
+        ];
+        let jobs = app.state::<Jobs>();
+        endpoints
+            .iter()
+            .flatten()
+            .all(|url| jobs.is_reachable(&app, url))
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_sync_settings(state: State<'_, AppState>) -> Result<SyncSettings, String> {
+    let conn = open_db(&state)?;
+    settings::load(&conn, "sync")
+}
+
+#[tauri::command]
+fn update_sync_settings(
+    mut sync: SyncSettings,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    sync.webhook_url = sync
+        .webhook_url
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty());
+    if let Some(url) = &sync.webhook_url {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err("Webhook URL must start with http:// or https://".to_string());
+        }
+    }
+    let conn = open_db(&state)?;
+    settings::save(&conn, "sync", &sync)?;
+    Ok("Saved".to_string())
+}
+
+// Runs on the job worker thread.
+fn run_job(app: &AppHandle, job: &Job) -> Result<Value, String> {
+    match job.kind.as_str() {
+        jobs::ANALYZE | jobs::TRANSCRIBE => {
+            let recording = {
+                let state = app.state::<AppState>();
+                let conn = open_db(&state)?;
+                recordings::get(&conn, job.payload_str("recording_id")?)?
+            };
+            let path = recording.path;
+            let audio_data = fs::read(&path).map_err(|e| e.to_string())?;
+            if job.kind == jobs::TRANSCRIBE {
+                let (_, transcription) = transcribe_only(app, &path, &audio_data)?;
+                return serde_json::to_value(transcription).map_err(|e| e.to_string());
+            }
+            let drafts = process_recording(app, &path, &audio_data, || {
+                transcribe_recording(app, &path, &audio_data)
+            })?;
+            Ok(Value::Array(drafts))
+        }
+        jobs::SYNC => {
+            let (invoice, sync) = {
+                let state = app.state::<AppState>();
+                let conn = open_db(&state)?;
+                let invoice = load_invoice(&conn, job.payload_str("invoice_id")?)?;
+                let sync: SyncSettings = settings::load(&conn, "sync")?;
+                (invoice, sync)
+            };
+            let url = sync.webhook_url.ok_or("No sync webhook configured")?;
+            let body = serde_json::to_value(&invoice).map_err(|e| e.to_string())?;
+            match ureq::post(&url).send_json(body) {
+                Ok(_) => Ok(json!({ "invoice_id": invoice.id })),
+                Err(ureq::Error::Status(code, _)) => Err(format!("Webhook returned {}", code)),
+                Err(e) => Err(format!("Network Error: {}", e)),
+            }
+        }
+        other => Err(format!("Unknown job kind '{}'", other)),
+    }
+}
+
+// Only cloud analysis and syncing have to reach a server; local
+// transcription and parsing run offline.
+fn job_endpoint(app: &AppHandle, job: &Job) -> Option<String> {
+    match job.kind.as_str() {
+        jobs::ANALYZE => {
+            let ai_settings: AiSettings = load_setting_or_default(app, "ai");
+            if ai_settings.voice_mode == VoiceMode::Cloud {
+                ai_settings.endpoint()
+            } else {
+                None
+            }
+        }
+        jobs::SYNC => load_setting_or_default::<SyncSettings>(app, "sync").webhook_url,
+        _ => None,
+    }
 }
 
-#[tauri::command]
-fn init_db(state: State<'_, AppState>) -> Result<String, String> {
-    let home = dirs::home_dir().ok_or("No Home")?;
-    let db_path = home.join(".construction-os").join("construction.db");
-    if let Some(parent) = db_path.parent() {
-        let _ = fs::create_dir_all(parent);
+// Start-up housekeeping: fails recordings interrupted by the last shutdown
+// and applies the retention policy.
+fn tidy_recordings(conn: &Connection) -> Result<(), String> {
+    let interrupted = recordings::fail_interrupted(conn)?;
+    let retention: RetentionSettings = settings::load(conn, "recordings")?;
+    let (archived, purged) = recordings::apply_retention(conn, &retention, &archive_dir()?)?;
+    if interrupted + archived + purged > 0 {
+        println!(
+            "DEBUG: Recordings: {} interrupted, {} archived, {} purged",
+            interrupted, archived, purged
+        );
     }
-    let conn = Connection::open(&db_path).map_err(|e| e.to_string())?;
-    conn.execute("CREATE TABLE IF NOT EXISTS invoices (id TEXT PRIMARY KEY, client TEXT, amount REAL, status TEXT, description TEXT, client_phone TEXT, client_company TEXT)", []).map_err(|e| e.to_string())?;
-    let _ = conn.execute("ALTER TABLE invoices ADD COLUMN description TEXT", []);
-    let _ = conn.execute("ALTER TABLE invoices ADD COLUMN client_phone TEXT", []);
-    let _ = conn.execute("ALTER TABLE invoices ADD COLUMN client_company TEXT", []);
-    conn.execute("CREATE TABLE IF NOT EXISTS tasks (id TEXT PRIMARY KEY, description TEXT, status TEXT, created_at TEXT, due_date TEXT)", []).map_err(|e| e.to_string())?;
-    let _ = conn.execute("ALTER TABLE tasks ADD COLUMN created_at TEXT", []);
-    let _ = conn.execute("ALTER TABLE tasks ADD COLUMN due_date TEXT", []);
-    conn.execute("CREATE TABLE IF NOT EXISTS contacts (id TEXT PRIMARY KEY, name TEXT, phone TEXT, company TEXT, created_at TEXT)", []).map_err(|e| e.to_string())?;
-    conn.execute("CREATE TABLE IF NOT EXISTS expenses (id TEXT PRIMARY KEY, merchant TEXT, amount REAL, category TEXT, date TEXT, image_path TEXT, status TEXT)", []).map_err(|e| e.to_string())?;
-    conn.execute("CREATE TABLE IF NOT EXISTS calendar_events (id TEXT PRIMARY KEY, title TEXT, start_time TEXT, duration_minutes INTEGER)", []).map_err(|e| e.to_string())?;
-    *state.db_path.lock().unwrap() = db_path.to_string_lossy().to_string();
-    Ok("Ready".to_string())
+    Ok(())
 }
 
 #[tauri::command]
 fn get_invoices(state: State<'_, AppState>) -> Result<Vec<Invoice>, String> {
-    let path_guard = state.db_path.lock().unwrap();
-    let conn = Connection::open(path_guard.as_str()).map_err(|e| e.to_string())?;
+    let conn = open_db(&state)?;
+    load_invoices(&conn)
+}
+
+fn load_invoice(conn: &Connection, id: &str) -> Result<Invoice, String> {
+    query_invoices(conn, Some(id))?
+        .pop()
+        .ok_or_else(|| format!("Invoice {} not found", id))
+}
+
+fn load_invoices(conn: &Connection) -> Result<Vec<Invoice>, String> {
+    query_invoices(conn, None)
+}
+
+// Every invoice, newest first, or just the one with `id`.
+fn query_invoices(conn: &Connection, id: Option<&str>) -> Result<Vec<Invoice>, String> {
     let home = dirs::home_dir().ok_or("No Home")?;
-    let mut stmt = conn.prepare("SELECT id, client, amount, status, COALESCE(description, ''), COALESCE(client_phone, ''), COALESCE(client_company, '') FROM invoices ORDER BY rowid DESC").map_err(|e| e.to_string())?;
+    let filter = if id.is_some() { "WHERE id = ?1" } else { "" };
+    let mut items_by_invoice = load_invoice_items(conn, id)?;
+    let mut stmt = conn.prepare(&format!("SELECT id, client, amount_cents, status, COALESCE(description, ''), COALESCE(client_phone, ''), COALESCE(client_company, ''), tax_rate, number, created_at, estimate_id FROM invoices {} ORDER BY created_at DESC, rowid DESC", filter)).map_err(|e| e.to_string())?;
 
     let rows = stmt
-        .query_map([], |row| {
+        .query_map(rusqlite::params_from_iter(id), |row| {
             let id: String = row.get(0)?;
             let pdf_path = home
                 .join(".construction-os")
                 .join("invoices")
                 .join(format!("{}.pdf", id));
             Ok(Invoice {
-                id: id,
+                id,
                 client: row.get(1)?,
                 amount: row.get(2)?,
                 status: row.get(3)?,
@@ -515,22 +1641,65 @@ fn get_invoices(state: State<'_, AppState>) -> Result<Vec<Invoice>, String> {
                 } else {
                     None
                 },
+                items: Vec::new(),
+                tax_rate: row.get(7)?,
+                subtotal: Money::ZERO,
+                tax: Money::ZERO,
+                number: row.get(8)?,
+                created_at: row.get(9)?,
+                estimate_id: row.get(10)?,
             })
         })
         .map_err(|e| e.to_string())?;
     let mut results = Vec::new();
     for row in rows {
-        results.push(row.map_err(|e| e.to_string())?);
+        let mut invoice = row.map_err(|e| e.to_string())?;
+        invoice.items = items_by_invoice.remove(&invoice.id).unwrap_or_default();
+        invoice.apply_item_totals();
+        results.push(invoice);
     }
     Ok(results)
 }
 
+fn load_invoice_items(
+    conn: &Connection,
+    invoice_id: Option<&str>,
+) -> Result<HashMap<String, Vec<InvoiceItem>>, String> {
+    let filter = if invoice_id.is_some() {
+        "WHERE invoice_id = ?1"
+    } else {
+        ""
+    };
+    let mut stmt = conn
+        .prepare(&format!("SELECT invoice_id, description, quantity, unit, unit_price_cents, taxable FROM invoice_items {} ORDER BY invoice_id, position", filter))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(invoice_id), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                InvoiceItem {
+                    description: row.get(1)?,
+                    quantity: row.get(2)?,
+                    unit: row.get(3)?,
+                    unit_price: row.get(4)?,
+                    taxable: row.get(5)?,
+                },
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    let mut items: HashMap<String, Vec<InvoiceItem>> = HashMap::new();
+    for row in rows {
+        let (invoice_id, item) = row.map_err(|e| e.to_string())?;
+        items.entry(invoice_id).or_default().push(item);
+    }
+    Ok(items)
+}
+
 #[tauri::command]
 fn get_calendar_events(state: State<'_, AppState>) -> Result<Vec<CalendarEvent>, String> {
-    let path_guard = state.db_path.lock().unwrap();
-    let conn = Connection::open(path_guard.as_str()).map_err(|e| e.to_string())?;
+    let conn = open_db(&state)?;
     let mut stmt = conn
-        .prepare("SELECT id, title, start_time, duration_minutes FROM calendar_events ORDER BY start_time ASC")
+        .prepare("SELECT id, title, start_time, duration_minutes, contact_id FROM calendar_events ORDER BY start_time ASC")
         .map_err(|e| e.to_string())?;
     let rows = stmt
         .query_map([], |row| {
@@ -539,6 +1708,7 @@ fn get_calendar_events(state: State<'_, AppState>) -> Result<Vec<CalendarEvent>,
                 title: row.get(1)?,
                 start_time: row.get(2)?,
                 duration_minutes: row.get(3)?,
+                contact_id: row.get(4)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -551,8 +1721,7 @@ fn get_calendar_events(state: State<'_, AppState>) -> Result<Vec<CalendarEvent>,
 
 #[tauri::command]
 fn get_tasks(state: State<'_, AppState>) -> Result<Vec<Task>, String> {
-    let path_guard = state.db_path.lock().unwrap();
-    let conn = Connection::open(path_guard.as_str()).map_err(|e| e.to_string())?;
+    let conn = open_db(&state)?;
     let mut stmt = conn
         .prepare(
             "SELECT id, description, status, created_at, due_date FROM tasks ORDER BY rowid DESC",
@@ -578,10 +1747,9 @@ fn get_tasks(state: State<'_, AppState>) -> Result<Vec<Task>, String> {
 
 #[tauri::command]
 fn get_contacts(state: State<'_, AppState>) -> Result<Vec<Contact>, String> {
-    let path_guard = state.db_path.lock().unwrap();
-    let conn = Connection::open(path_guard.as_str()).map_err(|e| e.to_string())?;
+    let conn = open_db(&state)?;
     let mut stmt = conn
-        .prepare("SELECT id, name, phone, company, created_at FROM contacts ORDER BY rowid DESC")
+        .prepare("SELECT id, name, phone, company, created_at, template_id FROM contacts ORDER BY rowid DESC")
         .map_err(|e| e.to_string())?;
     let rows = stmt
         .query_map([], |row| {
@@ -591,6 +1759,7 @@ fn get_contacts(state: State<'_, AppState>) -> Result<Vec<Contact>, String> {
                 phone: row.get(2)?,
                 company: row.get(3)?,
                 created_at: row.get(4)?,
+                template_id: row.get(5)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -603,9 +1772,8 @@ fn get_contacts(state: State<'_, AppState>) -> Result<Vec<Contact>, String> {
 
 #[tauri::command]
 fn get_expenses(state: State<'_, AppState>) -> Result<Vec<Expense>, String> {
-    let path_guard = state.db_path.lock().unwrap();
-    let conn = Connection::open(path_guard.as_str()).map_err(|e| e.to_string())?;
-    let mut stmt = conn.prepare("SELECT id, merchant, amount, category, date, image_path, status FROM expenses ORDER BY rowid DESC").map_err(|e| e.to_string())?;
+    let conn = open_db(&state)?;
+    let mut stmt = conn.prepare("SELECT id, merchant, amount_cents, category, date, image_path, status, created_at FROM expenses ORDER BY rowid DESC").map_err(|e| e.to_string())?;
     let rows = stmt
         .query_map([], |row| {
             Ok(Expense {
@@ -616,6 +1784,7 @@ fn get_expenses(state: State<'_, AppState>) -> Result<Vec<Expense>, String> {
                 date: row.get(4)?,
                 image_path: row.get(5).ok(),
                 status: row.get(6)?,
+                created_at: row.get(7)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -628,16 +1797,19 @@ fn get_expenses(state: State<'_, AppState>) -> Result<Vec<Expense>, String> {
 
 #[tauri::command]
 fn get_financial_summary(state: State<'_, AppState>) -> Result<FinancialSummary, String> {
-    let path_guard = state.db_path.lock().unwrap();
-    let conn = Connection::open(path_guard.as_str()).map_err(|e| e.to_string())?;
+    let conn = open_db(&state)?;
     let mut stmt = conn
-        .prepare("SELECT COALESCE(SUM(amount), 0.0) FROM invoices WHERE status != 'DRAFT'")
+        .prepare("SELECT COALESCE(SUM(amount_cents), 0) FROM invoices WHERE status != 'DRAFT'")
+        .map_err(|e| e.to_string())?;
+    let revenue: Money = stmt
+        .query_row([], |row| row.get(0))
         .map_err(|e| e.to_string())?;
-    let revenue: f64 = stmt.query_row([], |row| row.get(0)).unwrap_or(0.0);
     let mut stmt_exp = conn
-        .prepare("SELECT COALESCE(SUM(amount), 0.0) FROM expenses WHERE status != 'DRAFT'")
+        .prepare("SELECT COALESCE(SUM(amount_cents), 0) FROM expenses WHERE status != 'DRAFT'")
+        .map_err(|e| e.to_string())?;
+    let expenses: Money = stmt_exp
+        .query_row([], |row| row.get(0))
         .map_err(|e| e.to_string())?;
-    let expenses: f64 = stmt_exp.query_row([], |row| row.get(0)).unwrap_or(0.0);
     Ok(FinancialSummary {
         revenue,
         expenses,
@@ -645,6 +1817,14 @@ fn get_financial_summary(state: State<'_, AppState>) -> Result<FinancialSummary,
     })
 }
 
+fn created_timestamp(created_at: &Option<String>) -> i64 {
+    created_at
+        .as_deref()
+        .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+        .map(|d| d.timestamp())
+        .unwrap_or(0)
+}
+
 #[tauri::command]
 fn get_recent_activity(state: State<'_, AppState>) -> Result<Vec<ActivityItem>, String> {
     let mut activity = Vec::new();
@@ -653,19 +1833,11 @@ fn get_recent_activity(state: State<'_, AppState>) -> Result<Vec<ActivityItem>,
         if inv.status == "DRAFT" {
             continue;
         }
-        let parts: Vec<&str> = inv.id.split('-').collect();
-        let ts = if parts.len() > 1 {
-            parts[1].parse::<i64>().unwrap_or(0)
-        } else {
-            0
-        };
-        let date_str = if ts > 0 {
-            chrono::DateTime::<Utc>::from_timestamp(ts as i64, 0)
-                .map(|d| d.with_timezone(&Local).format("%Y-%m-%d").to_string())
-                .unwrap_or("Unknown".to_string())
-        } else {
-            "Unknown".to_string()
-        };
+        let ts = created_timestamp(&inv.created_at);
+        let date_str = chrono::DateTime::<Utc>::from_timestamp(ts, 0)
+            .filter(|_| ts > 0)
+            .map(|d| d.with_timezone(&Local).format("%Y-%m-%d").to_string())
+            .unwrap_or("Unknown".to_string());
         activity.push(ActivityItem {
             id: inv.id,
             intent: "INVOICE".to_string(),
@@ -681,41 +1853,72 @@ fn get_recent_activity(state: State<'_, AppState>) -> Result<Vec<ActivityItem>,
         if exp.status == "DRAFT" {
             continue;
         }
-        let parts: Vec<&str> = exp.id.split('-').collect();
-        let ts = if parts.len() > 1 {
-            parts[1].parse::<i64>().unwrap_or(0)
-        } else {
-            0
-        };
         activity.push(ActivityItem {
+            timestamp: created_timestamp(&exp.created_at),
             id: exp.id,
             intent: "EXPENSE".to_string(),
             description: format!("{} ({})", exp.merchant, exp.category),
             amount: exp.amount,
             date: exp.date,
-            timestamp: ts,
             file_path: None,
         });
     }
-    activity.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    activity.sort_by_key(|item| std::cmp::Reverse(item.timestamp));
     Ok(activity.into_iter().take(5).collect())
 }
 
 #[tauri::command]
-fn save_audio_blob(audio_data: Vec<u8>) -> Result<String, String> {
+fn get_invoice_numbering(
+    state: State<'_, AppState>,
+) -> Result<numbering::NumberingSettings, String> {
+    let conn = open_db(&state)?;
+    numbering::settings(&conn, "invoice")
+}
+
+#[tauri::command]
+fn update_invoice_numbering(
+    settings: numbering::NumberingSettings,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    numbering::update_settings(&conn, "invoice", &settings)?;
+    Ok("Saved".to_string())
+}
+
+#[tauri::command]
+fn save_audio_blob(audio_data: Vec<u8>, state: State<'_, AppState>) -> Result<String, String> {
+    let path = inbox_path(audio::file_extension(&audio_data))?;
+    fs::write(&path, audio_data).map_err(|e| e.to_string())?;
+    let path = path.to_string_lossy().to_string();
+    let conn = open_db(&state)?;
+    recordings::register(&conn, &path)?;
+    Ok(path)
+}
+
+fn inbox_dir() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("No Home")?;
+    Ok(home.join(".construction-os").join("inbox"))
+}
+
+fn archive_dir() -> Result<PathBuf, String> {
     let home = dirs::home_dir().ok_or("No Home")?;
-    let path = home.join(".construction-os").join("inbox").join(format!(
-        "voice_{}.webm",
+    Ok(home.join(".construction-os").join("archive"))
+}
+
+// ~/.construction-os/inbox/voice_{ms}.{extension}, creating the folder.
+fn inbox_path(extension: &str) -> Result<PathBuf, String> {
+    let path = inbox_dir()?.join(format!(
+        "voice_{}.{}",
         std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
-            .as_millis()
+            .as_millis(),
+        extension
     ));
     if let Some(parent) = path.parent() {
         let _ = fs::create_dir_all(parent);
     }
-    fs::write(&path, audio_data).map_err(|e| e.to_string())?;
-    Ok(path.to_string_lossy().to_string())
+    Ok(path)
 }
 
 #[tauri::command]
@@ -743,107 +1946,569 @@ fn save_image(image_data: String) -> Result<String, String> {
     Ok(path.to_string_lossy().to_string())
 }
 
+// Recordings in the inbox, newest first, with archived ones on request.
+// Files saved without a row (e.g. before tracking existed) are picked up.
 #[tauri::command]
-fn get_recordings() -> Result<Vec<String>, String> {
-    let home = dirs::home_dir().ok_or("No Home")?;
-    let inbox = home.join(".construction-os").join("inbox");
-    let mut files = Vec::new();
-    if inbox.exists() {
-        for entry in fs::read_dir(inbox).map_err(|e| e.to_string())? {
-            let path = entry.map_err(|e| e.to_string())?.path();
-            if path.extension().and_then(|s| s.to_str()) == Some("webm") {
-                files.push(path.to_string_lossy().to_string());
-            }
+fn get_recordings(
+    include_archived: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Recording>, String> {
+    let conn = open_db(&state)?;
+    recordings::register_untracked(&conn, &inbox_dir()?)?;
+    recordings::list(&conn, include_archived.unwrap_or(false))
+}
+
+// Runs a recording through transcription and analysis again, e.g. after a
+// failure or a model change. Returns the new drafts.
+#[tauri::command]
+async fn reprocess_recording(app: AppHandle, id: String) -> Result<Vec<Value>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let recording = {
+            let state = app.state::<AppState>();
+            let conn = open_db(&state)?;
+            recordings::get(&conn, &id)?
+        };
+        if recording.status == recordings::PROCESSING {
+            return Err("Recording is already being processed".to_string());
         }
-    }
-    Ok(files)
+        let path = recording.path;
+        let audio_data = fs::read(&path).map_err(|e| e.to_string())?;
+        process_recording(&app, &path, &audio_data, || {
+            transcribe_recording(&app, &path, &audio_data)
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
-async fn save_invoice_pdf(id: String, pdf_data: String) -> Result<String, String> {
-    let home = dirs::home_dir().ok_or("No Home directory found")?;
-    let folder_path = home.join(".construction-os").join("invoices");
+fn archive_recording(id: String, state: State<'_, AppState>) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    recordings::archive(&conn, &id, &archive_dir()?)?;
+    Ok("Archived".to_string())
+}
+
+// Deletes the audio file and its row; drafts made from it are kept.
+#[tauri::command]
+fn purge_recording(id: String, state: State<'_, AppState>) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    recordings::purge(&conn, &id)?;
+    Ok("Deleted".to_string())
+}
+
+#[tauri::command]
+fn get_recording_retention(state: State<'_, AppState>) -> Result<RetentionSettings, String> {
+    let conn = open_db(&state)?;
+    settings::load(&conn, "recordings")
+}
 
-    if !folder_path.exists() {
-        tokio::fs::create_dir_all(&folder_path)
-            .await
-            .map_err(|e| e.to_string())?;
+// Saves the policy and applies it straight away.
+#[tauri::command]
+fn update_recording_retention(
+    retention: RetentionSettings,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    if retention.archive_after_days == Some(0) || retention.purge_after_days == Some(0) {
+        return Err("Retention periods must be at least one day".to_string());
     }
+    let conn = open_db(&state)?;
+    settings::save(&conn, "recordings", &retention)?;
+    recordings::apply_retention(&conn, &retention, &archive_dir()?)?;
+    Ok("Saved".to_string())
+}
+
+// Renders the saved invoice with the client's template to
+// ~/.construction-os/invoices/{id}.pdf and returns the path. Everything comes
+// from the database, so only the ID crosses IPC.
+#[tauri::command]
+async fn save_invoice_pdf(app: AppHandle, id: String) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let conn = open_db(&state)?;
+        let invoice = load_invoice(&conn, &id)?;
+        let company: CompanyProfile = settings::load(&conn, "company")?;
+        let template = templates::for_client(&conn, &invoice.client, &company)?;
+        let client = document_client(
+            &conn,
+            &invoice.client,
+            invoice.client_phone.as_deref(),
+            invoice.client_company.as_deref(),
+        );
+        let document = Document::invoice(&invoice, client, &company);
+        let title = format!("Invoice {}", document.number);
+        write_document_pdf(
+            &template,
+            &document,
+            &company,
+            title.trim(),
+            "invoices",
+            &id,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// The client block of a document, filling in details the record was saved
+// without from the contact.
+fn document_client(
+    conn: &Connection,
+    name: &str,
+    phone: Option<&str>,
+    company: Option<&str>,
+) -> templates::Client {
+    let contact = find_contact_details(conn, name);
+    let (contact_phone, contact_company) = match &contact {
+        Some((phone, company)) => (Some(phone.as_str()), company.as_deref()),
+        None => (None, None),
+    };
+    templates::Client::new(
+        name,
+        non_empty(company).or_else(|| non_empty(contact_company)),
+        non_empty(phone).or_else(|| non_empty(contact_phone)),
+    )
+}
+
+// Renders `document` and writes it to ~/.construction-os/{folder}/{id}.pdf.
+fn write_document_pdf(
+    template: &Template,
+    document: &Document,
+    company: &CompanyProfile,
+    title: &str,
+    folder: &str,
+    id: &str,
+) -> Result<String, String> {
+    let markup = templates::render(template, document)?;
+    let bytes = pdf::render(&markup, title, company::load_logo(company).as_ref())?;
 
+    let home = dirs::home_dir().ok_or("No Home directory found")?;
+    let folder_path = home.join(".construction-os").join(folder);
+    fs::create_dir_all(&folder_path).map_err(|e| e.to_string())?;
     let file_path = folder_path.join(format!("{}.pdf", id));
+    fs::write(&file_path, bytes).map_err(|e| format!("File write error: {}", e))?;
 
-    println!("DEBUG: Received PDF data request for ID: {}", id);
+    println!(
+        "DEBUG: Saved PDF to {:?} using template '{}'",
+        file_path, template.name
+    );
+    Ok(file_path.to_string_lossy().to_string())
+}
 
-    // Remove data URI prefix if present
-    let clean_base64 = if let Some(index) = pdf_data.find(',') {
-        &pdf_data[index + 1..]
-    } else {
-        &pdf_data
-    };
+// --- ESTIMATES ---
 
+#[tauri::command]
+fn confirm_estimate(mut estimate: Estimate, state: State<'_, AppState>) -> Result<String, String> {
     println!(
-        "DEBUG: Processing PDF data of length: {}",
-        clean_base64.len()
+        "DEBUG: Attempting to confirm estimate for client: {}",
+        estimate.client
     );
+    check_record_id(&estimate.id)?;
+    estimate.apply_item_totals();
+    let mut conn = open_db(&state)?;
+    link_client_details(
+        &conn,
+        &estimate.client,
+        &mut estimate.client_phone,
+        &mut estimate.client_company,
+    );
+    estimate
+        .valid_until
+        .get_or_insert_with(estimates::default_valid_until);
 
-    let decoded = general_purpose::STANDARD
-        .decode(clean_base64)
-        .map_err(|e| format!("Base64 decode error: {}", e))?;
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| e.to_string())?;
+    let number = estimates::insert(&tx, &estimate)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    println!("DEBUG: Estimate {} saved as {}", estimate.id, number);
+    Ok(number)
+}
+
+#[tauri::command]
+fn get_estimates(state: State<'_, AppState>) -> Result<Vec<Estimate>, String> {
+    let conn = open_db(&state)?;
+    estimates::load_all(&conn)
+}
 
-    tokio::fs::write(&file_path, decoded)
-        .await
-        .map_err(|e| format!("File write error: {}", e))?;
+#[tauri::command]
+fn update_estimate(mut estimate: Estimate, state: State<'_, AppState>) -> Result<String, String> {
+    estimate.apply_item_totals();
+    let mut conn = open_db(&state)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    estimates::update(&tx, &estimate)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok("Updated".to_string())
+}
 
-    println!("DEBUG: Saved PDF to {:?}", file_path);
-    Ok(file_path.to_string_lossy().to_string())
+// ACCEPTED or DECLINED once the customer answers; DRAFT or SENT to undo.
+#[tauri::command]
+fn set_estimate_status(
+    id: String,
+    status: String,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    estimates::set_status(&conn, &id, &status)?;
+    Ok("Updated".to_string())
+}
+
+#[tauri::command]
+fn delete_estimate(id: String, state: State<'_, AppState>) -> Result<String, String> {
+    let mut conn = open_db(&state)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let affected = estimates::delete(&tx, &id)?;
+    ensure_found(affected, "Estimate", &id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok("Deleted".to_string())
+}
+
+// Turns the estimate into an invoice with the same client and items, marks
+// it accepted and links the two. Each estimate converts once.
+#[tauri::command]
+fn convert_estimate_to_invoice(
+    app: AppHandle,
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<Invoice, String> {
+    let mut conn = open_db(&state)?;
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| e.to_string())?;
+    let (invoice_id, number) = invoice_estimate(&tx, &id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    println!("DEBUG: Estimate {} converted to invoice {}", id, number);
+
+    queue_invoice_sync(&conn, &app, &invoice_id)?;
+    load_invoice(&conn, &invoice_id)
+}
+
+// The conversion itself; call inside a transaction. Returns the new invoice's
+// ID and number. An expired quote has to be re-dated before it can be
+// accepted at its price.
+fn invoice_estimate(conn: &Connection, id: &str) -> Result<(String, String), String> {
+    let estimate = estimates::load(conn, id)?;
+    let label = estimate.number.clone().unwrap_or_else(|| id.to_string());
+    if estimate.invoice_id.is_some() {
+        return Err(format!("Estimate {} has already been invoiced", label));
+    }
+    if estimate.status == estimates::DECLINED {
+        return Err(format!("Estimate {} was declined", label));
+    }
+    if estimate.expired {
+        return Err(format!(
+            "Estimate {} expired on {}; update its expiry date before invoicing it",
+            label,
+            estimate.valid_until.unwrap_or_default()
+        ));
+    }
+
+    let invoice = Invoice {
+        id: new_id(),
+        client: estimate.client,
+        amount: estimate.amount,
+        status: "GENERATED".to_string(),
+        description: estimate.description,
+        client_phone: estimate.client_phone,
+        client_company: estimate.client_company,
+        pdf_path: None,
+        items: estimate.items,
+        tax_rate: estimate.tax_rate,
+        subtotal: estimate.subtotal,
+        tax: estimate.tax,
+        number: None,
+        created_at: Some(Local::now().to_rfc3339()),
+        estimate_id: Some(id.to_string()),
+    };
+    let number = insert_invoice(conn, &invoice, &invoice.status)?;
+    estimates::link_invoice(conn, id, &invoice.id)?;
+    Ok((invoice.id, number))
+}
+
+#[tauri::command]
+async fn save_estimate_pdf(app: AppHandle, id: String) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let conn = open_db(&state)?;
+        let estimate = estimates::load(&conn, &id)?;
+        let company: CompanyProfile = settings::load(&conn, "company")?;
+        let template = templates::for_client(&conn, &estimate.client, &company)?;
+        let client = document_client(
+            &conn,
+            &estimate.client,
+            estimate.client_phone.as_deref(),
+            estimate.client_company.as_deref(),
+        );
+        let document = Document::estimate(&estimate, client, &company);
+        let title = format!("Estimate {}", document.number);
+        write_document_pdf(
+            &template,
+            &document,
+            &company,
+            title.trim(),
+            "estimates",
+            &id,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn get_estimate_numbering(
+    state: State<'_, AppState>,
+) -> Result<numbering::NumberingSettings, String> {
+    let conn = open_db(&state)?;
+    numbering::settings(&conn, "estimate")
+}
+
+#[tauri::command]
+fn update_estimate_numbering(
+    settings: numbering::NumberingSettings,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    numbering::update_settings(&conn, "estimate", &settings)?;
+    Ok("Saved".to_string())
+}
+
+// --- COMPANY PROFILE & TEMPLATES ---
+
+#[tauri::command]
+fn get_company_profile(state: State<'_, AppState>) -> Result<CompanyProfile, String> {
+    let conn = open_db(&state)?;
+    settings::load(&conn, "company")
+}
+
+#[tauri::command]
+fn update_company_profile(
+    profile: CompanyProfile,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    if let Some(template_id) = &profile.template_id {
+        if !templates::exists(&conn, template_id)? {
+            return Err(format!("Template {} not found", template_id));
+        }
+    }
+    settings::save(&conn, "company", &profile)?;
+    Ok("Saved".to_string())
+}
+
+// Stores the uploaded image as the company logo and returns its path.
+#[tauri::command]
+async fn set_company_logo(app: AppHandle, logo_data: Vec<u8>) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let home = dirs::home_dir().ok_or("No Home")?;
+        let path = company::save_logo(&logo_data, &home.join(".construction-os").join("branding"))?;
+        let path = path.to_string_lossy().to_string();
+
+        let state = app.state::<AppState>();
+        let conn = open_db(&state)?;
+        let mut profile: CompanyProfile = settings::load(&conn, "company")?;
+        profile.logo_path = Some(path.clone());
+        settings::save(&conn, "company", &profile)?;
+        Ok(path)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn get_templates(state: State<'_, AppState>) -> Result<Vec<Template>, String> {
+    let conn = open_db(&state)?;
+    templates::list(&conn)
+}
+
+// Creates or updates a custom template and returns its ID.
+#[tauri::command]
+fn save_template(template: Template, state: State<'_, AppState>) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    templates::save(&conn, &template)
+}
+
+#[tauri::command]
+fn delete_template(id: String, state: State<'_, AppState>) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    templates::delete(&conn, &id)?;
+    let mut company: CompanyProfile = settings::load(&conn, "company")?;
+    if company.template_id.as_deref() == Some(id.as_str()) {
+        company.template_id = None;
+        settings::save(&conn, "company", &company)?;
+    }
+    Ok("Deleted".to_string())
+}
+
+// Picks the template for a client's documents; `None` goes back to the
+// company default.
+#[tauri::command]
+fn set_contact_template(
+    contact_id: String,
+    template_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let conn = open_db(&state)?;
+    if let Some(template_id) = &template_id {
+        if !templates::exists(&conn, template_id)? {
+            return Err(format!("Template {} not found", template_id));
+        }
+    }
+    let updated = conn
+        .execute(
+            "UPDATE contacts SET template_id = ?1 WHERE id = ?2",
+            params![template_id, contact_id],
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Contact {} not found", contact_id));
+    }
+    Ok("Updated".to_string())
+}
+
+#[tauri::command]
+async fn transcribe_audio(
+    app: AppHandle,
+    audio_data: Vec<u8>,
+) -> Result<TranscribedRecording, String> {
+    // Inference takes seconds; keep it off the async runtime's threads.
+    tauri::async_runtime::spawn_blocking(move || {
+        let path = inbox_path(audio::file_extension(&audio_data))?;
+        fs::write(&path, &audio_data).map_err(|e| e.to_string())?;
+        let path = path.to_string_lossy();
+        let (recording_id, transcription) = transcribe_only(&app, &path, &audio_data)?;
+        Ok(TranscribedRecording {
+            recording_id,
+            transcription,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+struct TranscribedRecording {
+    recording_id: String,
+    #[serde(flatten)]
+    transcription: Transcription,
+}
+
+// Stores a transcript against the recording at `path`, replacing any earlier
+// one and tracking the file if it is not yet. Returns the recording ID.
+fn save_transcript(
+    app: &AppHandle,
+    path: &str,
+    transcription: &Transcription,
+) -> Result<String, String> {
+    let state = app.state::<AppState>();
+    let conn = open_db(&state)?;
+    let id = recordings::register(&conn, path)?;
+    recordings::save_transcript(&conn, &id, transcription)?;
+    Ok(id)
+}
+
+// Transcribes without drafting anything, which finishes the recording: it is
+// PROCESSED with its transcript (analyze_transcript links drafts made from it
+// later) or FAILED. Returns the recording ID with the transcript.
+fn transcribe_only(
+    app: &AppHandle,
+    path: &str,
+    audio_data: &[u8],
+) -> Result<(String, Transcription), String> {
+    let state = app.state::<AppState>();
+    let id = {
+        let conn = open_db(&state)?;
+        let id = recordings::register(&conn, path)?;
+        recordings::set_status(&conn, &id, recordings::PROCESSING, None)?;
+        id
+    };
+    let transcription = transcribe_recording(app, path, audio_data);
+    let mut conn = open_db(&state)?;
+    match transcription {
+        Ok(transcription) => {
+            recordings::save_transcript(&conn, &id, &transcription)?;
+            recordings::set_processed(&mut conn, &id, &[])?;
+            Ok((id, transcription))
+        }
+        Err(e) => {
+            recordings::set_status(&conn, &id, recordings::FAILED, Some(&e))?;
+            Err(e)
+        }
+    }
+}
+
+// Best effort; a recording without a duration is still usable.
+fn record_duration(app: &AppHandle, path: &str, samples: usize) {
+    let duration_ms = (samples as u64 * 1000 / audio::SAMPLE_RATE as u64) as i64;
+    let result = open_db(&app.state::<AppState>()).and_then(|conn| {
+        let id = recordings::register(&conn, path)?;
+        recordings::set_duration(&conn, &id, duration_ms)
+    });
+    if let Err(e) = result {
+        println!("WARNING: Failed to save recording duration: {}", e);
+    }
 }
 
+// The stored transcript of a recording, or `None` if it has not been
+// transcribed on this device.
 #[tauri::command]
-async fn transcribe_audio(app: AppHandle, audio_data: Vec<u8>) -> Result<String, String> {
+fn get_transcript(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Option<Transcription>, String> {
+    let conn = open_db(&state)?;
+    recordings::transcript(&conn, &path)
+}
+
+// `recording` is the file `audio_data` was read from.
+fn transcribe_recording(
+    app: &AppHandle,
+    recording: &str,
+    audio_data: &[u8],
+) -> Result<Transcription, String> {
     println!("Rust: Received {} bytes of audio", audio_data.len());
-    // 1. Decode WAV to Samples (Critical Step)
-    let cursor = Cursor::new(audio_data);
-    let mut reader = hound::WavReader::new(cursor).map_err(|e| e.to_string())?;
-
-    // Convert to 16-bit Mono Samples for Whisper
-    let samples: Vec<i16> = reader.samples::<i16>().map(|s| s.unwrap_or(0)).collect();
-
-    // Convert i16 to f32 (Whisper expects floats between -1.0 and 1.0)
-    let audio_f32: Vec<f32> = samples.iter().map(|&s| s as f32 / 32768.0).collect();
-
-    // 2. Load Model
-    // Use your 'ensure_model_exists' function from Task 1 here to get the path
-    let model_path = crate::model_manager::ensure_model_exists(&app).map_err(|e| e.to_string())?;
-
-    let ctx = WhisperContext::new_with_params(&model_path, Default::default())
-        .map_err(|_| "Failed to load model".to_string())?;
-    let mut state = ctx.create_state().expect("failed to create state");
-
-    // 3. Configure Whisper Params
-    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
-    params.set_language(Some("en"));
-    params.set_print_special(false);
-    params.set_print_progress(false);
-
-    // 4. Run Inference
-    state
-        .full(params, &audio_f32[..])
-        .map_err(|_| "failed to run model".to_string())?;
-
-    // 5. Extract Text
-    let num_segments = state
-        .full_n_segments()
-        .map_err(|_| "failed to get segments".to_string())?;
-    let mut result = String::new();
-    for i in 0..num_segments {
-        let segment = state
-            .full_get_segment_text(i)
-            .map_err(|_| "failed to get text".to_string())?;
-        result.push_str(&segment);
-        result.push(' ');
-    }
-    println!("Rust: Transcription complete: '{}'", result.trim());
-    Ok(result.trim().to_string())
+    let samples = audio::decode(audio_data)?;
+    record_duration(app, recording, samples.len());
+    transcribe_samples(app, &samples)
+}
+
+// `samples` are 16 kHz mono, as produced by `audio::decode`. Only the parts
+// that contain speech are sent to Whisper, one chunk at a time.
+fn transcribe_samples(app: &AppHandle, samples: &[f32]) -> Result<Transcription, String> {
+    let chunks = vad::speech_chunks(samples);
+    if chunks.is_empty() {
+        println!("DEBUG: No speech detected in {} samples", samples.len());
+        return Ok(Transcription::NoSpeech);
+    }
+    let transcription = load_transcription_settings(app);
+    let (model_path, multilingual) =
+        model_manager::resolve(app, &transcription.model, &load_imported_models(app))?;
+    let vocabulary = load_vocabulary(app);
+    let prompt = vocabulary.prompt();
+    let engine = app.state::<WhisperEngine>();
+    let mut segments = Vec::new();
+    for chunk in chunks {
+        let offset_ms = (chunk.start as u64 * 1000 / audio::SAMPLE_RATE as u64) as i64;
+        let chunk_segments = engine.transcribe(
+            &model_path,
+            transcription.language_for(multilingual),
+            prompt.as_deref(),
+            &samples[chunk],
+        )?;
+        segments.extend(chunk_segments.into_iter().map(|mut segment| {
+            segment.start_ms += offset_ms;
+            segment.end_ms += offset_ms;
+            segment
+        }));
+    }
+    vocabulary.snap_names(&mut segments);
+    let text = segments
+        .iter()
+        .map(|segment| segment.text.as_str())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    println!("Rust: Transcription complete: '{}'", text);
+    Ok(Transcription::Speech { text, segments })
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -852,27 +2517,69 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .setup(|app| {
-            match model_manager::ensure_model_exists(app.handle()) {
-                Ok(path) => println!("Whisper model ready at: {}", path),
-                Err(e) => println!("Warning: Failed to ensure Whisper model: {}", e),
+            // Fetch a first model in the background so startup never waits on
+            // the network; progress is reported through download events.
+            let handle = app.handle().clone();
+            if !model_manager::any_installed(&handle) {
+                tauri::async_runtime::spawn_blocking(move || {
+                    match model_manager::ensure_model_exists(&handle, model_manager::DEFAULT_MODEL)
+                    {
+                        Ok(path) => println!("Whisper model ready at: {}", path),
+                        Err(e) => println!("Warning: Failed to ensure Whisper model: {}", e),
+                    }
+                });
             }
+            jobs::spawn_worker(
+                app.handle().clone(),
+                jobs::Handler {
+                    run: run_job,
+                    endpoint: job_endpoint,
+                },
+            );
             Ok(())
         })
         .manage(AppState {
             db_path: Mutex::new(String::new()),
         })
+        .manage(WhisperEngine::default())
+        .manage(Recorder::default())
+        .manage(Streaming::default())
+        .manage(Jobs::default())
         .invoke_handler(tauri::generate_handler![
             init_db,
             save_audio_blob,
             save_image,
             get_recordings,
             analyze_audio,
+            analyze_transcript,
+            list_input_devices,
+            start_recording,
+            stop_recording,
+            list_models,
+            select_model,
+            download_model,
+            delete_model,
+            import_model,
+            get_transcription_settings,
+            update_transcription_settings,
+            get_vocabulary,
+            update_vocabulary,
             analyze_image,
             confirm_invoice,
             confirm_task,
             confirm_contact,
             confirm_expense,
             confirm_calendar_event,
+            update_invoice,
+            update_task,
+            update_contact,
+            update_expense,
+            update_calendar_event,
+            delete_invoice,
+            delete_task,
+            delete_contact,
+            delete_expense,
+            delete_calendar_event,
             save_invoice_pdf,
             get_invoices,
             get_tasks,
@@ -881,10 +2588,249 @@ pub fn run() {
             get_calendar_events,
             get_financial_summary,
             get_recent_activity,
+            get_invoice_numbering,
+            update_invoice_numbering,
+            get_ai_settings,
+            update_ai_settings,
             open_system_link,
             open_invoice_pdf,
-            transcribe_audio
+            confirm_estimate,
+            get_estimates,
+            update_estimate,
+            set_estimate_status,
+            delete_estimate,
+            convert_estimate_to_invoice,
+            save_estimate_pdf,
+            open_estimate_pdf,
+            get_estimate_numbering,
+            update_estimate_numbering,
+            transcribe_audio,
+            get_transcript,
+            reprocess_recording,
+            archive_recording,
+            purge_recording,
+            get_recording_retention,
+            update_recording_retention,
+            queue_recording,
+            enqueue_job,
+            get_jobs,
+            retry_job,
+            cancel_job,
+            get_connectivity,
+            get_sync_settings,
+            update_sync_settings,
+            get_company_profile,
+            update_company_profile,
+            set_company_logo,
+            get_templates,
+            save_template,
+            delete_template,
+            set_contact_template
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::test_db;
+
+    // Answers with `replies` in order and keeps the prompts it was sent.
+    struct Scripted {
+        replies: Mutex<Vec<&'static str>>,
+        prompts: Mutex<Vec<String>>,
+    }
+
+    impl Scripted {
+        fn new(replies: &[&'static str]) -> Self {
+            Scripted {
+                replies: Mutex::new(replies.iter().rev().copied().collect()),
+                prompts: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl LlmProvider for Scripted {
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+
+        fn accepts(&self, _mime_type: &str) -> bool {
+            true
+        }
+
+        fn complete(
+            &self,
+            system_prompt: &str,
+            _input: &LlmInput,
+            _schema: &Value,
+        ) -> Result<String, String> {
+            self.prompts.lock().unwrap().push(system_prompt.to_string());
+            self.replies
+                .lock()
+                .unwrap()
+                .pop()
+                .map(str::to_string)
+                .ok_or_else(|| "no more replies".to_string())
+        }
+    }
+
+    fn ask(provider: &Scripted) -> Result<Vec<Analysis>, String> {
+        ask_ai(
+            provider,
+            "PROMPT",
+            &LlmInput::Text("invoice for the deck"),
+            &["INVOICE"],
+        )
+    }
+
+    #[test]
+    fn complete_replies_are_used_without_repair() {
+        let provider = Scripted::new(&[
+            r#"{"intents": [{"intent": "INVOICE", "client": "Dave", "amount": 400, "description": "Deck"}]}"#,
+        ]);
+        let analyses = ask(&provider).unwrap();
+        assert_eq!(analyses.len(), 1);
+        assert!(analyses[0].guessed.is_empty());
+        assert_eq!(provider.prompts.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn repaired_reply_is_used_and_remaining_gaps_are_reported() {
+        let provider = Scripted::new(&[
+            r#"{"intents": [{"intent": "INVOICE", "description": "Deck"}]}"#,
+            r#"{"intents": [{"intent": "INVOICE", "client": "Dave", "description": "Deck"}]}"#,
+        ]);
+        let analyses = ask(&provider).unwrap();
+        assert_eq!(
+            analyses[0].intent,
+            ParsedIntent::Invoice {
+                client: Some("Dave".to_string()),
+                amount: None,
+                description: Some("Deck".to_string()),
+            }
+        );
+        assert_eq!(analyses[0].guessed, vec!["amount"]);
+
+        let prompts = provider.prompts.lock().unwrap();
+        assert_eq!(prompts.len(), 2);
+        assert!(prompts[1].starts_with("PROMPT"));
+        assert!(
+            prompts[1].contains("item 1 was missing client, amount"),
+            "{}",
+            prompts[1]
+        );
+    }
+
+    #[test]
+    fn a_worse_or_broken_repair_keeps_the_first_reply() {
+        let first =
+            r#"{"intents": [{"intent": "INVOICE", "client": "Dave", "description": "Deck"}]}"#;
+        for second in [r#"{"intents": [{"intent": "INVOICE"}]}"#, "Sorry, no."] {
+            let analyses = ask(&Scripted::new(&[first, second])).unwrap();
+            assert_eq!(analyses[0].guessed, vec!["amount"]);
+        }
+        let Err(e) = ask(&Scripted::new(&["nope", "still nope"])) else {
+            panic!("bad JSON was accepted");
+        };
+        assert!(e.contains("bad JSON"), "{}", e);
+    }
+
+    fn estimate(conn: &Connection, id: &str, valid_until: &str) {
+        let mut estimate: Estimate = serde_json::from_value(json!({
+            "id": id,
+            "client": "Ann",
+            "description": "Deck",
+            "tax_rate": 10,
+            "valid_until": valid_until,
+            "items": [
+                {"description": "Boards", "quantity": 20, "unit": "each", "unit_price": 15, "taxable": true},
+                {"description": "Labour", "quantity": 2, "unit": "day", "unit_price": 400}
+            ]
+        }))
+        .unwrap();
+        estimate.apply_item_totals();
+        estimates::insert(conn, &estimate).unwrap();
+    }
+
+    #[test]
+    fn record_ids_must_be_uuids() {
+        assert!(check_record_id(&new_id()).is_ok());
+        assert_eq!(
+            check_record_id("INV-48213").unwrap_err(),
+            "Record ID 'INV-48213' is not a UUID"
+        );
+        assert!(check_record_id("").is_err());
+    }
+
+    #[test]
+    fn converts_an_estimate_once() {
+        let conn = test_db();
+        estimate(&conn, "e1", &estimates::default_valid_until());
+        let (invoice_id, number) = invoice_estimate(&conn, "e1").unwrap();
+        assert_eq!(number, "INV-0001");
+
+        let invoice = load_invoice(&conn, &invoice_id).unwrap();
+        let quoted = estimates::load(&conn, "e1").unwrap();
+        assert_eq!(invoice.estimate_id.as_deref(), Some("e1"));
+        assert_eq!(quoted.invoice_id.as_deref(), Some(invoice_id.as_str()));
+        assert_eq!(quoted.status, estimates::ACCEPTED);
+        assert_eq!(invoice.items.len(), 2);
+        assert_eq!(invoice.items[0].description, "Boards");
+        assert_eq!(invoice.items[1].unit.as_deref(), Some("day"));
+        assert_eq!(invoice.amount, quoted.amount);
+
+        assert!(invoice_estimate(&conn, "e1")
+            .unwrap_err()
+            .contains("already been invoiced"));
+    }
+
+    #[test]
+    fn declined_and_expired_estimates_are_not_converted() {
+        let conn = test_db();
+        estimate(&conn, "declined", &estimates::default_valid_until());
+        estimates::set_status(&conn, "declined", estimates::DECLINED).unwrap();
+        assert!(invoice_estimate(&conn, "declined")
+            .unwrap_err()
+            .contains("declined"));
+
+        estimate(&conn, "stale", "2020-01-31");
+        assert!(invoice_estimate(&conn, "stale")
+            .unwrap_err()
+            .contains("expired"));
+        assert_eq!(
+            estimates::load(&conn, "stale").unwrap().status,
+            estimates::DRAFT
+        );
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM invoices", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn records_load_by_id_with_only_their_own_items() {
+        let conn = test_db();
+        estimate(&conn, "e1", &estimates::default_valid_until());
+        estimate(&conn, "e2", &estimates::default_valid_until());
+        let (invoice_id, _) = invoice_estimate(&conn, "e2").unwrap();
+
+        let quoted = estimates::load(&conn, "e1").unwrap();
+        assert_eq!(quoted.id, "e1");
+        assert_eq!(quoted.items.len(), 2);
+        assert_eq!(estimates::load_all(&conn).unwrap().len(), 2);
+        let invoice = load_invoice(&conn, &invoice_id).unwrap();
+        assert_eq!(invoice.items.len(), 2);
+        assert_eq!(load_invoices(&conn).unwrap().len(), 1);
+
+        assert_eq!(
+            estimates::load(&conn, "missing").unwrap_err(),
+            "Estimate missing not found"
+        );
+        assert_eq!(
+            load_invoice(&conn, "missing").unwrap_err(),
+            "Invoice missing not found"
+        );
+    }
+}
@@ -0,0 +1,435 @@
+use serde_json::{json, Value};
+use std::env;
+
+// Vendor-neutral access to the language model that turns recordings, photos
+// and transcripts into structured drafts. `analyze_audio` and `analyze_image`
+// only see the `LlmProvider` trait; which implementation backs it is chosen
+// by the "ai" entry in `app_settings`.
+
+pub enum LlmInput<'a> {
+    // Base64 encoded media such as "audio/webm" or "image/jpeg".
+    Media { mime_type: &'a str, data: &'a str },
+    // Plain text, e.g. a local Whisper transcript.
+    Text(&'a str),
+}
+
+pub trait LlmProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Whether this provider can take `mime_type` as direct input.
+    fn accepts(&self, mime_type: &str) -> bool;
+
+    // Sends the prompt and returns the model's raw text reply. Providers with
+    // a structured output mode constrain the reply to `schema`.
+    fn complete(
+        &self,
+        system_prompt: &str,
+        input: &LlmInput,
+        schema: &Value,
+    ) -> Result<String, String>;
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    #[default]
+    Gemini,
+    // OpenAI's chat completions API, also served by Groq, llama.cpp and Ollama.
+    OpenaiCompatible,
+    Mock,
+}
+
+// How voice notes are turned into drafts. `Local` never leaves the device:
+// Whisper transcribes and `intent_parser` extracts the fields.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum VoiceMode {
+    #[default]
+    Local,
+    // Local first; the cloud provider only sees recordings the device could
+    // not transcribe or transcripts the parser could not classify.
+    LocalWithCloudFallback,
+    Cloud,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+pub struct AiSettings {
+    #[serde(default)]
+    pub provider: ProviderKind,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub voice_mode: VoiceMode,
+}
+
+const GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+impl AiSettings {
+    // The server the provider talks to; None for the offline mock.
+    pub fn endpoint(&self) -> Option<String> {
+        match self.provider {
+            ProviderKind::Gemini => Some(
+                self.base_url
+                    .clone()
+                    .unwrap_or_else(|| GEMINI_BASE_URL.to_string()),
+            ),
+            ProviderKind::OpenaiCompatible => self.base_url.clone(),
+            ProviderKind::Mock => None,
+        }
+    }
+}
+
+pub fn provider_from_settings(settings: &AiSettings) -> Result<Box<dyn LlmProvider>, String> {
+    build_provider(settings, |name| env::var(name).ok())
+}
+
+// `var` looks up environment variables, so tests need not touch the real ones.
+fn build_provider(
+    settings: &AiSettings,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<Box<dyn LlmProvider>, String> {
+    let api_key = settings.api_key.clone().filter(|k| !k.trim().is_empty());
+    match settings.provider {
+        ProviderKind::Gemini => {
+            let api_key = api_key
+                .or_else(|| var("GEMINI_API_KEY"))
+                .or_else(|| {
+                    // Older builds read the Gemini key from this misnamed variable.
+                    let legacy = var("VITE_GROQ_API_KEY");
+                    if legacy.is_some() {
+                        println!("WARNING: Using VITE_GROQ_API_KEY as the Gemini key; please rename it to GEMINI_API_KEY");
+                    }
+                    legacy
+                })
+                .ok_or("Gemini API key not configured (set it in Settings or GEMINI_API_KEY)")?;
+            Ok(Box::new(GeminiProvider {
+                api_key,
+                model: settings
+                    .model
+                    .clone()
+                    .unwrap_or_else(|| "gemini-2.5-flash".to_string()),
+                base_url: settings
+                    .base_url
+                    .clone()
+                    .unwrap_or_else(|| GEMINI_BASE_URL.to_string()),
+            }))
+        }
+        ProviderKind::OpenaiCompatible => {
+            let base_url = settings.base_url.clone().ok_or(
+                "OpenAI-compatible provider needs a base URL (e.g. http://localhost:11434/v1)",
+            )?;
+            let model = settings
+                .model
+                .clone()
+                .ok_or("OpenAI-compatible provider needs a model name")?;
+            let api_key = api_key
+                .or_else(|| var("LLM_API_KEY"))
+                .or_else(|| var("OPENAI_API_KEY"));
+            // Servers on this machine (llama.cpp, Ollama) usually run without one.
+            if api_key.is_none() && !is_local(&base_url) {
+                return Err(format!(
+                    "OpenAI-compatible provider at {} needs an API key (set it in Settings or LLM_API_KEY)",
+                    base_url
+                ));
+            }
+            Ok(Box::new(OpenAiCompatibleProvider {
+                api_key,
+                model,
+                base_url,
+            }))
+        }
+        ProviderKind::Mock => Ok(Box::new(MockProvider)),
+    }
+}
+
+fn is_local(base_url: &str) -> bool {
+    let host = base_url
+        .split("://")
+        .nth(1)
+        .unwrap_or(base_url)
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+fn post_json(url: &str, bearer: Option<&str>, body: Value) -> Result<Value, String> {
+    let mut request = ureq::post(url);
+    if let Some(key) = bearer {
+        request = request.set("Authorization", &format!("Bearer {}", key));
+    }
+    let response = match request.send_json(body) {
+        Ok(res) => res,
+        Err(ureq::Error::Status(code, response)) => {
+            let body = response
+                .into_string()
+                .unwrap_or_else(|_| "No error body".to_string());
+            return Err(format!("API Error {}: {}", code, body));
+        }
+        Err(e) => return Err(format!("Network Error: {}", e)),
+    };
+    response
+        .into_json()
+        .map_err(|e| format!("JSON Error: {}", e))
+}
+
+// --- GEMINI ---
+
+pub struct GeminiProvider {
+    api_key: String,
+    model: String,
+    base_url: String,
+}
+
+impl LlmProvider for GeminiProvider {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn accepts(&self, mime_type: &str) -> bool {
+        mime_type.starts_with("audio/") || mime_type.starts_with("image/")
+    }
+
+    fn complete(
+        &self,
+        system_prompt: &str,
+        input: &LlmInput,
+        schema: &Value,
+    ) -> Result<String, String> {
+        let input_part = match input {
+            LlmInput::Media { mime_type, data } => {
+                json!({ "inline_data": { "mime_type": mime_type, "data": data } })
+            }
+            LlmInput::Text(text) => json!({ "text": text }),
+        };
+        let prompt = json!({
+            "contents": [{
+                "parts": [
+                    { "text": system_prompt },
+                    input_part
+                ]
+            }],
+            "generationConfig": {
+                "responseMimeType": "application/json",
+                "responseSchema": schema
+            }
+        });
+        let url = format!(
+            "{}/models/{}:generateContent?key={}",
+            self.base_url.trim_end_matches('/'),
+            self.model,
+            self.api_key
+        );
+        let response_body = post_json(&url, None, prompt)?;
+        response_body["candidates"][0]["content"]["parts"][0]["text"]
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| "AI returned no text".to_string())
+    }
+}
+
+// --- OPENAI-COMPATIBLE ---
+
+pub struct OpenAiCompatibleProvider {
+    api_key: Option<String>,
+    model: String,
+    base_url: String,
+}
+
+impl LlmProvider for OpenAiCompatibleProvider {
+    fn name(&self) -> &'static str {
+        "openai_compatible"
+    }
+
+    fn accepts(&self, mime_type: &str) -> bool {
+        mime_type.starts_with("image/")
+    }
+
+    fn complete(
+        &self,
+        system_prompt: &str,
+        input: &LlmInput,
+        schema: &Value,
+    ) -> Result<String, String> {
+        let user_content = match input {
+            LlmInput::Media { mime_type, data } => {
+                if !self.accepts(mime_type) {
+                    return Err(format!(
+                        "The {} provider cannot read {} input",
+                        self.name(),
+                        mime_type
+                    ));
+                }
+                json!([{
+                    "type": "image_url",
+                    "image_url": { "url": format!("data:{};base64,{}", mime_type, data) }
+                }])
+            }
+            LlmInput::Text(text) => json!(text),
+        };
+        let body = json!({
+            "model": self.model,
+            "messages": [
+                { "role": "system", "content": system_prompt },
+                { "role": "user", "content": user_content }
+            ],
+            "response_format": {
+                "type": "json_schema",
+                "json_schema": { "name": "parsed_intent", "schema": schema }
+            }
+        });
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        let response_body = post_json(&url, self.api_key.as_deref(), body)?;
+        response_body["choices"][0]["message"]["content"]
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| "AI returned no text".to_string())
+    }
+}
+
+// --- MOCK ---
+
+// Deterministic stand-in for demos and offline development: receipts become a
+// fixed expense, everything else a fixed invoice.
+pub struct MockProvider;
+
+impl LlmProvider for MockProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn accepts(&self, _mime_type: &str) -> bool {
+        true
+    }
+
+    fn complete(
+        &self,
+        system_prompt: &str,
+        input: &LlmInput,
+        _schema: &Value,
+    ) -> Result<String, String> {
+        let is_receipt = matches!(input, LlmInput::Media { mime_type, .. } if mime_type.starts_with("image/"))
+            || system_prompt.contains("RECEIPT");
+        let reply = if is_receipt {
+            json!({ "intent": "EXPENSE", "merchant": "Mock Lumber Co", "amount": 42.5, "date": "2024-01-01", "category": "Materials" })
+        } else {
+            json!({ "intent": "INVOICE", "client": "Mock Client", "amount": 400, "description": "Deck repair" })
+        };
+        Ok(reply.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intents::{self, ParsedIntent};
+
+    fn settings(provider: ProviderKind) -> AiSettings {
+        AiSettings {
+            provider,
+            ..Default::default()
+        }
+    }
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    fn name(provider: Result<Box<dyn LlmProvider>, String>) -> Result<&'static str, String> {
+        provider.map(|provider| provider.name())
+    }
+
+    #[test]
+    fn gemini_needs_a_key() {
+        let mut gemini = settings(ProviderKind::Gemini);
+        assert!(name(build_provider(&gemini, no_env))
+            .unwrap_err()
+            .contains("API key"));
+        gemini.api_key = Some("  ".to_string());
+        assert!(build_provider(&gemini, no_env).is_err());
+
+        let from_env = |name: &str| (name == "GEMINI_API_KEY").then(|| "env-key".to_string());
+        assert_eq!(name(build_provider(&gemini, from_env)), Ok("gemini"));
+        gemini.api_key = Some("key".to_string());
+        assert_eq!(name(build_provider(&gemini, no_env)), Ok("gemini"));
+    }
+
+    #[test]
+    fn openai_compatible_needs_a_url_a_model_and_a_remote_key() {
+        let mut openai = settings(ProviderKind::OpenaiCompatible);
+        assert!(name(build_provider(&openai, no_env))
+            .unwrap_err()
+            .contains("base URL"));
+        openai.base_url = Some("https://api.openai.com/v1".to_string());
+        assert!(name(build_provider(&openai, no_env))
+            .unwrap_err()
+            .contains("model"));
+        openai.model = Some("gpt-4o-mini".to_string());
+        assert!(name(build_provider(&openai, no_env))
+            .unwrap_err()
+            .contains("API key"));
+        let from_env = |name: &str| (name == "OPENAI_API_KEY").then(|| "env-key".to_string());
+        assert_eq!(
+            name(build_provider(&openai, from_env)),
+            Ok("openai_compatible")
+        );
+
+        for local in [
+            "http://localhost:11434/v1",
+            "http://127.0.0.1:8080/v1",
+            "http://[::1]:8080",
+        ] {
+            openai.base_url = Some(local.to_string());
+            assert_eq!(
+                name(build_provider(&openai, no_env)),
+                Ok("openai_compatible"),
+                "{}",
+                local
+            );
+        }
+        openai.base_url = Some("http://localhost.example.com/v1".to_string());
+        assert!(build_provider(&openai, no_env).is_err());
+    }
+
+    #[test]
+    fn mock_answers_receipts_with_an_expense_and_the_rest_with_an_invoice() {
+        let mock = build_provider(&settings(ProviderKind::Mock), no_env).unwrap();
+        assert_eq!(mock.name(), "mock");
+        let schema = json!({});
+        let reply = |prompt: &str, input: &LlmInput| {
+            intents::decode(&mock.complete(prompt, input, &schema).unwrap()).unwrap()
+        };
+        let photo = LlmInput::Media {
+            mime_type: "image/jpeg",
+            data: "",
+        };
+        let audio = LlmInput::Media {
+            mime_type: "audio/webm",
+            data: "",
+        };
+        assert!(matches!(
+            reply("Read this", &photo)[0],
+            ParsedIntent::Expense { .. }
+        ));
+        assert!(matches!(
+            reply("Read this RECEIPT", &LlmInput::Text("Home Depot 42.50"))[0],
+            ParsedIntent::Expense { .. }
+        ));
+        assert!(matches!(
+            reply("Voice note", &audio)[0],
+            ParsedIntent::Invoice { .. }
+        ));
+        assert!(matches!(
+            reply("Voice note", &LlmInput::Text("invoice Dave 400"))[0],
+            ParsedIntent::Invoice { .. }
+        ));
+    }
+}
@@ -0,0 +1,208 @@
+use rusqlite::{Connection, Transaction};
+
+// Schema version is tracked in SQLite's `PRAGMA user_version`.
+// Migrations are applied in order, each inside its own transaction, so a
+// failure leaves the database at the last fully applied version.
+
+enum Step {
+    Sql(&'static str),
+    Rust(fn(&Transaction) -> rusqlite::Result<()>),
+}
+
+struct Migration {
+    version: i64,
+    name: &'static str,
+    step: Step,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "baseline",
+        step: Step::Sql(include_str!("migrations/0001_baseline.sql")),
+    },
+    Migration {
+        version: 2,
+        name: "legacy_columns",
+        step: Step::Rust(legacy_columns),
+    },
+    Migration {
+        version: 3,
+        name: "invoice_items",
+        step: Step::Sql(include_str!("migrations/0003_invoice_items.sql")),
+    },
+    Migration {
+        version: 4,
+        name: "money_cents",
+        step: Step::Sql(include_str!("migrations/0004_money_cents.sql")),
+    },
+    Migration {
+        version: 5,
+        name: "invoice_numbering",
+        step: Step::Sql(include_str!("migrations/0005_invoice_numbering.sql")),
+    },
+    Migration {
+        version: 6,
+        name: "app_settings",
+        step: Step::Sql(include_str!("migrations/0006_app_settings.sql")),
+    },
+    Migration {
+        version: 7,
+        name: "calendar_contacts",
+        step: Step::Sql(include_str!("migrations/0007_calendar_contacts.sql")),
+    },
+    Migration {
+        version: 8,
+        name: "recordings",
+        step: Step::Sql(include_str!("migrations/0008_recordings.sql")),
+    },
+    Migration {
+        version: 9,
+        name: "recording_inbox",
+        step: Step::Sql(include_str!("migrations/0009_recording_inbox.sql")),
+    },
+    Migration {
+        version: 10,
+        name: "jobs",
+        step: Step::Sql(include_str!("migrations/0010_jobs.sql")),
+    },
+    Migration {
+        version: 11,
+        name: "document_templates",
+        step: Step::Sql(include_str!("migrations/0011_document_templates.sql")),
+    },
+    Migration {
+        version: 12,
+        name: "estimates",
+        step: Step::Sql(include_str!("migrations/0012_estimates.sql")),
+    },
+];
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+pub fn current_version(conn: &Connection) -> Result<i64, String> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| format!("Failed to read schema version: {}", e))
+}
+
+pub fn run(conn: &mut Connection) -> Result<i64, String> {
+    let start = current_version(conn)?;
+    let latest = latest_version();
+
+    if start > latest {
+        return Err(format!(
+            "Database schema version {} is newer than this app supports ({}). Please update ConstructionOS.",
+            start, latest
+        ));
+    }
+
+    let mut version = start;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > start) {
+        println!(
+            "DEBUG: Applying migration {} ({})",
+            migration.version, migration.name
+        );
+        apply(conn, migration).map_err(|e| {
+            let err = format!(
+                "Database migration {} ({}) failed: {}",
+                migration.version, migration.name, e
+            );
+            println!("ERROR: {}", err);
+            err
+        })?;
+        version = migration.version;
+    }
+
+    Ok(version)
+}
+
+fn apply(conn: &mut Connection, migration: &Migration) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    match migration.step {
+        Step::Sql(sql) => tx.execute_batch(sql)?,
+        Step::Rust(up) => up(&tx)?,
+    }
+    tx.pragma_update(None, "user_version", migration.version)?;
+    tx.commit()
+}
+
+fn has_column(tx: &Transaction, table: &str, column: &str) -> rusqlite::Result<bool> {
+    let mut stmt = tx.prepare(&format!("PRAGMA table_info({})", table))?;
+    let names = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    for name in names {
+        if name? == column {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn add_column_if_missing(
+    tx: &Transaction,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> rusqlite::Result<()> {
+    if !has_column(tx, table, column)? {
+        tx.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+// --- MIGRATIONS ---
+
+// Databases created before versioning may have the tables but lack columns
+// that used to be patched in with unchecked ALTER TABLE calls.
+fn legacy_columns(tx: &Transaction) -> rusqlite::Result<()> {
+    add_column_if_missing(tx, "invoices", "description", "TEXT")?;
+    add_column_if_missing(tx, "invoices", "client_phone", "TEXT")?;
+    add_column_if_missing(tx, "invoices", "client_company", "TEXT")?;
+    add_column_if_missing(tx, "tasks", "created_at", "TEXT")?;
+    add_column_if_missing(tx, "tasks", "due_date", "TEXT")?;
+    Ok(())
+}
+
+// A fully migrated in-memory database, with foreign keys on as in the app.
+#[cfg(test)]
+pub fn test_db() -> Connection {
+    let mut conn = crate::open_db_at(":memory:").unwrap();
+    run(&mut conn).unwrap();
+    conn
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fresh_database_reaches_latest_version() {
+        let mut conn = test_db();
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        // Running again applies nothing.
+        assert_eq!(run(&mut conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn refuses_newer_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+        assert!(run(&mut conn).unwrap_err().contains("newer"));
+    }
+
+    #[test]
+    fn legacy_columns_are_added_once() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE invoices (id TEXT PRIMARY KEY, client TEXT, amount REAL, status TEXT); CREATE TABLE tasks (id TEXT PRIMARY KEY, description TEXT, status TEXT);")
+            .unwrap();
+        run(&mut conn).unwrap();
+        let tx = conn.transaction().unwrap();
+        assert!(has_column(&tx, "invoices", "client_phone").unwrap());
+        assert!(has_column(&tx, "tasks", "due_date").unwrap());
+    }
+}
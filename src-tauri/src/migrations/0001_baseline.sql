@@ -0,0 +1,43 @@
+-- Schema as it existed before versioned migrations were introduced.
+CREATE TABLE IF NOT EXISTS invoices (
+    id TEXT PRIMARY KEY,
+    client TEXT,
+    amount REAL,
+    status TEXT,
+    description TEXT,
+    client_phone TEXT,
+    client_company TEXT
+);
+
+CREATE TABLE IF NOT EXISTS tasks (
+    id TEXT PRIMARY KEY,
+    description TEXT,
+    status TEXT,
+    created_at TEXT,
+    due_date TEXT
+);
+
+CREATE TABLE IF NOT EXISTS contacts (
+    id TEXT PRIMARY KEY,
+    name TEXT,
+    phone TEXT,
+    company TEXT,
+    created_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS expenses (
+    id TEXT PRIMARY KEY,
+    merchant TEXT,
+    amount REAL,
+    category TEXT,
+    date TEXT,
+    image_path TEXT,
+    status TEXT
+);
+
+CREATE TABLE IF NOT EXISTS calendar_events (
+    id TEXT PRIMARY KEY,
+    title TEXT,
+    start_time TEXT,
+    duration_minutes INTEGER
+);
@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS invoice_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    invoice_id TEXT NOT NULL REFERENCES invoices(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    quantity REAL NOT NULL DEFAULT 1,
+    unit TEXT,
+    unit_price REAL NOT NULL DEFAULT 0,
+    taxable INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS idx_invoice_items_invoice ON invoice_items(invoice_id, position);
+
+ALTER TABLE invoices ADD COLUMN tax_rate REAL NOT NULL DEFAULT 0;
+
+-- Existing invoices become a single line item so totals can always be derived from items.
+INSERT INTO invoice_items (invoice_id, position, description, quantity, unit_price, taxable)
+SELECT id, 0, COALESCE(NULLIF(description, ''), 'Services'), 1, COALESCE(amount, 0), 0
+FROM invoices;
@@ -0,0 +1,12 @@
+-- Money moves from REAL dollars to INTEGER cents.
+ALTER TABLE invoices ADD COLUMN amount_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE invoices SET amount_cents = CAST(ROUND(COALESCE(amount, 0) * 100) AS INTEGER);
+ALTER TABLE invoices DROP COLUMN amount;
+
+ALTER TABLE invoice_items ADD COLUMN unit_price_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE invoice_items SET unit_price_cents = CAST(ROUND(COALESCE(unit_price, 0) * 100) AS INTEGER);
+ALTER TABLE invoice_items DROP COLUMN unit_price;
+
+ALTER TABLE expenses ADD COLUMN amount_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE expenses SET amount_cents = CAST(ROUND(COALESCE(amount, 0) * 100) AS INTEGER);
+ALTER TABLE expenses DROP COLUMN amount;
@@ -0,0 +1,33 @@
+-- Customer-facing document numbers come from a per-kind sequence instead of the primary key.
+CREATE TABLE IF NOT EXISTS number_sequences (
+    kind TEXT PRIMARY KEY,
+    prefix TEXT NOT NULL,
+    next_value INTEGER NOT NULL DEFAULT 1,
+    yearly_reset INTEGER NOT NULL DEFAULT 0,
+    year INTEGER,
+    padding INTEGER NOT NULL DEFAULT 4
+);
+
+INSERT OR IGNORE INTO number_sequences (kind, prefix) VALUES ('invoice', 'INV-');
+
+-- Existing invoices keep the ID the customer already saw as their number.
+ALTER TABLE invoices ADD COLUMN number TEXT;
+UPDATE invoices SET number = id;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_invoices_number ON invoices(number);
+
+-- Real creation timestamps. Legacy IDs embedded unix seconds ("INV-1712345678"),
+-- so recover them one last time (short random IDs from the webview are not timestamps);
+-- anything else is stamped with the migration time.
+ALTER TABLE invoices ADD COLUMN created_at TEXT;
+UPDATE invoices SET created_at = CASE
+    WHEN id GLOB 'INV-[0-9]*' AND id NOT GLOB 'INV-*[^0-9]*' AND length(id) >= 13
+        THEN strftime('%Y-%m-%dT%H:%M:%SZ', CAST(substr(id, 5) AS INTEGER), 'unixepoch')
+    ELSE strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+END;
+
+ALTER TABLE expenses ADD COLUMN created_at TEXT;
+UPDATE expenses SET created_at = CASE
+    WHEN id GLOB 'EXP-[0-9]*' AND id NOT GLOB 'EXP-*[^0-9]*' AND length(id) >= 13
+        THEN strftime('%Y-%m-%dT%H:%M:%SZ', CAST(substr(id, 5) AS INTEGER), 'unixepoch')
+    ELSE strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+END;
@@ -0,0 +1,5 @@
+-- Key/value store for user preferences. Values are JSON documents.
+CREATE TABLE IF NOT EXISTS app_settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
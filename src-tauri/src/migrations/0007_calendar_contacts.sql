@@ -0,0 +1,2 @@
+-- Calendar events can be linked to the contact they are with.
+ALTER TABLE calendar_events ADD COLUMN contact_id TEXT REFERENCES contacts(id) ON DELETE SET NULL;
@@ -0,0 +1,9 @@
+-- Voice recordings in the inbox and what Whisper made of them.
+CREATE TABLE IF NOT EXISTS recordings (
+    id TEXT PRIMARY KEY,
+    path TEXT NOT NULL UNIQUE,
+    transcript TEXT,
+    -- JSON list of {start_ms, end_ms, text, probability, words}.
+    segments TEXT NOT NULL DEFAULT '[]',
+    created_at TEXT NOT NULL
+);
@@ -0,0 +1,21 @@
+-- Processing state for inbox recordings and the drafts each one produced.
+ALTER TABLE recordings ADD COLUMN duration_ms INTEGER;
+-- PENDING, PROCESSING, PROCESSED or FAILED.
+ALTER TABLE recordings ADD COLUMN status TEXT NOT NULL DEFAULT 'PENDING';
+ALTER TABLE recordings ADD COLUMN error TEXT;
+ALTER TABLE recordings ADD COLUMN processed_at TEXT;
+-- NULL until transcribed locally; a NULL transcript after that means no speech.
+ALTER TABLE recordings ADD COLUMN transcribed_at TEXT;
+-- Set when the file is moved out of the inbox.
+ALTER TABLE recordings ADD COLUMN archived_at TEXT;
+
+-- Rows so far were only written once a recording had been transcribed.
+UPDATE recordings SET status = 'PROCESSED', processed_at = created_at, transcribed_at = created_at;
+
+-- Draft IDs carry over to the invoice, task, contact or event once confirmed.
+CREATE TABLE IF NOT EXISTS recording_drafts (
+    recording_id TEXT NOT NULL REFERENCES recordings(id) ON DELETE CASCADE,
+    draft_id TEXT NOT NULL,
+    intent TEXT NOT NULL,
+    PRIMARY KEY (recording_id, draft_id)
+);
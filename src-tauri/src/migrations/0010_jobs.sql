@@ -0,0 +1,20 @@
+-- Background work that has to survive restarts and wait out offline spells.
+CREATE TABLE IF NOT EXISTS jobs (
+    id TEXT PRIMARY KEY,
+    -- ANALYZE, TRANSCRIBE or SYNC.
+    kind TEXT NOT NULL,
+    -- JSON, e.g. {"recording_id": "..."}.
+    payload TEXT NOT NULL,
+    -- PENDING, RUNNING, DONE or FAILED.
+    status TEXT NOT NULL DEFAULT 'PENDING',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    -- JSON, once DONE.
+    result TEXT,
+    -- Unix milliseconds; pending jobs wait until then (backoff).
+    run_after INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_jobs_due ON jobs(status, run_after);
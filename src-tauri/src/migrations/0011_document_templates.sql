@@ -0,0 +1,13 @@
+-- Custom layouts for invoices and estimates; the built-in ones live in code.
+CREATE TABLE IF NOT EXISTS document_templates (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    -- Handlebars source, see templates.rs.
+    body TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- Layout used for a client's documents; NULL for the company default. Not a
+-- foreign key since built-in template IDs have no row.
+ALTER TABLE contacts ADD COLUMN template_id TEXT;
@@ -0,0 +1,40 @@
+-- Quotes sent before the work is done; accepted ones become invoices.
+CREATE TABLE IF NOT EXISTS estimates (
+    id TEXT PRIMARY KEY,
+    number TEXT,
+    client TEXT NOT NULL,
+    client_phone TEXT,
+    client_company TEXT,
+    description TEXT,
+    amount_cents INTEGER NOT NULL DEFAULT 0,
+    tax_rate REAL NOT NULL DEFAULT 0,
+    -- DRAFT, SENT, ACCEPTED or DECLINED.
+    status TEXT NOT NULL DEFAULT 'DRAFT',
+    -- YYYY-MM-DD, the last day the price holds.
+    valid_until TEXT,
+    -- When it was accepted or declined.
+    decided_at TEXT,
+    -- The invoice it was converted into.
+    invoice_id TEXT REFERENCES invoices(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_estimates_number ON estimates(number);
+
+CREATE TABLE IF NOT EXISTS estimate_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    estimate_id TEXT NOT NULL REFERENCES estimates(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    quantity REAL NOT NULL DEFAULT 1,
+    unit TEXT,
+    unit_price_cents INTEGER NOT NULL DEFAULT 0,
+    taxable INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS idx_estimate_items_estimate ON estimate_items(estimate_id, position);
+
+INSERT OR IGNORE INTO number_sequences (kind, prefix) VALUES ('estimate', 'EST-');
+
+-- The estimate an invoice was converted from.
+ALTER TABLE invoices ADD COLUMN estimate_id TEXT REFERENCES estimates(id) ON DELETE SET NULL;
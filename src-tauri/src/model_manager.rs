@@ -1,64 +1,783 @@
-use std::fs;
-use std::io::Write;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use tauri::AppHandle;
-use tauri::Manager;
+use reqwest::header::{CONTENT_RANGE, RANGE};
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, Manager};
 
-const MODEL_URL: &str =
-    "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-base.en.bin";
-const MODEL_FILENAME: &str = "ggml-base.en.bin";
+const MODEL_BASE_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
+// Overrides MODEL_BASE_URL, e.g. to point at a local mirror or test server.
+const MODEL_BASE_URL_ENV: &str = "WHISPER_MODEL_BASE_URL";
+const PROGRESS_EVENT: &str = "model-download-progress";
 
-pub fn ensure_model_exists(app_handle: &AppHandle) -> Result<String, String> {
+// One download or import at a time: a second request for the same model waits
+// and then finds the file in place instead of writing into the same `.part`
+// file.
+static DOWNLOAD_LOCK: Mutex<()> = Mutex::new(());
+
+// Whisper models published by whisper.cpp. `.en` models are English-only and
+// a little more accurate for English; the others detect or take a language.
+// Quantized variants trade some accuracy for a third of the size.
+#[derive(serde::Serialize, Clone, Copy, Debug)]
+pub struct CatalogueModel {
+    pub id: &'static str,
+    pub size_mb: u32,
+    pub multilingual: bool,
+    pub quantized: bool,
+    // SHA-256 of the file, as published for the Hugging Face LFS object.
+    // Downloads (including from a `WHISPER_MODEL_BASE_URL` mirror) are only
+    // installed if they match.
+    pub sha256: &'static str,
+}
+
+const fn model(
+    id: &'static str,
+    size_mb: u32,
+    multilingual: bool,
+    quantized: bool,
+    sha256: &'static str,
+) -> CatalogueModel {
+    CatalogueModel {
+        id,
+        size_mb,
+        multilingual,
+        quantized,
+        sha256,
+    }
+}
+
+pub const CATALOGUE: &[CatalogueModel] = &[
+    model(
+        "tiny",
+        75,
+        true,
+        false,
+        "be07e048e1e599ad46341c8d2a135645097a538221678b7acdd1b1919c6e1b21",
+    ),
+    model(
+        "tiny.en",
+        75,
+        false,
+        false,
+        "921e4cf8686fdd993dcd081a5da5b6c365bfde1162e72b08d75ac75289920b1f",
+    ),
+    model(
+        "tiny-q5_1",
+        31,
+        true,
+        true,
+        "818710568da3ca15689e31a743197b520007872ff9576237bda97bd1b469c3d7",
+    ),
+    model(
+        "tiny.en-q5_1",
+        31,
+        false,
+        true,
+        "c77c5766f1cef09b6b7d47f21b546cbddd4157886b3b5d6d4f709e91e66c7c2b",
+    ),
+    model(
+        "base",
+        142,
+        true,
+        false,
+        "60ed5bc3dd14eea856493d334349b405782ddcaf0028d4b5df4088345fba2efe",
+    ),
+    model(
+        "base.en",
+        142,
+        false,
+        false,
+        "a03779c86df3323075f5e796cb2ce5029f00ec8869eee3fdfb897afe36c6d002",
+    ),
+    model(
+        "base-q5_1",
+        57,
+        true,
+        true,
+        "422f1ae452ade6f30a004d7e5c6a43195e4433bc370bf23fac9cc591f01a8898",
+    ),
+    model(
+        "base.en-q5_1",
+        57,
+        false,
+        true,
+        "4baf70dd0d7c4247ba2b81fafd9c01005ac77c2f9ef064e00dcf195d0e2fdd2f",
+    ),
+    model(
+        "small",
+        466,
+        true,
+        false,
+        "1be3a9b2063867b937e64e2ec7483364a79917e157fa98c5d94b5c1fffea987b",
+    ),
+    model(
+        "small.en",
+        466,
+        false,
+        false,
+        "c6138d6d58ecc8322097e0f987c32f1be8bb0a18532a3f88f734d1bbf9c41e5d",
+    ),
+    model(
+        "small-q5_1",
+        181,
+        true,
+        true,
+        "ae85e4a935d7a567bd102fe55afc16bb595bdb618e11b2fc7591bc08120411bb",
+    ),
+    model(
+        "small.en-q5_1",
+        181,
+        false,
+        true,
+        "bfdff4894dcb76bbf647d56263ea2a96645423f1669176f4844a1bf8e478ad30",
+    ),
+    model(
+        "medium",
+        1500,
+        true,
+        false,
+        "6c14d5adee5f86394037b4e4e8b59f1673b6cee10e3cf0b11bbdbee79c156208",
+    ),
+    model(
+        "medium.en",
+        1500,
+        false,
+        false,
+        "cc37e93478338ec7700281a7ac30a10128929eb8f427dda2e865faa8f6da4356",
+    ),
+    model(
+        "medium-q5_0",
+        514,
+        true,
+        true,
+        "19fea4b380c3a618ec4723c3eef2eb785ffba0d0538cf43f8f235e7b3b34220f",
+    ),
+    model(
+        "medium.en-q5_0",
+        514,
+        false,
+        true,
+        "76733e26ad8fe1c7a5bf7531a9d41917b2adc0f20f2e4f5531688a8c6cd88eb0",
+    ),
+];
+
+pub const DEFAULT_MODEL: &str = "base.en";
+
+impl CatalogueModel {
+    pub fn file_name(&self) -> String {
+        format!("ggml-{}.bin", self.id)
+    }
+}
+
+pub fn find(id: &str) -> Result<&'static CatalogueModel, String> {
+    CATALOGUE
+        .iter()
+        .find(|m| m.id == id)
+        .ok_or_else(|| format!("Unknown Whisper model '{}'", id))
+}
+
+// A model file the user brought in with `import`, kept under "imported_models"
+// in `app_settings`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct ImportedModel {
+    pub id: String,
+    pub file_name: String,
+    pub sha256: String,
+    pub size_mb: u32,
+    pub multilingual: bool,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct ModelStatus {
+    pub id: String,
+    pub size_mb: u32,
+    pub multilingual: bool,
+    pub quantized: bool,
+    pub imported: bool,
+    pub installed: bool,
+    pub selected: bool,
+}
+
+pub fn models_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
     let app_data_dir = app_handle
         .path()
         .app_data_dir()
         .map_err(|e| format!("Failed to resolve app data dir: {}", e))?;
+    Ok(app_data_dir.join("models"))
+}
+
+pub fn list(
+    app_handle: &AppHandle,
+    imported: &[ImportedModel],
+    selected: &str,
+) -> Result<Vec<ModelStatus>, String> {
+    let models_dir = models_dir(app_handle)?;
+    let catalogue = CATALOGUE.iter().map(|model| ModelStatus {
+        id: model.id.to_string(),
+        size_mb: model.size_mb,
+        multilingual: model.multilingual,
+        quantized: model.quantized,
+        imported: false,
+        installed: models_dir.join(model.file_name()).exists(),
+        selected: model.id == selected,
+    });
+    let imported = imported.iter().map(|model| ModelStatus {
+        id: model.id.clone(),
+        size_mb: model.size_mb,
+        multilingual: model.multilingual,
+        quantized: false,
+        imported: true,
+        installed: models_dir.join(&model.file_name).exists(),
+        selected: model.id == selected,
+    });
+    Ok(catalogue.chain(imported).collect())
+}
+
+// Checks that `id` names a catalogue or imported model.
+pub fn validate(id: &str, imported: &[ImportedModel]) -> Result<(), String> {
+    if imported.iter().any(|m| m.id == id) {
+        return Ok(());
+    }
+    find(id).map(|_| ())
+}
 
-    let models_dir = app_data_dir.join("models");
+// Path and multilingual flag for `id`. Catalogue models are downloaded if
+// missing; imported ones can only be imported again.
+pub fn resolve(
+    app_handle: &AppHandle,
+    id: &str,
+    imported: &[ImportedModel],
+) -> Result<(String, bool), String> {
+    if let Some(model) = imported.iter().find(|m| m.id == id) {
+        let path = models_dir(app_handle)?.join(&model.file_name);
+        if !path.exists() {
+            return Err(format!(
+                "Imported model '{}' is missing; import it again",
+                id
+            ));
+        }
+        return Ok((path.to_string_lossy().to_string(), model.multilingual));
+    }
+    let model = find(id)?;
+    Ok((ensure_model_exists(app_handle, id)?, model.multilingual))
+}
 
-    if !models_dir.exists() {
-        fs::create_dir_all(&models_dir)
-            .map_err(|e| format!("Failed to create models dir: {}", e))?;
+pub fn delete(app_handle: &AppHandle, id: &str, imported: &[ImportedModel]) -> Result<(), String> {
+    let file_name = match imported.iter().find(|m| m.id == id) {
+        Some(model) => model.file_name.clone(),
+        None => find(id)?.file_name(),
+    };
+    let path = models_dir(app_handle)?.join(file_name);
+    if !path.exists() {
+        return Err(format!("Model '{}' is not installed", id));
     }
+    fs::remove_file(&path).map_err(|e| format!("Failed to delete model: {}", e))
+}
+
+pub enum ImportOutcome {
+    // Same bytes as a catalogue model; installed under its name.
+    Catalogue(&'static CatalogueModel),
+    // Same bytes as a model imported earlier.
+    Existing(String),
+    // A new model that needs registering.
+    New(ImportedModel),
+}
 
-    let model_path = models_dir.join(MODEL_FILENAME);
+// ggml files start with the little-endian magic 0x67676d6c.
+const GGML_MAGIC: &[u8; 4] = b"lmgg";
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+// Multilingual Whisper vocabularies have 51865 or more tokens (see
+// whisper.cpp); English-only ones 51864.
+const MULTILINGUAL_VOCAB: i32 = 51865;
 
+// Reads the header and returns whether the model is multilingual. GGUF files
+// do not carry the Whisper vocabulary size up front, so they are treated as
+// multilingual and left to detect the language.
+fn read_header(path: &Path) -> Result<bool, String> {
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to open model: {}", e))?;
+    let mut header = [0u8; 8];
+    file.read_exact(&mut header)
+        .map_err(|_| "File is too short to be a Whisper model".to_string())?;
+    if &header[..4] == GGUF_MAGIC {
+        return Ok(true);
+    }
+    if &header[..4] != GGML_MAGIC {
+        return Err("Not a Whisper model: expected a ggml or gguf file".to_string());
+    }
+    let n_vocab = i32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    if !(50_000..=60_000).contains(&n_vocab) {
+        return Err(format!(
+            "Not a Whisper model: unexpected vocabulary size {}",
+            n_vocab
+        ));
+    }
+    Ok(n_vocab >= MULTILINGUAL_VOCAB)
+}
+
+// Copies a model file the user already has into the models directory,
+// hashing it on the way. Blocks for the length of the copy.
+pub fn import(
+    app_handle: &AppHandle,
+    source: &Path,
+    imported: &[ImportedModel],
+) -> Result<ImportOutcome, String> {
+    import_into(&models_dir(app_handle)?, source, CATALOGUE, imported)
+}
+
+// `import` into `models_dir`, recognising the models in `catalogue` by hash.
+fn import_into(
+    models_dir: &Path,
+    source: &Path,
+    catalogue: &'static [CatalogueModel],
+    imported: &[ImportedModel],
+) -> Result<ImportOutcome, String> {
+    let multilingual = read_header(source)?;
+    let _guard = DOWNLOAD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    fs::create_dir_all(models_dir).map_err(|e| format!("Failed to create models dir: {}", e))?;
+
+    // Copy to a temporary name first; which name it ends up under depends
+    // on the hash.
+    let staging = models_dir.join("import.part");
+    let mut input = fs::File::open(source).map_err(|e| format!("Failed to open model: {}", e))?;
+    let mut output =
+        fs::File::create(&staging).map_err(|e| format!("Failed to copy model: {}", e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut size: u64 = 0;
+    let copied = (|| -> std::io::Result<()> {
+        loop {
+            let n = input.read(&mut buffer)?;
+            if n == 0 {
+                return output.sync_all();
+            }
+            output.write_all(&buffer[..n])?;
+            hasher.update(&buffer[..n]);
+            size += n as u64;
+        }
+    })();
+    drop(output);
+    if let Err(e) = copied {
+        let _ = fs::remove_file(&staging);
+        return Err(format!("Failed to copy model: {}", e));
+    }
+    let sha256 = hex::encode(hasher.finalize());
+
+    let known = catalogue
+        .iter()
+        .find(|m| m.sha256.eq_ignore_ascii_case(&sha256));
+    if let Some(model) = known {
+        println!("DEBUG: Imported file is catalogue model {}", model.id);
+        install(&staging, &models_dir.join(model.file_name()))?;
+        return Ok(ImportOutcome::Catalogue(model));
+    }
+    if let Some(model) = imported.iter().find(|m| m.sha256 == sha256) {
+        println!("DEBUG: Imported file matches {}", model.id);
+        install(&staging, &models_dir.join(&model.file_name))?;
+        return Ok(ImportOutcome::Existing(model.id.clone()));
+    }
+
+    let id = import_id(source, &sha256, imported);
+    let model = ImportedModel {
+        file_name: format!("ggml-{}.bin", id),
+        id,
+        sha256,
+        size_mb: (size / (1024 * 1024)) as u32,
+        multilingual,
+    };
+    install(&staging, &models_dir.join(&model.file_name))?;
+    println!("DEBUG: Imported model {} ({})", model.id, model.sha256);
+    Ok(ImportOutcome::New(model))
+}
+
+fn install(staging: &Path, target: &Path) -> Result<(), String> {
+    fs::rename(staging, target).map_err(|e| {
+        let _ = fs::remove_file(staging);
+        format!("Failed to install model: {}", e)
+    })
+}
+
+// "ggml-large-v3.bin" becomes "large-v3"; names already taken by the
+// catalogue or an earlier import get a hash suffix.
+fn import_id(source: &Path, sha256: &str, imported: &[ImportedModel]) -> String {
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let stem: String = stem
+        .trim_start_matches("ggml-")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let stem = if stem.is_empty() {
+        "custom".to_string()
+    } else {
+        stem
+    };
+    let taken = |id: &str| find(id).is_ok() || imported.iter().any(|m| m.id == id);
+    if taken(&stem) {
+        format!("{}-{}", stem, &sha256[..8])
+    } else {
+        stem
+    }
+}
+
+// Returns the path of an installed model, downloading it first if needed.
+// Blocks for the length of the download; call it from a blocking worker.
+pub fn ensure_model_exists(app_handle: &AppHandle, id: &str) -> Result<String, String> {
+    let model = find(id)?;
+    let models_dir = models_dir(app_handle)?;
+    let model_path = models_dir.join(model.file_name());
+    if model_path.exists() {
+        return Ok(model_path.to_string_lossy().to_string());
+    }
+
+    let _guard = DOWNLOAD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    // Another caller may have finished it while we waited.
     if model_path.exists() {
-        println!("DEBUG: Model found at {:?}", model_path);
         return Ok(model_path.to_string_lossy().to_string());
     }
+    fs::create_dir_all(&models_dir).map_err(|e| format!("Failed to create models dir: {}", e))?;
+
+    let result = download(app_handle, model, &model_path);
+    match &result {
+        Ok(size) => emit_progress(app_handle, model, "done", *size, Some(*size), None),
+        Err(e) => emit_progress(app_handle, model, "failed", 0, None, Some(e.clone())),
+    }
+    result.map(|_| model_path.to_string_lossy().to_string())
+}
+
+// Whether any model, downloaded or imported, is in the models directory.
+pub fn any_installed(app_handle: &AppHandle) -> bool {
+    let Ok(entries) =
+        models_dir(app_handle).and_then(|dir| fs::read_dir(dir).map_err(|e| e.to_string()))
+    else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let name = entry.file_name().to_string_lossy().to_string();
+        name.starts_with("ggml-") && name.ends_with(".bin")
+    })
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+struct DownloadProgress {
+    model: &'static str,
+    // "downloading", "verifying", "done" or "failed".
+    state: &'static str,
+    downloaded_bytes: u64,
+    total_bytes: Option<u64>,
+    error: Option<String>,
+}
+
+fn emit_progress(
+    app_handle: &AppHandle,
+    model: &CatalogueModel,
+    state: &'static str,
+    downloaded_bytes: u64,
+    total_bytes: Option<u64>,
+    error: Option<String>,
+) {
+    let payload = DownloadProgress {
+        model: model.id,
+        state,
+        downloaded_bytes,
+        total_bytes,
+        error,
+    };
+    if let Err(e) = app_handle.emit(PROGRESS_EVENT, payload) {
+        println!("WARNING: Failed to emit download progress: {}", e);
+    }
+}
+
+fn part_path(model_path: &Path) -> PathBuf {
+    let mut name = model_path.as_os_str().to_owned();
+    name.push(".part");
+    PathBuf::from(name)
+}
 
-    println!("DEBUG: Model not found. Downloading from {}", MODEL_URL);
+// Streams the model into `<file>.part`, resuming from whatever a previous
+// attempt left there, checks its SHA-256 and renames it into place. The
+// rename is atomic, so `model_path` only ever holds a complete, verified file.
+fn download(
+    app_handle: &AppHandle,
+    model: &CatalogueModel,
+    model_path: &Path,
+) -> Result<u64, String> {
+    let base_url = std::env::var(MODEL_BASE_URL_ENV).unwrap_or_else(|_| MODEL_BASE_URL.to_string());
+    let model_url = format!("{}/{}", base_url.trim_end_matches('/'), model.file_name());
+    let part_path = part_path(model_path);
+
+    let mut resume_from = fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0);
+    println!(
+        "DEBUG: Downloading {} from byte {} of {}",
+        model.id, resume_from, model_url
+    );
 
-    // Download the model
     let client = reqwest::blocking::Client::builder()
         .user_agent("ConstructionOS/1.0")
+        .connect_timeout(Duration::from_secs(30))
         .build()
         .map_err(|e| e.to_string())?;
-
-    let response = client
-        .get(MODEL_URL)
+    let mut request = client.get(&model_url);
+    if resume_from > 0 {
+        request = request.header(RANGE, format!("bytes={}-", resume_from));
+    }
+    let mut response = request
         .send()
         .map_err(|e| format!("Failed to request model: {}", e))?;
 
-    if !response.status().is_success() {
-        return Err(format!(
-            "Failed to download model: HTTP {}",
-            response.status()
-        ));
+    match response.status() {
+        StatusCode::PARTIAL_CONTENT => {}
+        StatusCode::RANGE_NOT_SATISFIABLE => {
+            // The partial file is as long as (or longer than) the model; let
+            // the hash decide whether it is the model.
+            println!("DEBUG: Partial download already complete");
+        }
+        status if status.is_success() => {
+            // Server ignored the range; start over.
+            resume_from = 0;
+        }
+        status => return Err(format!("Failed to download model: HTTP {}", status)),
     }
+    let complete = response.status() == StatusCode::RANGE_NOT_SATISFIABLE;
 
-    let bytes = response
-        .bytes()
-        .map_err(|e| format!("Failed to get model bytes: {}", e))?;
+    let total_bytes = if complete {
+        Some(resume_from)
+    } else {
+        total_size(&response)
+    };
 
-    let mut file =
-        fs::File::create(&model_path).map_err(|e| format!("Failed to create model file: {}", e))?;
+    // Hash what is already on disk, then keep hashing as we append.
+    let mut hasher = Sha256::new();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&part_path)
+        .map_err(|e| format!("Failed to open partial model file: {}", e))?;
+    if resume_from > 0 {
+        let mut existing = fs::File::open(&part_path)
+            .map_err(|e| format!("Failed to read partial model file: {}", e))?;
+        std::io::copy(&mut existing, &mut hasher)
+            .map_err(|e| format!("Failed to read partial model file: {}", e))?;
+    } else {
+        file.set_len(0)
+            .map_err(|e| format!("Failed to reset partial model file: {}", e))?;
+    }
 
-    file.write_all(&bytes)
+    let mut downloaded = resume_from;
+    if !complete {
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut last_emit = Instant::now();
+        loop {
+            let n = response
+                .read(&mut buffer)
+                .map_err(|e| format!("Download interrupted: {}", e))?;
+            if n == 0 {
+                break;
+            }
+            file.write_all(&buffer[..n])
+                .map_err(|e| format!("Failed to write model file: {}", e))?;
+            hasher.update(&buffer[..n]);
+            downloaded += n as u64;
+            if last_emit.elapsed() >= Duration::from_millis(250) {
+                emit_progress(
+                    app_handle,
+                    model,
+                    "downloading",
+                    downloaded,
+                    total_bytes,
+                    None,
+                );
+                last_emit = Instant::now();
+            }
+        }
+    }
+    file.sync_all()
         .map_err(|e| format!("Failed to write model file: {}", e))?;
+    drop(file);
+
+    if total_bytes.is_some_and(|total| downloaded < total) {
+        return Err(format!(
+            "Download ended early ({} of {} bytes); it will resume on the next attempt",
+            downloaded,
+            total_bytes.unwrap_or_default()
+        ));
+    }
 
+    emit_progress(
+        app_handle,
+        model,
+        "verifying",
+        downloaded,
+        total_bytes,
+        None,
+    );
+    install_verified(hasher, &part_path, model_path, model)?;
     println!("DEBUG: Model downloaded successfully to {:?}", model_path);
+    Ok(downloaded)
+}
+
+// Moves the finished `.part` file into place if `hasher`, fed with its
+// contents, matches the catalogue hash. A mismatching file is deleted, since
+// resuming a corrupt file would fail forever.
+fn install_verified(
+    hasher: Sha256,
+    part_path: &Path,
+    model_path: &Path,
+    model: &CatalogueModel,
+) -> Result<(), String> {
+    let actual = hex::encode(hasher.finalize());
+    if !model.sha256.eq_ignore_ascii_case(&actual) {
+        let _ = fs::remove_file(part_path);
+        return Err(format!(
+            "Model checksum mismatch for {}: expected {}, got {}",
+            model.id, model.sha256, actual
+        ));
+    }
+    println!("DEBUG: Verified {} (sha256 {})", model.id, actual);
+    fs::rename(part_path, model_path).map_err(|e| format!("Failed to install model: {}", e))
+}
 
-    Ok(model_path.to_string_lossy().to_string())
+// Full file size, from Content-Range on a resumed request ("bytes 100-199/200")
+// or Content-Length otherwise.
+fn total_size(response: &reqwest::blocking::Response) -> Option<u64> {
+    if response.status() == StatusCode::PARTIAL_CONTENT {
+        let range = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+        return range.rsplit('/').next()?.parse().ok();
+    }
+    response.content_length()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An empty scratch directory for one test.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("model-manager-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn every_catalogue_model_has_a_sha256() {
+        for model in CATALOGUE {
+            assert_eq!(model.sha256.len(), 64, "{}", model.id);
+            assert!(
+                model.sha256.chars().all(|c| c.is_ascii_hexdigit()),
+                "{}",
+                model.id
+            );
+        }
+        assert!(find(DEFAULT_MODEL).is_ok());
+    }
+
+    #[test]
+    fn mismatching_part_file_is_rejected() {
+        let dir = scratch_dir("mismatch");
+        let model = find("tiny").unwrap();
+        let model_path = dir.join(model.file_name());
+        let part_path = part_path(&model_path);
+        fs::write(&part_path, b"lmgg not really a model").unwrap();
+
+        let mut hasher = Sha256::new();
+        hasher.update(fs::read(&part_path).unwrap());
+        let error = install_verified(hasher, &part_path, &model_path, model).unwrap_err();
+
+        assert!(error.contains("checksum mismatch"), "{}", error);
+        assert!(!part_path.exists());
+        assert!(!model_path.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn matching_part_file_is_installed() {
+        let dir = scratch_dir("match");
+        let contents = b"lmgg the expected bytes";
+        let sha256: &'static str = hex::encode(Sha256::digest(contents)).leak();
+        let model = model("test", 1, false, false, sha256);
+        let model_path = dir.join(model.file_name());
+        let part_path = part_path(&model_path);
+        fs::write(&part_path, contents).unwrap();
+
+        let mut hasher = Sha256::new();
+        hasher.update(contents);
+        install_verified(hasher, &part_path, &model_path, &model).unwrap();
+
+        assert!(!part_path.exists());
+        assert_eq!(fs::read(&model_path).unwrap(), contents);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    // The header of an English-only ggml model, then `body`.
+    fn ggml_file(path: &Path, body: &[u8]) -> Vec<u8> {
+        let mut contents = GGML_MAGIC.to_vec();
+        contents.extend_from_slice(&(MULTILINGUAL_VOCAB - 1).to_le_bytes());
+        contents.extend_from_slice(body);
+        fs::write(path, &contents).unwrap();
+        contents
+    }
+
+    #[test]
+    fn importing_a_catalogue_model_installs_it_under_its_name() {
+        let dir = scratch_dir("import-known");
+        let source = dir.join("ggml-base.en.bin");
+        let contents = ggml_file(&source, b"official weights");
+        let sha256: &'static str = hex::encode(Sha256::digest(&contents)).to_uppercase().leak();
+        let catalogue: &'static [CatalogueModel] =
+            vec![model("base.en", 142, false, false, sha256)].leak();
+        let models_dir = dir.join("models");
+
+        let outcome = import_into(&models_dir, &source, catalogue, &[]).unwrap();
+
+        assert!(matches!(outcome, ImportOutcome::Catalogue(model) if model.id == "base.en"));
+        assert_eq!(
+            fs::read(models_dir.join("ggml-base.en.bin")).unwrap(),
+            contents
+        );
+        assert!(!models_dir.join("import.part").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn importing_an_unknown_model_registers_it_once() {
+        let dir = scratch_dir("import-new");
+        let source = dir.join("ggml-large v3.bin");
+        ggml_file(&source, b"someone's fine-tune");
+        let models_dir = dir.join("models");
+
+        let ImportOutcome::New(model) = import_into(&models_dir, &source, CATALOGUE, &[]).unwrap()
+        else {
+            panic!("expected a new model");
+        };
+        assert_eq!(model.id, "large-v3");
+        assert!(!model.multilingual);
+        assert!(models_dir.join(&model.file_name).exists());
+
+        let again = import_into(&models_dir, &source, CATALOGUE, &[model]).unwrap();
+        assert!(matches!(again, ImportOutcome::Existing(id) if id == "large-v3"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn import_rejects_files_that_are_not_models() {
+        let dir = scratch_dir("import-bad");
+        let source = dir.join("notes.bin");
+        fs::write(&source, b"just some notes").unwrap();
+        assert!(import_into(&dir.join("models"), &source, CATALOGUE, &[]).is_err());
+        assert!(!dir.join("models").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
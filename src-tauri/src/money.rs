@@ -0,0 +1,251 @@
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+
+// An amount of money stored as integer cents.
+//
+// All arithmetic happens on cents so sums never drift. Over IPC it is
+// written as a decimal dollar number (e.g. `1234.5`), which the webview
+// already expects; a single cents -> dollars conversion is exact to the cent.
+// Incoming values may be numbers or strings like "$1,234.50".
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    pub fn from_cents(cents: i64) -> Self {
+        Money(cents)
+    }
+
+    pub fn from_dollars(dollars: f64) -> Self {
+        if dollars.is_finite() {
+            Money((dollars * 100.0).round() as i64)
+        } else {
+            Money::ZERO
+        }
+    }
+
+    pub fn to_dollars(self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+
+    // Price times a (possibly fractional) quantity, rounded to the nearest cent.
+    pub fn times(self, quantity: f64) -> Self {
+        Money::from_cents((self.0 as f64 * quantity).round() as i64)
+    }
+
+    // A percentage of this amount (e.g. a tax rate of 8.25), rounded to the nearest cent.
+    pub fn percent(self, rate: f64) -> Self {
+        self.times(rate / 100.0)
+    }
+
+    // Parses "1234.5", "$1,234.50" or "-12" without going through floats.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let cleaned: String = input
+            .trim()
+            .chars()
+            .filter(|c| !matches!(c, '$' | ',' | ' '))
+            .collect();
+        let (negative, digits) = match cleaned.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, cleaned.as_str()),
+        };
+        let (whole, fraction) = match digits.split_once('.') {
+            Some((w, f)) => (w, f),
+            None => (digits, ""),
+        };
+        let valid = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty()) || !valid(whole) || !valid(fraction) {
+            return Err(format!("Invalid amount: '{}'", input));
+        }
+
+        let whole: i64 = if whole.is_empty() {
+            0
+        } else {
+            whole
+                .parse()
+                .map_err(|_| format!("Amount out of range: '{}'", input))?
+        };
+        let mut fraction_digits = fraction.chars().map(|c| c as i64 - '0' as i64);
+        let tenths = fraction_digits.next().unwrap_or(0);
+        let hundredths = fraction_digits.next().unwrap_or(0);
+        let round_up = fraction_digits.next().unwrap_or(0) >= 5;
+
+        let cents = whole
+            .checked_mul(100)
+            .and_then(|c| c.checked_add(tenths * 10 + hundredths + round_up as i64))
+            .ok_or_else(|| format!("Amount out of range: '{}'", input))?;
+        Ok(Money(if negative { -cents } else { cents }))
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        write!(f, "{}{}.{:02}", sign, abs / 100, abs % 100)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+    fn add(self, other: Money) -> Money {
+        Money(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+    fn sub(self, other: Money) -> Money {
+        Money(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        self.0 -= other.0;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+// --- SERDE ---
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.to_dollars())
+    }
+}
+
+struct MoneyVisitor;
+
+impl<'de> Visitor<'de> for MoneyVisitor {
+    type Value = Money;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an amount as a number or string")
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Money, E> {
+        v.checked_mul(100)
+            .map(Money)
+            .ok_or_else(|| E::custom("amount out of range"))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Money, E> {
+        i64::try_from(v)
+            .map_err(|_| E::custom("amount out of range"))
+            .and_then(|v| self.visit_i64(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Money, E> {
+        Ok(Money::from_dollars(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Money, E> {
+        Money::parse(v).map_err(E::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Money, D::Error> {
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+// --- SQLITE ---
+
+impl ToSql for Money {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.0))
+    }
+}
+
+impl FromSql for Money {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        i64::column_result(value).map(Money)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dollar_strings() {
+        assert_eq!(Money::parse("1234.5"), Ok(Money(123450)));
+        assert_eq!(Money::parse("$1,234.50"), Ok(Money(123450)));
+        assert_eq!(Money::parse(" -12 "), Ok(Money(-1200)));
+        assert_eq!(Money::parse(".75"), Ok(Money(75)));
+        assert_eq!(Money::parse("3."), Ok(Money(300)));
+    }
+
+    #[test]
+    fn rounds_extra_decimals_half_up() {
+        assert_eq!(Money::parse("0.005"), Ok(Money(1)));
+        assert_eq!(Money::parse("0.004"), Ok(Money(0)));
+        assert_eq!(Money::parse("19.999"), Ok(Money(2000)));
+    }
+
+    #[test]
+    fn rejects_malformed_amounts() {
+        for input in [
+            "",
+            "$",
+            "abc",
+            "1.2.3",
+            "12a",
+            "--5",
+            "99999999999999999999",
+        ] {
+            assert!(Money::parse(input).is_err(), "{:?} parsed", input);
+        }
+    }
+
+    #[test]
+    fn arithmetic_stays_in_cents() {
+        let total: Money = std::iter::repeat_n(Money::from_dollars(0.1), 10).sum();
+        assert_eq!(total, Money(100));
+        assert_eq!(Money(8550).times(2.5), Money(21375));
+        assert_eq!(Money(1000).percent(8.25), Money(83));
+        assert_eq!(Money::from_dollars(f64::NAN), Money::ZERO);
+    }
+
+    #[test]
+    fn displays_with_two_decimals() {
+        assert_eq!(Money(123405).to_string(), "1234.05");
+        assert_eq!(Money(-5).to_string(), "-0.05");
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        assert_eq!(serde_json::to_string(&Money(123450)).unwrap(), "1234.5");
+        let parsed: Vec<Money> = serde_json::from_str(r#"[12, 0.1, "$1,000"]"#).unwrap();
+        assert_eq!(parsed, vec![Money(1200), Money(10), Money(100000)]);
+        // A missing amount is not $0; only `Option<Money>` accepts null.
+        assert!(serde_json::from_str::<Money>("null").is_err());
+        let missing: Option<Money> = serde_json::from_str("null").unwrap();
+        assert_eq!(missing, None);
+    }
+}
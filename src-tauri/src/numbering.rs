@@ -0,0 +1,190 @@
+use chrono::{Datelike, Local};
+use rusqlite::{params, Connection, OptionalExtension};
+
+// Sequential, human-friendly document numbers ("INV-0042", "INV-2026-0007").
+// Each kind of document has one row in `number_sequences`; callers should
+// allocate inside the same transaction that inserts the document.
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct NumberingSettings {
+    pub prefix: String,
+    pub next_value: i64,
+    pub yearly_reset: bool,
+    pub padding: usize,
+}
+
+pub fn settings(conn: &Connection, kind: &str) -> Result<NumberingSettings, String> {
+    conn.query_row(
+        "SELECT prefix, next_value, yearly_reset, padding FROM number_sequences WHERE kind = ?1",
+        [kind],
+        |row| {
+            Ok(NumberingSettings {
+                prefix: row.get(0)?,
+                next_value: row.get(1)?,
+                yearly_reset: row.get(2)?,
+                padding: row.get::<_, i64>(3)? as usize,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("No numbering sequence for '{}'", kind))
+}
+
+pub fn update_settings(
+    conn: &Connection,
+    kind: &str,
+    settings: &NumberingSettings,
+) -> Result<(), String> {
+    if settings.next_value < 1 {
+        return Err("Next number must be at least 1".to_string());
+    }
+    conn.execute(
+        "INSERT INTO number_sequences (kind, prefix, next_value, yearly_reset, padding) VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(kind) DO UPDATE SET prefix = excluded.prefix, next_value = excluded.next_value, yearly_reset = excluded.yearly_reset, padding = excluded.padding",
+        params![
+            kind,
+            settings.prefix,
+            settings.next_value,
+            settings.yearly_reset,
+            settings.padding.clamp(1, 10) as i64
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Allocates the next number for `kind`, skipping any value already used in
+// `table.number` (e.g. after the counter was manually lowered).
+pub fn next_number(conn: &Connection, kind: &str, table: &str) -> Result<String, String> {
+    let current = settings(conn, kind)?;
+    let stored_year: Option<i32> = conn
+        .query_row(
+            "SELECT year FROM number_sequences WHERE kind = ?1",
+            [kind],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    let year = Local::now().year();
+
+    let mut value = if current.yearly_reset && stored_year != Some(year) {
+        1
+    } else {
+        current.next_value
+    };
+    let exists_sql = format!("SELECT EXISTS(SELECT 1 FROM {} WHERE number = ?1)", table);
+    let number = loop {
+        let candidate = format_number(&current, year, value);
+        let taken: bool = conn
+            .query_row(&exists_sql, [&candidate], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        if !taken {
+            break candidate;
+        }
+        value += 1;
+    };
+
+    conn.execute(
+        "UPDATE number_sequences SET next_value = ?2, year = ?3 WHERE kind = ?1",
+        params![kind, value + 1, year],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(number)
+}
+
+fn format_number(settings: &NumberingSettings, year: i32, value: i64) -> String {
+    if settings.yearly_reset {
+        format!(
+            "{}{}-{:0width$}",
+            settings.prefix,
+            year,
+            value,
+            width = settings.padding
+        )
+    } else {
+        format!(
+            "{}{:0width$}",
+            settings.prefix,
+            value,
+            width = settings.padding
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::test_db;
+
+    fn invoice(conn: &Connection, number: &str) {
+        conn.execute(
+            "INSERT INTO invoices (id, number, client) VALUES (?1, ?1, 'Dave')",
+            [number],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn numbers_are_sequential_and_padded() {
+        let conn = test_db();
+        assert_eq!(
+            next_number(&conn, "invoice", "invoices").unwrap(),
+            "INV-0001"
+        );
+        assert_eq!(
+            next_number(&conn, "invoice", "invoices").unwrap(),
+            "INV-0002"
+        );
+        assert_eq!(
+            next_number(&conn, "estimate", "estimates").unwrap(),
+            "EST-0001"
+        );
+        assert_eq!(settings(&conn, "invoice").unwrap().next_value, 3);
+    }
+
+    #[test]
+    fn skips_numbers_already_used() {
+        let conn = test_db();
+        invoice(&conn, "INV-0001");
+        invoice(&conn, "INV-0002");
+        assert_eq!(
+            next_number(&conn, "invoice", "invoices").unwrap(),
+            "INV-0003"
+        );
+    }
+
+    #[test]
+    fn yearly_numbers_restart_in_a_new_year() {
+        let conn = test_db();
+        let yearly = NumberingSettings {
+            prefix: "Q".to_string(),
+            next_value: 41,
+            yearly_reset: true,
+            padding: 3,
+        };
+        update_settings(&conn, "invoice", &yearly).unwrap();
+        let year = Local::now().year();
+        conn.execute(
+            "UPDATE number_sequences SET year = ?1 WHERE kind = 'invoice'",
+            [year - 1],
+        )
+        .unwrap();
+        assert_eq!(
+            next_number(&conn, "invoice", "invoices").unwrap(),
+            format!("Q{}-001", year)
+        );
+        assert_eq!(
+            next_number(&conn, "invoice", "invoices").unwrap(),
+            format!("Q{}-002", year)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_settings_and_unknown_kinds() {
+        let conn = test_db();
+        let mut bad = settings(&conn, "invoice").unwrap();
+        bad.next_value = 0;
+        assert!(update_settings(&conn, "invoice", &bad).is_err());
+        assert!(next_number(&conn, "receipt", "invoices").is_err());
+    }
+}
@@ -0,0 +1,483 @@
+use printpdf::image_crate::{DynamicImage, GenericImageView};
+use printpdf::path::PaintMode;
+use printpdf::{
+    BuiltinFont, Color, Image, ImageTransform, IndirectFontRef, Line, Mm, PdfDocument,
+    PdfDocumentReference, PdfLayerReference, Point, Rect, Rgb,
+};
+
+// Invoice and estimate PDFs drawn natively with the PDF base-14 Helvetica, so
+// nothing has to be embedded and the webview never handles the file. The page
+// content is the markup a template renders to (see templates.rs). Sizes are in
+// points, positions in millimetres from the bottom left corner.
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+const BOTTOM: f32 = 20.0;
+const RIGHT: f32 = PAGE_WIDTH - MARGIN;
+// Width of each right-aligned table column.
+const COLUMN_WIDTH: f32 = 30.0;
+// Right edge of the labels of summary lines.
+const SUM_LABEL_RIGHT: f32 = RIGHT - 32.0;
+const LOGO_MAX_HEIGHT: f32 = 20.0;
+const LOGO_MAX_WIDTH: f32 = 60.0;
+const PT_TO_MM: f32 = 0.3528;
+
+// Put in front of values that would otherwise read as markup (see
+// `templates::field`). A line that starts with it is a paragraph, whatever
+// follows; it is never drawn.
+pub const LITERAL: char = '\u{2060}';
+
+pub fn render(markup: &str, title: &str, logo: Option<&DynamicImage>) -> Result<Vec<u8>, String> {
+    let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+    let regular = doc
+        .add_builtin_font(BuiltinFont::Helvetica)
+        .map_err(|e| e.to_string())?;
+    let bold = doc
+        .add_builtin_font(BuiltinFont::HelveticaBold)
+        .map_err(|e| e.to_string())?;
+    let layer = doc.get_page(page).get_layer(layer);
+    let mut page = Page {
+        doc: &doc,
+        layer,
+        regular,
+        bold,
+        y: PAGE_HEIGHT - MARGIN,
+        table: None,
+        spaced: true,
+    };
+
+    for line in markup.lines() {
+        page.draw(line.trim(), logo);
+    }
+
+    doc.save_to_bytes().map_err(|e| e.to_string())
+}
+
+#[derive(Clone, Copy)]
+enum Weight {
+    Regular,
+    Bold,
+}
+
+#[derive(Clone, Copy)]
+enum Shade {
+    Black,
+    Grey,
+}
+
+#[derive(Clone, Copy)]
+struct Style {
+    size: f32,
+    weight: Weight,
+    shade: Shade,
+}
+
+const fn style(size: f32, weight: Weight, shade: Shade) -> Style {
+    Style {
+        size,
+        weight,
+        shade,
+    }
+}
+
+const TITLE: Style = style(28.0, Weight::Bold, Shade::Black);
+const TITLE_RIGHT: Style = style(14.0, Weight::Bold, Shade::Black);
+const HEADING: Style = style(14.0, Weight::Bold, Shade::Black);
+const LABEL: Style = style(9.0, Weight::Bold, Shade::Grey);
+const DETAIL: Style = style(10.0, Weight::Regular, Shade::Grey);
+const BODY: Style = style(11.0, Weight::Regular, Shade::Black);
+const CELL: Style = style(10.0, Weight::Regular, Shade::Black);
+const SUM_LABEL: Style = style(10.0, Weight::Regular, Shade::Grey);
+const TOTAL_LABEL: Style = style(12.0, Weight::Bold, Shade::Black);
+const TOTAL: Style = style(16.0, Weight::Bold, Shade::Black);
+
+struct Page<'a> {
+    doc: &'a PdfDocumentReference,
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    // Bottom of what has been drawn so far.
+    y: f32,
+    // Header cells of the table being drawn, repeated on a new page.
+    table: Option<Vec<String>>,
+    // Whether the last thing drawn was a gap, so blank lines do not add up.
+    spaced: bool,
+}
+
+impl Page<'_> {
+    fn draw(&mut self, line: &str, logo: Option<&DynamicImage>) {
+        let literal = line.starts_with(LITERAL);
+        let line = &line.replace(LITERAL, "");
+        if line.is_empty() {
+            if !self.spaced {
+                self.y -= 4.0;
+                self.spaced = true;
+            }
+            return;
+        }
+        self.spaced = false;
+        if literal || !line.starts_with('|') {
+            self.table = None;
+        }
+
+        if literal {
+            self.text_line(line, BODY, BODY);
+        } else if line == "---" {
+            self.ensure_room(4.0);
+            self.y -= 2.0;
+            self.rule(MARGIN, 0.5);
+            self.y -= 2.0;
+        } else if line == "!logo" {
+            if let Some(logo) = logo {
+                self.logo(logo);
+            }
+        } else if let Some(rest) = line.strip_prefix("|=") {
+            self.table_header(cells(rest));
+        } else if let Some(rest) = line.strip_prefix('|') {
+            self.table_row(cells(rest));
+        } else if let Some(rest) = line.strip_prefix(">>") {
+            self.ensure_room(12.0);
+            self.y -= 2.0;
+            self.rule(SUM_LABEL_RIGHT - 30.0, 0.5);
+            self.sum(rest, TOTAL_LABEL, TOTAL);
+        } else if let Some(rest) = line.strip_prefix('>') {
+            self.sum(rest, SUM_LABEL, CELL);
+        } else if let Some(rest) = line.strip_prefix("###") {
+            self.text_line(rest, LABEL, LABEL);
+        } else if let Some(rest) = line.strip_prefix("##") {
+            self.text_line(rest, HEADING, DETAIL);
+        } else if let Some(rest) = line.strip_prefix('#') {
+            self.text_line(rest, TITLE, TITLE_RIGHT);
+        } else if let Some(rest) = line.strip_prefix('~') {
+            self.text_line(rest, DETAIL, DETAIL);
+        } else {
+            self.text_line(line, BODY, BODY);
+        }
+    }
+
+    // `left | right`: the left part wrapped from the margin, the right part
+    // right-aligned on its first line.
+    fn text_line(&mut self, line: &str, style: Style, right_style: Style) {
+        let (left, right) = match line.split_once('|') {
+            Some((left, right)) => (left.trim(), right.trim()),
+            None => (line.trim(), ""),
+        };
+        let right_width = if right.is_empty() {
+            0.0
+        } else {
+            text_width(right, right_style) + 5.0
+        };
+        let lines = wrap(left, style, RIGHT - MARGIN - right_width);
+        for (i, text) in lines.iter().enumerate() {
+            let baseline = self.advance(style);
+            self.text(text, style, MARGIN, baseline);
+            if i == 0 && !right.is_empty() {
+                self.text_right(right, right_style, RIGHT, baseline);
+            }
+        }
+    }
+
+    fn table_header(&mut self, header: Vec<String>) {
+        self.ensure_room(20.0);
+        self.y -= 2.0;
+        self.layer.set_fill_color(rgb(0.93));
+        self.layer.add_rect(
+            Rect::new(Mm(MARGIN), Mm(self.y - 7.5), Mm(RIGHT), Mm(self.y))
+                .with_mode(PaintMode::Fill),
+        );
+        let baseline = self.y - 5.0;
+        let rights = column_rights(header.len());
+        for (i, cell) in header.iter().enumerate() {
+            if i == 0 {
+                self.text(cell, LABEL, MARGIN + 2.0, baseline);
+            } else {
+                // Keep the last header off the edge of the shading.
+                let inset = if i + 1 == header.len() { 2.0 } else { 0.0 };
+                self.text_right(cell, LABEL, rights[i] - inset, baseline);
+            }
+        }
+        self.y -= 7.5;
+        self.table = Some(header);
+    }
+
+    fn table_row(&mut self, row: Vec<String>) {
+        let columns = self.table.as_ref().map_or(row.len(), Vec::len).max(1);
+        let rights = column_rights(columns);
+        let first_width = rights.get(1).map_or(RIGHT, |right| right - COLUMN_WIDTH) - MARGIN - 4.0;
+        let description = row.first().map(String::as_str).unwrap_or_default();
+        let lines = wrap(description, CELL, first_width);
+
+        let line_height = CELL.size * PT_TO_MM * 1.4;
+        if self.y - lines.len() as f32 * line_height - 3.0 < BOTTOM {
+            self.new_page();
+        }
+        let mut baseline = self.y - line_height;
+        for (i, cell) in row.iter().enumerate().skip(1).take(columns - 1) {
+            self.text_right(cell, CELL, rights[i], baseline);
+        }
+        for text in &lines {
+            self.text(text, CELL, MARGIN, baseline);
+            baseline -= line_height;
+        }
+        self.y = baseline + line_height - 3.0;
+        self.rule(MARGIN, 0.2);
+    }
+
+    fn sum(&mut self, line: &str, label_style: Style, value_style: Style) {
+        let (label, value) = match line.split_once('|') {
+            Some((label, value)) => (label.trim(), value.trim()),
+            None => ("", line.trim()),
+        };
+        let baseline = self.advance(value_style);
+        self.text_right(label, label_style, SUM_LABEL_RIGHT, baseline);
+        self.text_right(value, value_style, RIGHT, baseline);
+    }
+
+    fn logo(&mut self, logo: &DynamicImage) {
+        let (width_px, height_px) = logo.dimensions();
+        if width_px == 0 || height_px == 0 {
+            return;
+        }
+        // The resolution that fits the logo in its box, in pixels per inch.
+        let dpi = (height_px as f32 * 25.4 / LOGO_MAX_HEIGHT)
+            .max(width_px as f32 * 25.4 / LOGO_MAX_WIDTH);
+        let height = height_px as f32 * 25.4 / dpi;
+        self.ensure_room(height);
+        self.y -= height;
+        Image::from_dynamic_image(logo).add_to_layer(
+            self.layer.clone(),
+            ImageTransform {
+                translate_x: Some(Mm(MARGIN)),
+                translate_y: Some(Mm(self.y)),
+                dpi: Some(dpi),
+                ..Default::default()
+            },
+        );
+        self.y -= 4.0;
+    }
+
+    // Moves down one line of `style` and returns its baseline.
+    fn advance(&mut self, style: Style) -> f32 {
+        let height = style.size * PT_TO_MM * 1.4;
+        self.ensure_room(height);
+        self.y -= height;
+        self.y + height * 0.25
+    }
+
+    fn ensure_room(&mut self, height: f32) {
+        if self.y - height < BOTTOM {
+            self.new_page();
+        }
+    }
+
+    // Continues on a new page, repeating the header of the table in progress.
+    fn new_page(&mut self) {
+        let (page, layer) = self
+            .doc
+            .add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.y = PAGE_HEIGHT - MARGIN;
+        if let Some(header) = self.table.take() {
+            self.table_header(header);
+        }
+    }
+
+    fn text(&self, text: &str, style: Style, x: f32, baseline: f32) {
+        let font = match style.weight {
+            Weight::Regular => &self.regular,
+            Weight::Bold => &self.bold,
+        };
+        self.layer.set_fill_color(match style.shade {
+            Shade::Black => rgb(0.0),
+            Shade::Grey => rgb(0.4),
+        });
+        self.layer
+            .use_text(text, style.size, Mm(x), Mm(baseline), font);
+    }
+
+    fn text_right(&self, text: &str, style: Style, right: f32, baseline: f32) {
+        self.text(text, style, right - text_width(text, style), baseline);
+    }
+
+    // A horizontal line at the current position from `from` to the right
+    // margin.
+    fn rule(&self, from: f32, thickness: f32) {
+        self.layer.set_outline_color(rgb(0.0));
+        self.layer.set_outline_thickness(thickness);
+        self.layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm(from), Mm(self.y)), false),
+                (Point::new(Mm(RIGHT), Mm(self.y)), false),
+            ],
+            is_closed: false,
+        });
+    }
+}
+
+fn rgb(level: f32) -> Color {
+    Color::Rgb(Rgb::new(level, level, level, None))
+}
+
+fn cells(row: &str) -> Vec<String> {
+    row.split('|').map(|cell| cell.trim().to_string()).collect()
+}
+
+// Right edges of the columns of a table with `count` columns. The first
+// column is left-aligned, so its entry is only a placeholder.
+fn column_rights(count: usize) -> Vec<f32> {
+    (0..count)
+        .map(|i| RIGHT - (count - 1 - i) as f32 * COLUMN_WIDTH)
+        .collect()
+}
+
+// Splits `text` into lines no wider than `width` millimetres, breaking at
+// spaces; a single word longer than a line is left to overflow.
+fn wrap(text: &str, style: Style, width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", line, word)
+        };
+        if !line.is_empty() && text_width(&candidate, style) > width {
+            lines.push(std::mem::replace(&mut line, word.to_string()));
+        } else {
+            line = candidate;
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+fn text_width(text: &str, style: Style) -> f32 {
+    let units: u32 = text.chars().map(|c| char_width(c, style.weight)).sum();
+    units as f32 / 1000.0 * style.size * PT_TO_MM
+}
+
+// Advance widths from the Helvetica AFM metrics, in 1/1000 em, for printable
+// ASCII; anything else is counted as a digit.
+fn char_width(c: char, weight: Weight) -> u32 {
+    const REGULAR: [u16; 95] = [
+        278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556,
+        556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722,
+        722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722,
+        667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556,
+        556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500,
+        500, 334, 260, 334, 584,
+    ];
+    const BOLD: [u16; 95] = [
+        278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556,
+        556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722,
+        722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722,
+        667, 944, 667, 667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611,
+        611, 278, 278, 556, 278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556,
+        500, 389, 280, 389, 584,
+    ];
+    let table = match weight {
+        Weight::Regular => &REGULAR,
+        Weight::Bold => &BOLD,
+    };
+    match c as u32 {
+        code @ 32..=126 => table[(code - 32) as usize] as u32,
+        _ => 556,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Draws `markup` on a fresh page and hands back the page to inspect.
+    fn drawn(markup: &str, inspect: impl FnOnce(&Page)) {
+        let (doc, page, layer) =
+            PdfDocument::new("test", Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        let mut page = Page {
+            layer: doc.get_page(page).get_layer(layer),
+            doc: &doc,
+            regular: doc.add_builtin_font(BuiltinFont::Helvetica).unwrap(),
+            bold: doc.add_builtin_font(BuiltinFont::HelveticaBold).unwrap(),
+            y: PAGE_HEIGHT - MARGIN,
+            table: None,
+            spaced: true,
+        };
+        for line in markup.lines() {
+            page.draw(line.trim(), None);
+        }
+        inspect(&page);
+    }
+
+    fn page_count(pdf: &[u8]) -> usize {
+        let pdf = String::from_utf8_lossy(pdf);
+        pdf.matches("/Type/Page").count() - pdf.matches("/Type/Pages").count()
+    }
+
+    #[test]
+    fn long_words_get_a_line_of_their_own() {
+        let width = text_width("Supercalifragilistic", BODY) - 1.0;
+        let lines = wrap(
+            "Supercalifragilisticexpialidocious deck boards",
+            BODY,
+            width,
+        );
+        assert_eq!(lines, ["Supercalifragilisticexpialidocious", "deck boards"]);
+        let lines = wrap("Two Supercalifragilisticexpialidocious", BODY, width);
+        assert_eq!(lines, ["Two", "Supercalifragilisticexpialidocious"]);
+        assert_eq!(wrap("", BODY, width), [""]);
+    }
+
+    #[test]
+    fn columns_end_at_the_right_margin() {
+        assert_eq!(column_rights(2), [RIGHT - COLUMN_WIDTH, RIGHT]);
+        assert_eq!(
+            column_rights(4),
+            [
+                RIGHT - 3.0 * COLUMN_WIDTH,
+                RIGHT - 2.0 * COLUMN_WIDTH,
+                RIGHT - COLUMN_WIDTH,
+                RIGHT
+            ]
+        );
+    }
+
+    #[test]
+    fn long_tables_continue_on_a_new_page_under_the_header() {
+        let mut markup = String::from("# Invoice\n|= Item | Qty | Total\n");
+        for i in 0..80 {
+            markup.push_str(&format!("| Item {} | 1 | $10.00\n", i));
+        }
+        drawn(&markup, |page| {
+            assert!(page.y >= BOTTOM);
+            assert_eq!(page.table.as_deref().map(<[String]>::len), Some(3));
+        });
+        let pdf = render(&markup, "INV-0001", None).unwrap();
+        assert!(page_count(&pdf) > 1);
+        let short = render(
+            "# Invoice\n|= Item | Qty | Total\n| Deck | 1 | $10.00",
+            "INV-0001",
+            None,
+        )
+        .unwrap();
+        assert_eq!(page_count(&short), 1);
+    }
+
+    #[test]
+    fn literal_lines_are_paragraphs() {
+        let body = BODY.size * PT_TO_MM * 1.4;
+        let top = PAGE_HEIGHT - MARGIN;
+        drawn(&format!("{}# Not a title", LITERAL), |page| {
+            assert!((page.y - (top - body)).abs() < 0.01);
+        });
+        drawn(
+            &format!("|= Item | Total\n{}| not | a row", LITERAL),
+            |page| {
+                assert!(page.table.is_none());
+            },
+        );
+        drawn(&format!("{}---", LITERAL), |page| {
+            assert!((page.y - (top - body)).abs() < 0.01);
+        });
+    }
+}
@@ -0,0 +1,291 @@
+use std::io::Cursor;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample};
+use tauri::{AppHandle, Emitter};
+
+use crate::audio;
+
+// Microphone capture through cpal, for when the webview has no microphone
+// permission. cpal streams cannot move between threads on every platform, so
+// each recording owns a thread that builds the stream, keeps it alive until
+// told to stop and hands back the captured samples.
+
+const LEVEL_EVENT: &str = "recording-level";
+const LEVEL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct InputDevice {
+    pub name: String,
+    pub is_default: bool,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+struct Level {
+    // Root mean square and peak of the last few milliseconds, 0.0 to 1.0.
+    rms: f32,
+    peak: f32,
+}
+
+#[derive(Default)]
+pub struct Recorder {
+    active: Mutex<Option<ActiveRecording>>,
+}
+
+struct ActiveRecording {
+    stop: mpsc::Sender<()>,
+    thread: JoinHandle<Result<Vec<f32>, String>>,
+    audio: LiveAudio,
+}
+
+// Read access to a recording in progress, for live transcription.
+#[derive(Clone)]
+pub struct LiveAudio {
+    capture: Arc<Capture>,
+    rate: u32,
+}
+
+impl LiveAudio {
+    // Device sample rate of what `since` returns.
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    // Mono samples captured from index `start` on. Empty once the recording
+    // has stopped.
+    pub fn since(&self, start: usize) -> Vec<f32> {
+        let samples = self
+            .capture
+            .samples
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        samples.get(start..).unwrap_or_default().to_vec()
+    }
+}
+
+pub fn list_devices() -> Result<Vec<InputDevice>, String> {
+    let host = cpal::default_host();
+    let default_name = host.default_input_device().and_then(|d| d.name().ok());
+    let devices = host
+        .input_devices()
+        .map_err(|e| format!("Failed to list input devices: {}", e))?;
+    Ok(devices
+        .filter_map(|device| device.name().ok())
+        .map(|name| InputDevice {
+            is_default: default_name.as_deref() == Some(name.as_str()),
+            name,
+        })
+        .collect())
+}
+
+impl Recorder {
+    // Starts capturing from `device_name`, or the system default input.
+    pub fn start(&self, app_handle: AppHandle, device_name: Option<String>) -> Result<(), String> {
+        let mut active = self
+            .active
+            .lock()
+            .map_err(|_| "Recorder lock poisoned".to_string())?;
+        if active.is_some() {
+            return Err("Already recording".to_string());
+        }
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let thread = thread::spawn(move || capture(app_handle, device_name, stop_rx, ready_tx));
+        // Surface device and stream errors to the caller instead of the log.
+        let audio = match ready_rx.recv() {
+            Ok(Ok(audio)) => audio,
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Err("Recording thread exited unexpectedly".to_string()),
+        };
+        *active = Some(ActiveRecording {
+            stop: stop_tx,
+            thread,
+            audio,
+        });
+        Ok(())
+    }
+
+    // The recording in progress, if any.
+    pub fn live_audio(&self) -> Option<LiveAudio> {
+        let active = self.active.lock().ok()?;
+        active.as_ref().map(|recording| recording.audio.clone())
+    }
+
+    // Stops the current recording and returns it as 16 kHz mono samples.
+    pub fn stop(&self) -> Result<Vec<f32>, String> {
+        let recording = self
+            .active
+            .lock()
+            .map_err(|_| "Recorder lock poisoned".to_string())?
+            .take()
+            .ok_or("Not recording")?;
+        let _ = recording.stop.send(());
+        recording
+            .thread
+            .join()
+            .map_err(|_| "Recording thread panicked".to_string())?
+    }
+}
+
+// Shared between the audio callback and the recording thread.
+struct Capture {
+    samples: Mutex<Vec<f32>>,
+    // f32 bits; the callback writes, the recording thread reads.
+    rms: AtomicU32,
+    peak: AtomicU32,
+}
+
+fn capture(
+    app_handle: AppHandle,
+    device_name: Option<String>,
+    stop: mpsc::Receiver<()>,
+    ready: mpsc::Sender<Result<LiveAudio, String>>,
+) -> Result<Vec<f32>, String> {
+    let capture = Arc::new(Capture {
+        samples: Mutex::new(Vec::new()),
+        rms: AtomicU32::new(0),
+        peak: AtomicU32::new(0),
+    });
+    let (error_tx, error_rx) = mpsc::channel();
+    let (stream, rate) = match open_stream(device_name, capture.clone(), error_tx) {
+        Ok((stream, rate)) => {
+            let _ = ready.send(Ok(LiveAudio {
+                capture: capture.clone(),
+                rate,
+            }));
+            (stream, rate)
+        }
+        Err(e) => {
+            let _ = ready.send(Err(e.clone()));
+            return Err(e);
+        }
+    };
+
+    let outcome = loop {
+        if let Ok(e) = error_rx.try_recv() {
+            break Err(format!("Recording failed: {}", e));
+        }
+        match stop.recv_timeout(LEVEL_INTERVAL) {
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => break Ok(()),
+            Err(RecvTimeoutError::Timeout) => {
+                let level = Level {
+                    rms: f32::from_bits(capture.rms.load(Ordering::Relaxed)),
+                    peak: f32::from_bits(capture.peak.load(Ordering::Relaxed)),
+                };
+                let _ = app_handle.emit(LEVEL_EVENT, level);
+            }
+        }
+    };
+    drop(stream);
+    outcome?;
+
+    let samples = std::mem::take(&mut *capture.samples.lock().unwrap_or_else(|e| e.into_inner()));
+    println!("DEBUG: Captured {} samples at {} Hz", samples.len(), rate);
+    if samples.is_empty() {
+        return Err("No audio was captured".to_string());
+    }
+    audio::resample(&samples, rate)
+}
+
+fn open_stream(
+    device_name: Option<String>,
+    capture: Arc<Capture>,
+    errors: mpsc::Sender<String>,
+) -> Result<(cpal::Stream, u32), String> {
+    let host = cpal::default_host();
+    let device = match &device_name {
+        Some(name) => host
+            .input_devices()
+            .map_err(|e| format!("Failed to list input devices: {}", e))?
+            .find(|d| d.name().is_ok_and(|n| &n == name))
+            .ok_or_else(|| format!("Input device '{}' not found", name))?,
+        None => host.default_input_device().ok_or("No microphone found")?,
+    };
+    let config = device
+        .default_input_config()
+        .map_err(|e| format!("Microphone is not usable: {}", e))?;
+    let rate = config.sample_rate().0;
+    let format = config.sample_format();
+    let config: cpal::StreamConfig = config.into();
+    println!(
+        "DEBUG: Recording from {} at {} Hz, {} channel(s), {:?}",
+        device.name().unwrap_or_default(),
+        rate,
+        config.channels,
+        format
+    );
+
+    let stream = match format {
+        SampleFormat::F32 => build_stream::<f32>(&device, &config, capture, errors),
+        SampleFormat::I16 => build_stream::<i16>(&device, &config, capture, errors),
+        SampleFormat::U16 => build_stream::<u16>(&device, &config, capture, errors),
+        SampleFormat::I32 => build_stream::<i32>(&device, &config, capture, errors),
+        other => return Err(format!("Unsupported microphone sample format {:?}", other)),
+    }?;
+    stream
+        .play()
+        .map_err(|e| format!("Failed to start microphone: {}", e))?;
+    Ok((stream, rate))
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    capture: Arc<Capture>,
+    errors: mpsc::Sender<String>,
+) -> Result<cpal::Stream, String>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels as usize;
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                let mut sum = 0.0f32;
+                let mut peak = 0.0f32;
+                let mut samples = capture.samples.lock().unwrap_or_else(|e| e.into_inner());
+                for frame in data.chunks_exact(channels) {
+                    let value =
+                        frame.iter().map(|s| s.to_sample::<f32>()).sum::<f32>() / channels as f32;
+                    sum += value * value;
+                    peak = peak.max(value.abs());
+                    samples.push(value);
+                }
+                let frames = (data.len() / channels).max(1);
+                let rms = (sum / frames as f32).sqrt();
+                capture.rms.store(rms.to_bits(), Ordering::Relaxed);
+                capture.peak.store(peak.to_bits(), Ordering::Relaxed);
+            },
+            move |e| {
+                let _ = errors.send(e.to_string());
+            },
+            None,
+        )
+        .map_err(|e| format!("Failed to open microphone: {}", e))
+}
+
+// 16-bit WAV, so native recordings sit in the inbox like webview ones.
+pub fn encode_wav(samples: &[f32]) -> Result<Vec<u8>, String> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: audio::SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut bytes = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut bytes, spec).map_err(|e| e.to_string())?;
+    for &sample in samples {
+        writer
+            .write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+            .map_err(|e| e.to_string())?;
+    }
+    writer.finalize().map_err(|e| e.to_string())?;
+    Ok(bytes.into_inner())
+}
@@ -0,0 +1,490 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use chrono::{DateTime, Duration, Local};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::new_id;
+use crate::whisper_engine::Transcription;
+
+// Voice recordings and what became of them. Every file saved to the inbox
+// gets a row; processing takes it from PENDING through PROCESSING to
+// PROCESSED or FAILED and links it to the drafts it produced. Archiving moves
+// the file out of the inbox folder, and the retention policy archives and
+// later purges recordings that are done with.
+
+pub const PENDING: &str = "PENDING";
+pub const PROCESSING: &str = "PROCESSING";
+pub const PROCESSED: &str = "PROCESSED";
+pub const FAILED: &str = "FAILED";
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct Recording {
+    pub id: String,
+    pub path: String,
+    // Known once the audio has been decoded on this device.
+    pub duration_ms: Option<i64>,
+    pub transcript: Option<String>,
+    pub status: String,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub processed_at: Option<String>,
+    pub archived_at: Option<String>,
+    pub drafts: Vec<DraftLink>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct DraftLink {
+    pub draft_id: String,
+    pub intent: String,
+}
+
+// Stored under "recordings" in `app_settings`. `None` turns a step off.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct RetentionSettings {
+    // Processed recordings leave the inbox after this many days.
+    #[serde(default = "default_archive_after_days")]
+    pub archive_after_days: Option<u32>,
+    // Archived recordings are deleted this many days after archiving.
+    #[serde(default = "default_purge_after_days")]
+    pub purge_after_days: Option<u32>,
+}
+
+fn default_archive_after_days() -> Option<u32> {
+    Some(30)
+}
+
+// Deleting audio is the owner's call; archived recordings are kept until
+// they set a limit.
+fn default_purge_after_days() -> Option<u32> {
+    None
+}
+
+impl Default for RetentionSettings {
+    fn default() -> Self {
+        RetentionSettings {
+            archive_after_days: default_archive_after_days(),
+            purge_after_days: default_purge_after_days(),
+        }
+    }
+}
+
+// Adds `path` as a PENDING recording if it is not tracked yet. Returns its ID.
+pub fn register(conn: &Connection, path: &str) -> Result<String, String> {
+    conn.execute(
+        "INSERT INTO recordings (id, path, status, created_at) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(path) DO NOTHING",
+        params![new_id(), path, PENDING, Local::now().to_rfc3339()],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.query_row("SELECT id FROM recordings WHERE path = ?1", [path], |row| {
+        row.get(0)
+    })
+    .map_err(|e| e.to_string())
+}
+
+// Tracks audio files in `inbox` that were saved without a row, such as
+// recordings from before the table existed.
+pub fn register_untracked(conn: &Connection, inbox: &Path) -> Result<(), String> {
+    if !inbox.exists() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(inbox).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if matches!(
+            path.extension().and_then(|s| s.to_str()),
+            Some("webm" | "wav" | "m4a")
+        ) {
+            register(conn, &path.to_string_lossy())?;
+        }
+    }
+    Ok(())
+}
+
+pub fn set_duration(conn: &Connection, id: &str, duration_ms: i64) -> Result<(), String> {
+    conn.execute(
+        "UPDATE recordings SET duration_ms = ?2 WHERE id = ?1",
+        params![id, duration_ms],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn set_status(
+    conn: &Connection,
+    id: &str,
+    status: &str,
+    error: Option<&str>,
+) -> Result<(), String> {
+    let processed_at = match status {
+        PROCESSED | FAILED => Some(Local::now().to_rfc3339()),
+        _ => None,
+    };
+    conn.execute(
+        "UPDATE recordings SET status = ?2, error = ?3, processed_at = COALESCE(?4, processed_at)
+         WHERE id = ?1",
+        params![id, status, error, processed_at],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Marks the recording PROCESSED with `drafts` as everything it produced,
+// replacing the links from any earlier run.
+pub fn set_processed(conn: &mut Connection, id: &str, drafts: &[DraftLink]) -> Result<(), String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM recording_drafts WHERE recording_id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+    for draft in drafts {
+        tx.execute(
+            "INSERT OR IGNORE INTO recording_drafts (recording_id, draft_id, intent) VALUES (?1, ?2, ?3)",
+            params![id, draft.draft_id, draft.intent],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    set_status(&tx, id, PROCESSED, None)?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+// Recordings left PROCESSING when the app last closed will never finish.
+pub fn fail_interrupted(conn: &Connection) -> Result<usize, String> {
+    conn.execute(
+        "UPDATE recordings SET status = ?1, error = 'Interrupted before processing finished'
+         WHERE status = ?2",
+        params![FAILED, PROCESSING],
+    )
+    .map_err(|e| e.to_string())
+}
+
+pub fn save_transcript(
+    conn: &Connection,
+    id: &str,
+    transcription: &Transcription,
+) -> Result<(), String> {
+    let (text, segments) = match transcription {
+        Transcription::Speech { text, segments } => (Some(text.as_str()), segments.as_slice()),
+        Transcription::NoSpeech => (None, &[][..]),
+    };
+    let segments = serde_json::to_string(segments).map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE recordings SET transcript = ?2, segments = ?3, transcribed_at = ?4 WHERE id = ?1",
+        params![id, text, segments, Local::now().to_rfc3339()],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// The stored transcript of the recording at `path`, or `None` if it has not
+// been transcribed on this device.
+pub fn transcript(conn: &Connection, path: &str) -> Result<Option<Transcription>, String> {
+    let row: Option<(Option<String>, String)> = conn
+        .query_row(
+            "SELECT transcript, segments FROM recordings WHERE path = ?1 AND transcribed_at IS NOT NULL",
+            [path],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    match row {
+        None => Ok(None),
+        Some((None, _)) => Ok(Some(Transcription::NoSpeech)),
+        Some((Some(text), segments)) => Ok(Some(Transcription::Speech {
+            text,
+            segments: serde_json::from_str(&segments).map_err(|e| e.to_string())?,
+        })),
+    }
+}
+
+const RECORDING_COLUMNS: &str =
+    "id, path, duration_ms, transcript, status, error, created_at, processed_at, archived_at";
+
+fn recording_from_row(row: &rusqlite::Row) -> rusqlite::Result<Recording> {
+    Ok(Recording {
+        id: row.get(0)?,
+        path: row.get(1)?,
+        duration_ms: row.get(2)?,
+        transcript: row.get(3)?,
+        status: row.get(4)?,
+        error: row.get(5)?,
+        created_at: row.get(6)?,
+        processed_at: row.get(7)?,
+        archived_at: row.get(8)?,
+        drafts: Vec::new(),
+    })
+}
+
+// Newest first.
+pub fn list(conn: &Connection, include_archived: bool) -> Result<Vec<Recording>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM recordings WHERE ?1 OR archived_at IS NULL ORDER BY created_at DESC",
+            RECORDING_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([include_archived], recording_from_row)
+        .map_err(|e| e.to_string())?;
+    let mut recordings = rows
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for recording in &mut recordings {
+        recording.drafts = drafts(conn, &recording.id)?;
+    }
+    Ok(recordings)
+}
+
+pub fn get(conn: &Connection, id: &str) -> Result<Recording, String> {
+    let mut recording = conn
+        .query_row(
+            &format!("SELECT {} FROM recordings WHERE id = ?1", RECORDING_COLUMNS),
+            [id],
+            recording_from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Recording {} not found", id))?;
+    recording.drafts = drafts(conn, id)?;
+    Ok(recording)
+}
+
+fn drafts(conn: &Connection, id: &str) -> Result<Vec<DraftLink>, String> {
+    let mut stmt = conn
+        .prepare("SELECT draft_id, intent FROM recording_drafts WHERE recording_id = ?1")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([id], |row| {
+            Ok(DraftLink {
+                draft_id: row.get(0)?,
+                intent: row.get(1)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+// Moves the recording's file into `archive_dir`. A file that is already gone
+// is not an error; the row is archived either way.
+pub fn archive(conn: &Connection, id: &str, archive_dir: &Path) -> Result<(), String> {
+    let recording = get(conn, id)?;
+    if recording.archived_at.is_some() {
+        return Ok(());
+    }
+    if recording.status == PROCESSING {
+        return Err("Recording is still being processed".to_string());
+    }
+    let source = Path::new(&recording.path);
+    let file_name = source.file_name().ok_or("Recording has no file name")?;
+    let target = archive_dir.join(file_name);
+    fs::create_dir_all(archive_dir).map_err(|e| e.to_string())?;
+    match fs::rename(source, &target) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            println!("WARNING: Archiving recording {} with no file", id);
+        }
+        Err(e) => return Err(format!("Failed to archive recording: {}", e)),
+    }
+    conn.execute(
+        "UPDATE recordings SET path = ?2, archived_at = ?3 WHERE id = ?1",
+        params![id, target.to_string_lossy(), Local::now().to_rfc3339()],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Deletes the recording's file and row. Drafts it produced are kept.
+pub fn purge(conn: &Connection, id: &str) -> Result<(), String> {
+    let recording = get(conn, id)?;
+    if recording.status == PROCESSING {
+        return Err("Recording is still being processed".to_string());
+    }
+    match fs::remove_file(&recording.path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to delete recording: {}", e)),
+    }
+    conn.execute("DELETE FROM recording_drafts WHERE recording_id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM recordings WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Archives processed recordings and purges archived ones that are past the
+// limits in `settings`. Returns how many were archived and purged. A
+// recording that cannot be moved or deleted is logged and skipped, so it does
+// not hold up the rest.
+pub fn apply_retention(
+    conn: &Connection,
+    settings: &RetentionSettings,
+    archive_dir: &Path,
+) -> Result<(usize, usize), String> {
+    let mut archived = 0;
+    if let Some(days) = settings.archive_after_days {
+        let ids = older_than(
+            conn,
+            "SELECT id, created_at FROM recordings WHERE status = ?1 AND archived_at IS NULL",
+            [PROCESSED],
+            days,
+        )?;
+        for id in ids {
+            match archive(conn, &id, archive_dir) {
+                Ok(()) => archived += 1,
+                Err(e) => println!("WARNING: Could not archive recording {}: {}", id, e),
+            }
+        }
+    }
+    let mut purged = 0;
+    if let Some(days) = settings.purge_after_days {
+        let ids = older_than(
+            conn,
+            "SELECT id, archived_at FROM recordings WHERE archived_at IS NOT NULL",
+            [],
+            days,
+        )?;
+        for id in ids {
+            match purge(conn, &id) {
+                Ok(()) => purged += 1,
+                Err(e) => println!("WARNING: Could not purge recording {}: {}", id, e),
+            }
+        }
+    }
+    Ok((archived, purged))
+}
+
+// IDs from `query`, which selects an ID and an RFC 3339 time, whose time is
+// more than `days` ago. Times are compared parsed, since stored offsets can
+// differ.
+fn older_than<P: rusqlite::Params>(
+    conn: &Connection,
+    query: &str,
+    params: P,
+    days: u32,
+) -> Result<Vec<String>, String> {
+    let cutoff = Local::now() - Duration::days(days as i64);
+    let mut stmt = conn.prepare(query).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params, |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| e.to_string())?;
+    let mut ids = Vec::new();
+    for row in rows {
+        let (id, time) = row.map_err(|e| e.to_string())?;
+        match DateTime::parse_from_rfc3339(&time) {
+            Ok(time) if time < cutoff => ids.push(id),
+            Ok(_) => {}
+            Err(e) => println!("WARNING: Recording {} has an invalid time: {}", id, e),
+        }
+    }
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::test_db;
+
+    fn recording(conn: &Connection, id: &str, created_at: &str) {
+        conn.execute(
+            "INSERT INTO recordings (id, path, status, created_at) VALUES (?1, ?1, ?2, ?3)",
+            params![id, PROCESSED, created_at],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn older_than_compares_parsed_times() {
+        let conn = test_db();
+        let now = Local::now();
+        recording(&conn, "old", &(now - Duration::days(31)).to_rfc3339());
+        recording(&conn, "new", &(now - Duration::days(29)).to_rfc3339());
+        // Same instant as "old", written with a different offset.
+        recording(
+            &conn,
+            "old-utc",
+            &(now - Duration::days(31))
+                .with_timezone(&chrono::Utc)
+                .to_rfc3339(),
+        );
+        recording(&conn, "garbled", "last month");
+
+        let mut ids = older_than(
+            &conn,
+            "SELECT id, created_at FROM recordings WHERE status = ?1",
+            [PROCESSED],
+            30,
+        )
+        .unwrap();
+        ids.sort();
+        assert_eq!(ids, vec!["old", "old-utc"]);
+    }
+
+    #[test]
+    fn retention_steps_can_be_turned_off() {
+        let conn = test_db();
+        recording(
+            &conn,
+            "old",
+            &(Local::now() - Duration::days(400)).to_rfc3339(),
+        );
+        let off = RetentionSettings {
+            archive_after_days: None,
+            purge_after_days: None,
+        };
+        let dir = std::env::temp_dir();
+        assert_eq!(apply_retention(&conn, &off, &dir).unwrap(), (0, 0));
+        assert_eq!(get(&conn, "old").unwrap().status, PROCESSED);
+    }
+
+    #[test]
+    fn audio_is_never_deleted_by_default() {
+        assert_eq!(RetentionSettings::default().purge_after_days, None);
+        let stored: RetentionSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(stored.purge_after_days, None);
+        assert_eq!(stored.archive_after_days, Some(30));
+    }
+
+    #[test]
+    fn one_failing_recording_does_not_stop_retention() {
+        let conn = test_db();
+        let dir = std::env::temp_dir().join("construction-os-retention-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let old = (Local::now() - Duration::days(400)).to_rfc3339();
+        let insert = |id: &str, path: &str, archived_at: Option<&str>| {
+            conn.execute(
+                "INSERT INTO recordings (id, path, status, created_at, archived_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![id, path, PROCESSED, old, archived_at],
+            )
+            .unwrap();
+        };
+        // "/" has no file name to archive under; a directory cannot be
+        // deleted as a file.
+        let audio = dir.join("good.wav");
+        fs::write(&audio, b"RIFF").unwrap();
+        insert("good", &audio.to_string_lossy(), None);
+        insert("unmovable", "/", None);
+        let stuck = dir.join("stuck");
+        fs::create_dir_all(&stuck).unwrap();
+        insert("undeletable", &stuck.to_string_lossy(), Some(&old));
+        insert("gone", &dir.join("gone.wav").to_string_lossy(), Some(&old));
+
+        let settings = RetentionSettings {
+            archive_after_days: Some(30),
+            purge_after_days: Some(90),
+        };
+        let archive_dir = dir.join("archive");
+        assert_eq!(
+            apply_retention(&conn, &settings, &archive_dir).unwrap(),
+            (1, 1)
+        );
+        assert!(archive_dir.join("good.wav").exists());
+        assert!(get(&conn, "good").unwrap().archived_at.is_some());
+        assert!(get(&conn, "unmovable").unwrap().archived_at.is_none());
+        assert!(get(&conn, "undeletable").is_ok());
+        assert!(get(&conn, "gone").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
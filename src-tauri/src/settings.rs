@@ -0,0 +1,34 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// Typed access to the `app_settings` key/value table. Missing keys fall back
+// to the type's `Default`, so new settings need no migration.
+
+pub fn load<T: DeserializeOwned + Default>(conn: &Connection, key: &str) -> Result<T, String> {
+    let raw: Option<String> = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            [key],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    match raw {
+        Some(json) => {
+            serde_json::from_str(&json).map_err(|e| format!("Invalid '{}' settings: {}", key, e))
+        }
+        None => Ok(T::default()),
+    }
+}
+
+pub fn save<T: Serialize>(conn: &Connection, key: &str, value: &T) -> Result<(), String> {
+    let json = serde_json::to_string(value).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![key, json],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,224 @@
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter};
+
+use crate::audio::{self, SAMPLE_RATE};
+use crate::recorder::LiveAudio;
+use crate::vad;
+use crate::whisper_engine::{Segment, Transcription};
+
+// Live transcription while the native recorder runs. Every pass the audio
+// since the last committed point is transcribed again and sent as a
+// "transcript-partial" event. Once the speaker pauses, or the window grows
+// too long, the settled part is committed and sent as "transcript-final", so
+// each stretch of audio is finalised once and the window stays short.
+
+const PARTIAL_EVENT: &str = "transcript-partial";
+const FINAL_EVENT: &str = "transcript-final";
+const PASS_INTERVAL: Duration = Duration::from_millis(1_000);
+// Less than this is not worth a pass.
+const MIN_WINDOW_MS: usize = 1_000;
+// Silence after the last speech that closes an utterance.
+const PAUSE_MS: usize = 1_000;
+// Windows longer than this commit everything but the segment still going.
+const MAX_WINDOW_MS: usize = 20_000;
+
+// Payload of both events. Times are from the start of the recording; a
+// partial replaces the previous partial, a final is appended to the ones
+// before it.
+#[derive(serde::Serialize, Clone, Debug)]
+struct TranscriptUpdate {
+    text: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Default)]
+pub struct Streaming {
+    active: Mutex<Option<LiveTranscriber>>,
+}
+
+pub struct LiveTranscriber {
+    finish: mpsc::Sender<Vec<f32>>,
+    thread: JoinHandle<Result<Transcription, String>>,
+}
+
+impl Streaming {
+    // Starts transcribing `audio` as it is captured. `transcribe` turns
+    // 16 kHz samples into text, as a whole recording would be. Replaces any
+    // transcriber left over from an earlier recording.
+    pub fn start<F>(&self, app_handle: AppHandle, audio: LiveAudio, transcribe: F)
+    where
+        F: Fn(&[f32]) -> Result<Transcription, String> + Send + 'static,
+    {
+        let (finish_tx, finish_rx) = mpsc::channel();
+        let thread = thread::spawn(move || run(app_handle, audio, finish_rx, transcribe));
+        let transcriber = LiveTranscriber {
+            finish: finish_tx,
+            thread,
+        };
+        if let Ok(mut active) = self.active.lock() {
+            *active = Some(transcriber);
+        }
+    }
+
+    pub fn take(&self) -> Option<LiveTranscriber> {
+        self.active.lock().ok()?.take()
+    }
+}
+
+impl LiveTranscriber {
+    // Hands over the finished recording (16 kHz mono) so the last words can
+    // be committed, and returns the transcript of the whole recording.
+    pub fn finish(self, samples: Vec<f32>) -> Result<Transcription, String> {
+        self.finish
+            .send(samples)
+            .map_err(|_| "Live transcription stopped unexpectedly".to_string())?;
+        self.thread
+            .join()
+            .map_err(|_| "Live transcription thread panicked".to_string())?
+    }
+}
+
+fn run<F>(
+    app_handle: AppHandle,
+    audio: LiveAudio,
+    finish: mpsc::Receiver<Vec<f32>>,
+    transcribe: F,
+) -> Result<Transcription, String>
+where
+    F: Fn(&[f32]) -> Result<Transcription, String>,
+{
+    let rate = audio.rate() as usize;
+    let mut live = Live {
+        app_handle,
+        transcribe,
+        // In device samples; the final recording is at 16 kHz.
+        committed: 0,
+        segments: Vec::new(),
+    };
+    loop {
+        match finish.recv_timeout(PASS_INTERVAL) {
+            Ok(samples) => {
+                let start = (live.committed * SAMPLE_RATE as usize / rate).min(samples.len());
+                live.commit_all(&samples[start..], live.committed * 1000 / rate)?;
+                return Ok(live.transcription());
+            }
+            // The recording was abandoned.
+            Err(RecvTimeoutError::Disconnected) => return Ok(live.transcription()),
+            Err(RecvTimeoutError::Timeout) => {
+                let raw = audio.since(live.committed);
+                if raw.len() * 1000 / rate < MIN_WINDOW_MS {
+                    continue;
+                }
+                let window = audio::resample(&raw, rate as u32)?;
+                let offset_ms = live.committed * 1000 / rate;
+                let committed_ms = live.pass(&window, offset_ms)?;
+                live.committed += committed_ms * rate / 1000;
+            }
+        }
+    }
+}
+
+struct Live<F> {
+    app_handle: AppHandle,
+    transcribe: F,
+    committed: usize,
+    segments: Vec<Segment>,
+}
+
+impl<F> Live<F>
+where
+    F: Fn(&[f32]) -> Result<Transcription, String>,
+{
+    // One look at the uncommitted `window`, which starts `offset_ms` into the
+    // recording. Returns how many milliseconds of it were committed.
+    fn pass(&mut self, window: &[f32], offset_ms: usize) -> Result<usize, String> {
+        let window_ms = window.len() * 1000 / SAMPLE_RATE as usize;
+        let last_speech_ms = match vad::speech_chunks(window).last() {
+            Some(chunk) => chunk.end * 1000 / SAMPLE_RATE as usize,
+            // Nothing said yet; drop the silence once it is long enough that
+            // the next words cannot be part of it.
+            None if window_ms >= PAUSE_MS * 2 => return Ok(window_ms - PAUSE_MS),
+            None => return Ok(0),
+        };
+        if window_ms - last_speech_ms >= PAUSE_MS {
+            self.commit_all(window, offset_ms)?;
+            return Ok(window_ms);
+        }
+
+        let mut segments = match (self.transcribe)(window)? {
+            Transcription::Speech { segments, .. } => segments,
+            Transcription::NoSpeech => Vec::new(),
+        };
+        shift(&mut segments, offset_ms);
+        if window_ms >= MAX_WINDOW_MS && segments.len() > 1 {
+            // The last segment may still be growing; everything before it is
+            // as good as it will get.
+            let open = segments.split_off(segments.len() - 1);
+            let committed_ms = (open[0].start_ms as usize).saturating_sub(offset_ms);
+            self.commit(segments);
+            self.emit(PARTIAL_EVENT, open);
+            return Ok(committed_ms);
+        }
+        self.emit(PARTIAL_EVENT, segments);
+        Ok(0)
+    }
+
+    fn commit_all(&mut self, window: &[f32], offset_ms: usize) -> Result<(), String> {
+        if window.is_empty() {
+            return Ok(());
+        }
+        if let Transcription::Speech { mut segments, .. } = (self.transcribe)(window)? {
+            shift(&mut segments, offset_ms);
+            self.commit(segments);
+        }
+        // Clear the partial now that its words are final.
+        self.emit(PARTIAL_EVENT, Vec::new());
+        Ok(())
+    }
+
+    fn commit(&mut self, segments: Vec<Segment>) {
+        if segments.is_empty() {
+            return;
+        }
+        self.emit(FINAL_EVENT, segments.clone());
+        self.segments.extend(segments);
+    }
+
+    fn emit(&self, event: &str, segments: Vec<Segment>) {
+        let update = TranscriptUpdate {
+            text: join_text(&segments),
+            segments,
+        };
+        let _ = self.app_handle.emit(event, update);
+    }
+
+    fn transcription(self) -> Transcription {
+        if self.segments.is_empty() {
+            return Transcription::NoSpeech;
+        }
+        Transcription::Speech {
+            text: join_text(&self.segments),
+            segments: self.segments,
+        }
+    }
+}
+
+fn shift(segments: &mut [Segment], offset_ms: usize) {
+    for segment in segments {
+        segment.start_ms += offset_ms as i64;
+        segment.end_ms += offset_ms as i64;
+    }
+}
+
+fn join_text(segments: &[Segment]) -> String {
+    segments
+        .iter()
+        .map(|segment| segment.text.as_str())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
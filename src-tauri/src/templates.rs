@@ -0,0 +1,566 @@
+use chrono::{DateTime, Local, NaiveDate};
+use handlebars::Handlebars;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::company::CompanyProfile;
+use crate::estimates::Estimate;
+use crate::money::Money;
+use crate::pdf::LITERAL;
+use crate::{Invoice, InvoiceItem};
+
+// Layouts for invoices and estimates. A template is Handlebars source that
+// renders to a few lines of markup, which pdf.rs turns into the page:
+//
+//   !logo                     the company logo, if one is set
+//   # Title | right           large heading, with an optional right-aligned part
+//   ## Heading | right        medium heading
+//   ### LABEL | right         small grey label
+//   ~ detail | right          grey detail text
+//   ---                       horizontal rule
+//   |= Col | Col | ...        table header; the first column wraps, the rest
+//   | cell | cell | ...       are right-aligned
+//   > Label | value           summary line under a table
+//   >> Label | value          bold total
+//   anything else             a paragraph, wrapped; blank lines add space
+//
+// Values are flattened to a single line with '|' replaced, and those that
+// start like markup are marked with `pdf::LITERAL`, so they cannot break the
+// markup. See `Document` for what templates can use; `kind` tells
+// invoices and estimates apart, e.g. `{{#if (eq kind "estimate")}}`.
+
+// The first is the default.
+const BUILT_IN: [(&str, &str, &str); 2] = [
+    ("classic", "Classic", include_str!("templates/classic.hbs")),
+    ("minimal", "Minimal", include_str!("templates/minimal.hbs")),
+];
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct Template {
+    // Empty for a new template.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub body: String,
+    #[serde(default)]
+    pub built_in: bool,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+// Built-in templates first, then the user's by name.
+pub fn list(conn: &Connection) -> Result<Vec<Template>, String> {
+    let mut templates: Vec<Template> = BUILT_IN.iter().map(built_in).collect();
+    let mut stmt = conn
+        .prepare("SELECT id, name, body, updated_at FROM document_templates ORDER BY name COLLATE NOCASE")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(Template {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                body: row.get(2)?,
+                built_in: false,
+                updated_at: row.get(3)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        templates.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(templates)
+}
+
+pub fn get(conn: &Connection, id: &str) -> Result<Option<Template>, String> {
+    if let Some(template) = BUILT_IN.iter().find(|(builtin_id, _, _)| *builtin_id == id) {
+        return Ok(Some(built_in(template)));
+    }
+    conn.query_row(
+        "SELECT id, name, body, updated_at FROM document_templates WHERE id = ?1",
+        [id],
+        |row| {
+            Ok(Template {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                body: row.get(2)?,
+                built_in: false,
+                updated_at: row.get(3)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+// Creates the template, or updates it when the ID exists. The body must
+// compile; built-in templates cannot be changed. Returns the ID.
+pub fn save(conn: &Connection, template: &Template) -> Result<String, String> {
+    if is_built_in(&template.id) {
+        return Err("Built-in templates cannot be changed; save a copy instead".to_string());
+    }
+    let name = template.name.trim();
+    if name.is_empty() {
+        return Err("Template name is required".to_string());
+    }
+    Handlebars::new()
+        .register_template_string("template", &template.body)
+        .map_err(|e| format!("Invalid template: {}", e))?;
+
+    let id = if template.id.is_empty() {
+        crate::new_id()
+    } else {
+        template.id.clone()
+    };
+    let now = Local::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO document_templates (id, name, body, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?4)
+         ON CONFLICT(id) DO UPDATE SET name = excluded.name, body = excluded.body, updated_at = excluded.updated_at",
+        params![id, name, template.body, now],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(id)
+}
+
+// Deletes the template; clients using it fall back to the company default.
+pub fn delete(conn: &Connection, id: &str) -> Result<(), String> {
+    if is_built_in(id) {
+        return Err("Built-in templates cannot be deleted".to_string());
+    }
+    conn.execute("DELETE FROM document_templates WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE contacts SET template_id = NULL WHERE template_id = ?1",
+        [id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn exists(conn: &Connection, id: &str) -> Result<bool, String> {
+    Ok(get(conn, id)?.is_some())
+}
+
+// The template for documents addressed to `client`: the contact's with that
+// exact name (ignoring case), else the company default, else the built-in
+// default. Several contacts with the name count as no match, and IDs that no
+// longer exist are skipped.
+pub fn for_client(
+    conn: &Connection,
+    client: &str,
+    company: &CompanyProfile,
+) -> Result<Template, String> {
+    let mut stmt = conn
+        .prepare("SELECT template_id FROM contacts WHERE lower(trim(name)) = lower(trim(?1))")
+        .map_err(|e| e.to_string())?;
+    let matches = stmt
+        .query_map([client], |row| row.get::<_, Option<String>>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let contact_template = match matches.as_slice() {
+        [template_id] => template_id.clone(),
+        _ => None,
+    };
+    for id in [contact_template.as_deref(), company.template_id.as_deref()]
+        .into_iter()
+        .flatten()
+    {
+        if let Some(template) = get(conn, id)? {
+            return Ok(template);
+        }
+    }
+    Ok(built_in(&BUILT_IN[0]))
+}
+
+fn built_in((id, name, body): &(&str, &str, &str)) -> Template {
+    Template {
+        id: id.to_string(),
+        name: name.to_string(),
+        body: body.to_string(),
+        built_in: true,
+        updated_at: None,
+    }
+}
+
+fn is_built_in(id: &str) -> bool {
+    BUILT_IN.iter().any(|(builtin_id, _, _)| *builtin_id == id)
+}
+
+// Everything a template can refer to, with amounts already formatted
+// ("$1234.50").
+#[derive(serde::Serialize, Debug)]
+pub struct Document {
+    // "invoice" or "estimate"
+    pub kind: &'static str,
+    // "INVOICE" or "ESTIMATE"
+    pub title: &'static str,
+    pub number: String,
+    pub date: String,
+    // Estimates only, e.g. "November 16, 2026"; empty otherwise.
+    pub valid_until: String,
+    pub description: String,
+    pub client: Client,
+    pub company: Company,
+    pub items: Vec<Line>,
+    pub subtotal: String,
+    // Empty when no tax applies, so `{{#if tax_rate}}` hides the line.
+    pub tax_rate: String,
+    pub tax: String,
+    pub total: String,
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct Client {
+    pub name: String,
+    pub company: String,
+    pub phone: String,
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct Company {
+    pub name: String,
+    pub licence_number: String,
+    pub address: String,
+    pub tax_id: String,
+    pub payment_terms: String,
+    pub footer: String,
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct Line {
+    pub description: String,
+    // With the unit, e.g. "2.5 hr".
+    pub quantity: String,
+    pub unit_price: String,
+    pub amount: String,
+    pub taxable: bool,
+}
+
+impl Document {
+    pub fn invoice(invoice: &Invoice, client: Client, company: &CompanyProfile) -> Self {
+        Document {
+            kind: "invoice",
+            title: "INVOICE",
+            number: field(invoice.number.as_deref().unwrap_or_default()),
+            date: long_date(invoice.created_at.as_deref()),
+            valid_until: String::new(),
+            description: field(&invoice.description),
+            client,
+            company: Company::new(company),
+            items: invoice.items.iter().map(Line::new).collect(),
+            subtotal: dollars(invoice.subtotal),
+            tax_rate: tax_rate(invoice.tax_rate, invoice.tax),
+            tax: dollars(invoice.tax),
+            total: dollars(invoice.amount),
+        }
+    }
+
+    pub fn estimate(estimate: &Estimate, client: Client, company: &CompanyProfile) -> Self {
+        Document {
+            kind: "estimate",
+            title: "ESTIMATE",
+            number: field(estimate.number.as_deref().unwrap_or_default()),
+            date: long_date(estimate.created_at.as_deref()),
+            valid_until: estimate
+                .valid_until
+                .as_deref()
+                .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+                .map(|date| date.format("%B %-d, %Y").to_string())
+                .unwrap_or_default(),
+            description: field(&estimate.description),
+            client,
+            company: Company::new(company),
+            items: estimate.items.iter().map(Line::new).collect(),
+            subtotal: dollars(estimate.subtotal),
+            tax_rate: tax_rate(estimate.tax_rate, estimate.tax),
+            tax: dollars(estimate.tax),
+            total: dollars(estimate.amount),
+        }
+    }
+}
+
+// The `tax_rate` field of a document.
+fn tax_rate(rate: f64, tax: Money) -> String {
+    if rate == 0.0 && tax == Money::ZERO {
+        String::new()
+    } else {
+        number(rate)
+    }
+}
+
+impl Client {
+    pub fn new(name: &str, company: Option<&str>, phone: Option<&str>) -> Self {
+        Client {
+            name: field(name),
+            company: field(company.unwrap_or_default()),
+            phone: field(phone.unwrap_or_default()),
+        }
+    }
+}
+
+impl Company {
+    pub fn new(profile: &CompanyProfile) -> Self {
+        let optional = |value: &Option<String>| field(value.as_deref().unwrap_or_default());
+        Company {
+            name: field(&profile.name),
+            licence_number: optional(&profile.licence_number),
+            address: optional(&profile.address),
+            tax_id: optional(&profile.tax_id),
+            payment_terms: optional(&profile.payment_terms),
+            footer: optional(&profile.footer),
+        }
+    }
+}
+
+impl Line {
+    pub fn new(item: &InvoiceItem) -> Self {
+        let quantity = match item.unit.as_deref().map(str::trim) {
+            Some(unit) if !unit.is_empty() => format!("{} {}", number(item.quantity), unit),
+            _ => number(item.quantity),
+        };
+        Line {
+            description: field(&item.description),
+            quantity: field(&quantity),
+            unit_price: dollars(item.unit_price),
+            amount: dollars(item.line_total()),
+            taxable: item.taxable,
+        }
+    }
+}
+
+// Renders `template` for `document` into markup for pdf.rs.
+pub fn render(template: &Template, document: &Document) -> Result<String, String> {
+    let mut handlebars = Handlebars::new();
+    // The output is markup, not HTML.
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars
+        .render_template(&template.body, document)
+        .map_err(|e| format!("Template '{}' failed: {}", template.name, e))
+}
+
+// A value that fits on one markup line; separate lines (e.g. of an address)
+// are joined with commas. A value that starts like markup ("# 1 priority",
+// "---", "!logo") is marked so that pdf.rs draws it as text even when a
+// template puts it at the start of a line.
+pub fn field(value: &str) -> String {
+    let value = value
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ")
+        .replace(['|', LITERAL], "/");
+    if value.starts_with(['#', '>', '~', '-', '!']) {
+        format!("{}{}", LITERAL, value)
+    } else {
+        value
+    }
+}
+
+// "$1234.50", with the sign in front of the dollar sign.
+pub fn dollars(amount: Money) -> String {
+    if amount < Money::ZERO {
+        format!("-${}", -amount)
+    } else {
+        format!("${}", amount)
+    }
+}
+
+// Up to two decimals, without trailing zeros.
+pub fn number(value: f64) -> String {
+    format!("{:.2}", value)
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+// E.g. "October 17, 2026"; today when `timestamp` is missing or invalid.
+pub fn long_date(timestamp: Option<&str>) -> String {
+    timestamp
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|date| date.with_timezone(&Local))
+        .unwrap_or_else(Local::now)
+        .format("%B %-d, %Y")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoice() -> Invoice {
+        let mut invoice: Invoice = serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "number": "INV-0042",
+            "client": "Dave",
+            "amount": 0,
+            "status": "DRAFT",
+            "description": "Kitchen\nremodel",
+            "created_at": "2026-10-17T09:00:00+00:00",
+            "tax_rate": 10,
+            "items": [
+                {"description": "Cabinets | install", "quantity": 2, "unit": "day", "unit_price": 450, "taxable": true},
+                {"description": "Disposal", "unit_price": "99.5"}
+            ]
+        }))
+        .unwrap();
+        invoice.apply_item_totals();
+        invoice
+    }
+
+    fn company() -> CompanyProfile {
+        CompanyProfile {
+            name: "Acme Builders".to_string(),
+            address: Some("1 Main St\nSpringfield".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn classic_invoice_renders_to_markup() {
+        let document = Document::invoice(
+            &invoice(),
+            Client::new("Dave", Some("Dave's Diner"), None),
+            &company(),
+        );
+        let markup = render(&built_in(&BUILT_IN[0]), &document).unwrap();
+        let lines: Vec<&str> = markup.lines().collect();
+        for expected in [
+            "!logo",
+            "# INVOICE | INV-0042",
+            "~ 1 Main St, Springfield",
+            "### BILL TO",
+            "## Dave",
+            "~ Dave's Diner",
+            "Kitchen, remodel",
+            "| Cabinets / install | 2 day | $450.00 | $900.00",
+            "| Disposal | 1 | $99.50 | $99.50",
+            "> Subtotal | $999.50",
+            "> Tax (10%) | $90.00",
+            ">> Total Due | $1089.50",
+        ] {
+            assert!(
+                lines.contains(&expected),
+                "missing {:?} in\n{}",
+                expected,
+                markup
+            );
+        }
+        assert!(!markup.contains("PAYMENT TERMS"));
+    }
+
+    #[test]
+    fn estimates_use_their_own_wording() {
+        let mut estimate: Estimate = serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "number": "EST-0001",
+            "client": "Ann",
+            "amount": 1200,
+            "description": "Deck",
+            "valid_until": "2026-11-16"
+        }))
+        .unwrap();
+        estimate.apply_item_totals();
+        let document = Document::estimate(&estimate, Client::new("Ann", None, None), &company());
+        for template in &BUILT_IN {
+            let markup = render(&built_in(template), &document).unwrap();
+            assert!(markup.contains("ESTIMATE"), "{}", markup);
+            assert!(markup.contains("November 16, 2026"), "{}", markup);
+            assert!(!markup.contains("Tax ("), "{}", markup);
+        }
+    }
+
+    #[test]
+    fn formats_amounts_and_quantities() {
+        assert_eq!(dollars(Money::from_cents(-1250)), "-$12.50");
+        assert_eq!(number(2.50), "2.5");
+        assert_eq!(number(3.0), "3");
+        assert_eq!(
+            long_date(Some("2026-10-17T12:00:00+00:00")),
+            "October 17, 2026"
+        );
+    }
+
+    #[test]
+    fn broken_templates_report_their_name() {
+        let template = Template {
+            id: String::new(),
+            name: "Mine".to_string(),
+            body: "{{#if}}".to_string(),
+            built_in: false,
+            updated_at: None,
+        };
+        let document = Document::invoice(&invoice(), Client::new("Dave", None, None), &company());
+        assert!(render(&template, &document).unwrap_err().contains("Mine"));
+    }
+
+    #[test]
+    fn values_cannot_inject_markup() {
+        assert_eq!(field("a | b"), "a / b");
+        assert_eq!(field("| x | y"), "/ x / y");
+        for hostile in ["# FREE", "---", "!logo", "> Discount | -$500", "~ note"] {
+            assert!(field(hostile).starts_with(LITERAL), "{:?}", hostile);
+        }
+        assert_eq!(field("-$5 credit").replace(LITERAL, ""), "-$5 credit");
+        assert!(!field("Kitchen").starts_with(LITERAL));
+
+        let mut invoice = invoice();
+        invoice.description = "# PAID IN FULL".to_string();
+        invoice.items[1].description = "---".to_string();
+        let document = Document::invoice(
+            &invoice,
+            Client::new("!logo", Some(">> Total Due | $0"), None),
+            &company(),
+        );
+        let markup = render(&built_in(&BUILT_IN[0]), &document).unwrap();
+        let lines: Vec<&str> = markup.lines().collect();
+        assert!(
+            lines.contains(&format!("{}# PAID IN FULL", LITERAL).as_str()),
+            "{}",
+            markup
+        );
+        assert!(!lines.contains(&"# PAID IN FULL"));
+        assert_eq!(lines.iter().filter(|line| **line == "!logo").count(), 1);
+        assert_eq!(
+            lines.iter().filter(|line| line.starts_with(">>")).count(),
+            1
+        );
+        assert!(crate::pdf::render(&markup, "INV-0042", None).is_ok());
+    }
+
+    #[test]
+    fn client_templates_match_exact_names_only() {
+        let conn = crate::migrations::test_db();
+        let mine = Template {
+            id: String::new(),
+            name: "Mine".to_string(),
+            body: "# {{title}}".to_string(),
+            built_in: false,
+            updated_at: None,
+        };
+        let id = save(&conn, &mine).unwrap();
+        let contact = |contact_id: &str, name: &str| {
+            conn.execute(
+                "INSERT INTO contacts (id, name, phone, template_id) VALUES (?1, ?2, '', ?3)",
+                params![contact_id, name, id],
+            )
+            .unwrap();
+        };
+        contact("1", "Dave Miller");
+        let chosen = |client: &str| for_client(&conn, client, &company()).unwrap().id;
+
+        assert_eq!(chosen("dave miller"), id);
+        // Not "Dave" for "Dave Miller", nor the other way round.
+        assert_eq!(chosen("Dave"), "classic");
+        contact("2", "Dave");
+        assert_eq!(chosen("Dave"), id);
+        // Two contacts called Dave: nobody's template.
+        contact("3", "DAVE");
+        assert_eq!(chosen("Dave"), "classic");
+
+        let company = CompanyProfile {
+            template_id: Some("minimal".to_string()),
+            ..company()
+        };
+        assert_eq!(for_client(&conn, "Nobody", &company).unwrap().id, "minimal");
+    }
+}
@@ -0,0 +1,181 @@
+use std::ops::Range;
+
+use crate::audio::SAMPLE_RATE;
+
+// Energy-based voice activity detection. Whisper invents text when fed long
+// stretches of wind or generator noise, so recordings are cut down to the
+// parts that stand out from their own background level before inference.
+// Works on 16 kHz mono samples; returns sample ranges.
+
+// 20 ms frames.
+const FRAME: usize = SAMPLE_RATE as usize / 50;
+// Frames this far above the background count as speech...
+const MARGIN_DB: f32 = 10.0;
+// ...as long as they are not quieter than this in absolute terms.
+const MIN_SPEECH_DB: f32 = -50.0;
+// Share of frames assumed to be background, for the noise floor estimate.
+const NOISE_PERCENTILE: usize = 15;
+// Bursts shorter than 200 ms are clicks or gusts, not words.
+const MIN_SPEECH_FRAMES: usize = 10;
+// Pauses shorter than 800 ms stay inside one chunk.
+const MAX_PAUSE_FRAMES: usize = 40;
+// 300 ms kept either side of speech so word edges are not clipped.
+const PAD_FRAMES: usize = 15;
+// Whisper's window is 30 s; longer chunks are split at 25 s or before.
+const MAX_CHUNK_FRAMES: usize = 25 * 50;
+const HIGH_PASS_HZ: f32 = 100.0;
+// How far back from the limit to look for a quiet place to split.
+const SPLIT_SEARCH_FRAMES: usize = 5 * 50;
+
+// Speech chunks in order, or an empty list if nothing sounded like speech.
+pub fn speech_chunks(samples: &[f32]) -> Vec<Range<usize>> {
+    let energies = frame_energies(samples);
+    if energies.is_empty() {
+        return Vec::new();
+    }
+    let mut sorted = energies.clone();
+    sorted.sort_by(f32::total_cmp);
+    let noise_floor = sorted[sorted.len() * NOISE_PERCENTILE / 100];
+    let threshold = (noise_floor + MARGIN_DB).max(MIN_SPEECH_DB);
+
+    // Runs of loud frames, with short pauses bridged and short bursts dropped.
+    let mut runs: Vec<Range<usize>> = Vec::new();
+    let mut start = None;
+    for (i, &energy) in energies.iter().enumerate() {
+        match (energy > threshold, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                runs.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        runs.push(s..energies.len());
+    }
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for run in runs {
+        match merged.last_mut() {
+            Some(last) if run.start - last.end <= MAX_PAUSE_FRAMES => last.end = run.end,
+            _ => merged.push(run),
+        }
+    }
+    merged.retain(|run| run.len() >= MIN_SPEECH_FRAMES);
+
+    let mut chunks = Vec::new();
+    for run in merged {
+        let padded =
+            run.start.saturating_sub(PAD_FRAMES)..(run.end + PAD_FRAMES).min(energies.len());
+        split_long(padded, &energies, &mut chunks);
+    }
+    chunks
+        .into_iter()
+        .map(|frames| frames.start * FRAME..(frames.end * FRAME).min(samples.len()))
+        .collect()
+}
+
+// Mean power per frame in dBFS, after a 100 Hz high-pass filter that takes
+// out most wind rumble and generator hum.
+fn frame_energies(samples: &[f32]) -> Vec<f32> {
+    // First-order high-pass: y[n] = a * (y[n-1] + x[n] - x[n-1]).
+    let a = 1.0 / (1.0 + std::f32::consts::TAU * HIGH_PASS_HZ / SAMPLE_RATE as f32);
+    let mut previous_in = 0.0;
+    let mut previous_out = 0.0;
+    let filtered: Vec<f32> = samples
+        .iter()
+        .map(|&x| {
+            previous_out = a * (previous_out + x - previous_in);
+            previous_in = x;
+            previous_out
+        })
+        .collect();
+    filtered
+        .chunks(FRAME)
+        .map(|frame| {
+            let power = frame.iter().map(|v| v * v).sum::<f32>() / frame.len() as f32;
+            10.0 * (power + 1e-10).log10()
+        })
+        .collect()
+}
+
+// Splits `frames` into pieces Whisper can take in one pass, cutting at the
+// quietest frame near each limit.
+fn split_long(mut frames: Range<usize>, energies: &[f32], chunks: &mut Vec<Range<usize>>) {
+    while frames.len() > MAX_CHUNK_FRAMES {
+        let limit = frames.start + MAX_CHUNK_FRAMES;
+        let search = limit - SPLIT_SEARCH_FRAMES..limit;
+        let cut = search
+            .min_by(|&a, &b| energies[a].total_cmp(&energies[b]))
+            .unwrap_or(limit);
+        chunks.push(frames.start..cut);
+        frames.start = cut;
+    }
+    chunks.push(frames);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: usize = SAMPLE_RATE as usize;
+
+    // Quiet hiss with a 440 Hz tone over the `speech` (start, end) sample spans.
+    fn clip(len: usize, speech: &[(usize, usize)]) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                let hiss = if i % 2 == 0 { 0.001 } else { -0.001 };
+                let tone = if speech.iter().any(|&(start, end)| (start..end).contains(&i)) {
+                    0.3 * (i as f32 * 440.0 * std::f32::consts::TAU / SAMPLE_RATE as f32).sin()
+                } else {
+                    0.0
+                };
+                hiss + tone
+            })
+            .collect()
+    }
+
+    #[test]
+    fn silence_has_no_speech() {
+        assert!(speech_chunks(&[]).is_empty());
+        assert!(speech_chunks(&vec![0.0; 3 * SECOND]).is_empty());
+        assert!(speech_chunks(&clip(3 * SECOND, &[])).is_empty());
+    }
+
+    #[test]
+    fn finds_speech_with_padding() {
+        let samples = clip(5 * SECOND, &[(2 * SECOND, 3 * SECOND)]);
+        let chunks = speech_chunks(&samples);
+        assert_eq!(chunks.len(), 1);
+        let chunk = &chunks[0];
+        let pad = PAD_FRAMES * FRAME;
+        assert!(chunk.start <= 2 * SECOND && chunk.start >= 2 * SECOND - pad - FRAME);
+        assert!(chunk.end >= 3 * SECOND && chunk.end <= 3 * SECOND + pad + FRAME);
+    }
+
+    #[test]
+    fn bridges_short_pauses_and_drops_clicks() {
+        let samples = clip(
+            8 * SECOND,
+            &[
+                (SECOND, 2 * SECOND),
+                (2 * SECOND + SECOND / 4, 3 * SECOND),
+                (6 * SECOND, 6 * SECOND + SECOND / 20),
+            ],
+        );
+        let chunks = speech_chunks(&samples);
+        assert_eq!(chunks.len(), 1, "{:?}", chunks);
+        assert!(chunks[0].end < 4 * SECOND);
+    }
+
+    #[test]
+    fn splits_long_speech_into_whisper_sized_chunks() {
+        // Enough silence around it for a noise floor.
+        let samples = clip(90 * SECOND, &[(10 * SECOND, 70 * SECOND)]);
+        let chunks = speech_chunks(&samples);
+        assert!(chunks.len() >= 3, "{:?}", chunks);
+        assert!(chunks.iter().all(|c| c.len() <= MAX_CHUNK_FRAMES * FRAME));
+        // Contiguous: nothing is lost at the cuts.
+        assert!(chunks.windows(2).all(|pair| pair[0].end == pair[1].start));
+    }
+}
@@ -0,0 +1,346 @@
+use crate::whisper_engine::{Segment, Word};
+
+// Domain words Whisper would otherwise mishear. Contact names and companies
+// plus a user-editable trade glossary go into the initial prompt, which biases
+// decoding towards their spellings; names it still gets slightly wrong are
+// snapped to the closest contact afterwards.
+
+// Stored under "vocabulary" in `app_settings`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct VocabularySettings {
+    #[serde(default = "default_glossary")]
+    pub glossary: Vec<String>,
+}
+
+impl Default for VocabularySettings {
+    fn default() -> Self {
+        VocabularySettings {
+            glossary: default_glossary(),
+        }
+    }
+}
+
+fn default_glossary() -> Vec<String> {
+    [
+        "soffit",
+        "fascia",
+        "joist hanger",
+        "Simpson Strong-Tie",
+        "sill plate",
+        "rafter",
+        "truss",
+        "lintel",
+        "flashing",
+        "drywall",
+        "shiplap",
+        "rebar",
+        "OSB",
+        "LVL",
+        "PEX",
+        "GFCI",
+        "stucco",
+        "grout",
+    ]
+    .iter()
+    .map(|term| term.to_string())
+    .collect()
+}
+
+// Trims terms and drops blanks and case-insensitive duplicates, keeping the
+// first spelling.
+pub fn clean_terms(terms: Vec<String>) -> Vec<String> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for term in terms {
+        let term = term.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !term.is_empty() && !cleaned.iter().any(|t| t.eq_ignore_ascii_case(&term)) {
+            cleaned.push(term);
+        }
+    }
+    cleaned
+}
+
+// Whisper only reads the last 224 tokens of a prompt; at roughly four
+// characters a token this keeps clear of that.
+const MAX_PROMPT_CHARS: usize = 800;
+// Shorter names match too many ordinary words to be snapped safely.
+const MIN_SNAP_CHARS: usize = 5;
+// Normalised Damerau-Levenshtein similarity a heard phrase needs to be
+// replaced by a name: one edit in five letters, two in ten.
+const MIN_SNAP_SIMILARITY: f64 = 0.8;
+// Below this probability Whisper was unsure of a word, which is when a near
+// miss for a name is likely to be a mishearing rather than another word.
+const UNSURE_PROBABILITY: f32 = 0.5;
+
+pub struct Vocabulary {
+    // Contact names, most recently added first.
+    pub names: Vec<String>,
+    // Contact companies, then the glossary.
+    pub terms: Vec<String>,
+}
+
+impl Vocabulary {
+    // Initial prompt for Whisper, or `None` when there is nothing to bias
+    // towards. Companies and glossary terms come first and names fill the
+    // remaining room, newest contacts first.
+    pub fn prompt(&self) -> Option<String> {
+        let mut words: Vec<&str> = Vec::new();
+        let mut length = 0;
+        for word in self.terms.iter().chain(&self.names) {
+            if length + word.len() + 2 > MAX_PROMPT_CHARS {
+                break;
+            }
+            if !words.iter().any(|w| w.eq_ignore_ascii_case(word)) {
+                length += word.len() + 2;
+                words.push(word);
+            }
+        }
+        if words.is_empty() {
+            return None;
+        }
+        Some(format!("{}.", words.join(", ")))
+    }
+
+    // Replaces words that are a near miss for a contact name with the name,
+    // then rebuilds the text of any segment that changed. Only words that
+    // look like a name (capitalised mid-sentence) or that Whisper was unsure
+    // of are considered, so "carry" stays "carry" with a contact Barry.
+    pub fn snap_names(&self, segments: &mut [Segment]) {
+        let names: Vec<(&str, String, usize)> = self
+            .names
+            .iter()
+            .map(|name| {
+                (
+                    name.as_str(),
+                    normalize(name),
+                    name.split_whitespace().count(),
+                )
+            })
+            .filter(|(_, normalized, words)| normalized.len() >= MIN_SNAP_CHARS && *words > 0)
+            .collect();
+        if names.is_empty() {
+            return;
+        }
+        for segment in segments.iter_mut() {
+            let mut changed = false;
+            let mut i = 0;
+            while i < segment.words.len() {
+                let sentence_start = i == 0 || segment.words[i - 1].text.ends_with(['.', '?', '!']);
+                match best_name(&segment.words[i..], sentence_start, &names) {
+                    Some((name, length)) => {
+                        println!(
+                            "DEBUG: Snapped '{}' to contact '{}'",
+                            join_words(&segment.words[i..i + length]),
+                            name
+                        );
+                        let replaced: Vec<Word> = segment.words.drain(i..i + length).collect();
+                        let name_words = name_words(name, &replaced);
+                        let count = name_words.len();
+                        segment.words.splice(i..i, name_words);
+                        i += count;
+                        changed = true;
+                    }
+                    None => i += 1,
+                }
+            }
+            if changed {
+                segment.text = join_words(&segment.words);
+            }
+        }
+    }
+}
+
+// The closest name to the words at the start of `words`, with how many words
+// it replaces. Names already spelled right are left alone.
+fn best_name<'a>(
+    words: &[Word],
+    sentence_start: bool,
+    names: &[(&'a str, String, usize)],
+) -> Option<(&'a str, usize)> {
+    let mut best: Option<(&str, usize, f64)> = None;
+    for (name, normalized, length) in names {
+        if *length > words.len() {
+            continue;
+        }
+        let heard_words = &words[..*length];
+        let heard = normalize(&join_words(heard_words));
+        if heard == *normalized {
+            return None;
+        }
+        let unsure = heard_words
+            .iter()
+            .any(|word| word.probability < UNSURE_PROBABILITY);
+        if !unsure && (sentence_start || !capitalised(&heard_words[0].text)) {
+            continue;
+        }
+        let similarity = strsim::normalized_damerau_levenshtein(&heard, normalized);
+        if similarity >= MIN_SNAP_SIMILARITY && best.is_none_or(|(_, _, s)| similarity > s) {
+            best = Some((name, *length, similarity));
+        }
+    }
+    best.map(|(name, length, _)| (name, length))
+}
+
+// `name` split into words, keeping the punctuation that followed the words it
+// replaces and their lowest probability.
+fn name_words(name: &str, replaced: &[Word]) -> Vec<Word> {
+    let trailing: String = replaced
+        .last()
+        .map(|word| {
+            word.text
+                .chars()
+                .rev()
+                .take_while(|c| c.is_ascii_punctuation())
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .collect()
+        })
+        .unwrap_or_default();
+    let probability = replaced
+        .iter()
+        .map(|word| word.probability)
+        .fold(1.0, f32::min);
+    let mut words: Vec<Word> = name
+        .split_whitespace()
+        .map(|part| Word {
+            text: part.to_string(),
+            probability,
+        })
+        .collect();
+    if let Some(last) = words.last_mut() {
+        last.text.push_str(&trailing);
+    }
+    words
+}
+
+fn capitalised(word: &str) -> bool {
+    word.chars()
+        .find(|c| c.is_alphabetic())
+        .is_some_and(char::is_uppercase)
+}
+
+fn join_words(words: &[Word]) -> String {
+    words
+        .iter()
+        .map(|word| word.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Lowercase letters and digits, words separated by single spaces.
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vocabulary(names: &[&str], terms: &[&str]) -> Vocabulary {
+        Vocabulary {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            terms: terms.iter().map(|term| term.to_string()).collect(),
+        }
+    }
+
+    // `text` as one segment; words ending in '?' were heard with low
+    // probability (the '?' is dropped).
+    fn segment(text: &str) -> Segment {
+        let words = text
+            .split_whitespace()
+            .map(|word| match word.strip_suffix('?') {
+                Some(word) => Word {
+                    text: word.to_string(),
+                    probability: 0.2,
+                },
+                None => Word {
+                    text: word.to_string(),
+                    probability: 0.95,
+                },
+            })
+            .collect::<Vec<_>>();
+        Segment {
+            start_ms: 0,
+            end_ms: 1000,
+            text: join_words(&words),
+            probability: 0.9,
+            words,
+        }
+    }
+
+    fn snapped(vocabulary: &Vocabulary, text: &str) -> String {
+        let mut segments = vec![segment(text)];
+        vocabulary.snap_names(&mut segments);
+        segments.remove(0).text
+    }
+
+    #[test]
+    fn snaps_names_that_were_misheard() {
+        let vocabulary = vocabulary(&["Barry", "Dave Kowalski", "Al"], &[]);
+        assert_eq!(
+            snapped(&vocabulary, "Invoice Bary for the deck."),
+            "Invoice Barry for the deck."
+        );
+        assert_eq!(
+            snapped(&vocabulary, "Call dave kowalsky? tomorrow"),
+            "Call Dave Kowalski tomorrow"
+        );
+        assert_eq!(snapped(&vocabulary, "Barey? said yes"), "Barry said yes");
+        // Already right, or too short a name to risk it.
+        assert_eq!(snapped(&vocabulary, "Invoice Barry"), "Invoice Barry");
+        assert_eq!(snapped(&vocabulary, "Invoice Ali"), "Invoice Ali");
+    }
+
+    #[test]
+    fn leaves_ordinary_words_alone() {
+        let vocabulary = vocabulary(&["Barry"], &[]);
+        assert_eq!(
+            snapped(&vocabulary, "Need to carry the drywall up"),
+            "Need to carry the drywall up"
+        );
+        assert_eq!(
+            snapped(&vocabulary, "Done. Carry the rest tomorrow"),
+            "Done. Carry the rest tomorrow"
+        );
+        assert_eq!(snapped(&vocabulary, "Berry picking"), "Berry picking");
+    }
+
+    #[test]
+    fn cleans_terms() {
+        let terms = vec![
+            "  sill   plate ".to_string(),
+            "".to_string(),
+            "Sill Plate".to_string(),
+            "PEX".to_string(),
+            "pex".to_string(),
+        ];
+        assert_eq!(clean_terms(terms), vec!["sill plate", "PEX"]);
+    }
+
+    #[test]
+    fn prompt_lists_terms_before_names_within_the_limit() {
+        assert_eq!(vocabulary(&[], &[]).prompt(), None);
+        assert_eq!(
+            vocabulary(&["Dave Miller", "soffit"], &["Acme Roofing", "soffit"]).prompt(),
+            Some("Acme Roofing, soffit, Dave Miller.".to_string())
+        );
+
+        let names: Vec<String> = (0..200).map(|i| format!("Contact {}", i)).collect();
+        let vocabulary = Vocabulary {
+            names,
+            terms: vec!["soffit".to_string()],
+        };
+        let prompt = vocabulary.prompt().unwrap();
+        assert!(prompt.len() <= MAX_PROMPT_CHARS);
+        assert!(prompt.starts_with("soffit, Contact 0, Contact 1,"));
+    }
+}
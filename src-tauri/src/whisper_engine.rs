@@ -0,0 +1,212 @@
+use std::sync::{Arc, Mutex};
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+use crate::model_manager;
+
+// Lazily loaded Whisper model, held in Tauri state so recordings reuse it
+// instead of re-reading the model file each time. Loading is keyed by path:
+// pointing it at a different model file swaps the context on the next call.
+// Inference is blocking; call it from a blocking worker, not the UI thread.
+
+// Stored under "transcription" in `app_settings`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct TranscriptionSettings {
+    #[serde(default = "default_model")]
+    pub model: String,
+    // ISO 639-1 code such as "es" or "pl"; `None` lets Whisper detect it.
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+fn default_model() -> String {
+    model_manager::DEFAULT_MODEL.to_string()
+}
+
+impl Default for TranscriptionSettings {
+    fn default() -> Self {
+        TranscriptionSettings {
+            model: default_model(),
+            language: None,
+        }
+    }
+}
+
+impl TranscriptionSettings {
+    // English-only models can only be told "en".
+    pub fn language_for(&self, multilingual: bool) -> Option<&str> {
+        if multilingual {
+            self.language.as_deref()
+        } else {
+            Some("en")
+        }
+    }
+}
+
+pub fn validate_language(language: &str) -> Result<(), String> {
+    if language.is_empty() || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!("Unsupported language '{}'", language));
+    }
+    match whisper_rs::get_lang_id(language) {
+        Some(_) => Ok(()),
+        None => Err(format!("Unsupported language '{}'", language)),
+    }
+}
+
+// One stretch of transcript. `probability` is Whisper's average token
+// probability (0.0 to 1.0); low values mark text worth double-checking.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct Segment {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+    pub probability: f32,
+    pub words: Vec<Word>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct Word {
+    pub text: String,
+    pub probability: f32,
+}
+
+// What came out of a recording. `NoSpeech` means voice activity detection
+// found nothing worth transcribing, so Whisper was never run.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Transcription {
+    Speech {
+        text: String,
+        segments: Vec<Segment>,
+    },
+    NoSpeech,
+}
+
+#[derive(Default)]
+pub struct WhisperEngine {
+    loaded: Mutex<Option<LoadedModel>>,
+}
+
+struct LoadedModel {
+    path: String,
+    context: Arc<WhisperContext>,
+}
+
+impl WhisperEngine {
+    // Returns the context for `model_path`, loading it if it is not the one
+    // already in memory. The lock is held while loading so concurrent callers
+    // wait for one load instead of each reading the file.
+    fn context(&self, model_path: &str) -> Result<Arc<WhisperContext>, String> {
+        let mut loaded = self
+            .loaded
+            .lock()
+            .map_err(|_| "Whisper engine lock poisoned".to_string())?;
+        if let Some(model) = loaded.as_ref() {
+            if model.path == model_path {
+                return Ok(model.context.clone());
+            }
+        }
+
+        println!("DEBUG: Loading Whisper model {}", model_path);
+        let context =
+            WhisperContext::new_with_params(model_path, WhisperContextParameters::default())
+                .map_err(|e| format!("Failed to load model: {}", e))?;
+        let context = Arc::new(context);
+        *loaded = Some(LoadedModel {
+            path: model_path.to_string(),
+            context: context.clone(),
+        });
+        Ok(context)
+    }
+
+    // Transcribes 16 kHz mono samples in [-1.0, 1.0]. With no `language`
+    // Whisper detects it from the first seconds of audio. `prompt` is text
+    // Whisper treats as already said, which steers it towards its spellings.
+    // Segment times are relative to the start of `samples`.
+    pub fn transcribe(
+        &self,
+        model_path: &str,
+        language: Option<&str>,
+        prompt: Option<&str>,
+        samples: &[f32],
+    ) -> Result<Vec<Segment>, String> {
+        let context = self.context(model_path)?;
+        let mut state = context
+            .create_state()
+            .map_err(|e| format!("Failed to create Whisper state: {}", e))?;
+
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_language(Some(language.unwrap_or("auto")));
+        params.set_print_special(false);
+        params.set_print_progress(false);
+        if let Some(prompt) = prompt {
+            params.set_initial_prompt(prompt);
+        }
+
+        state
+            .full(params, samples)
+            .map_err(|e| format!("Failed to run model: {}", e))?;
+        if language.is_none() {
+            if let Ok(id) = state.full_lang_id_from_state() {
+                println!(
+                    "DEBUG: Detected language: {}",
+                    whisper_rs::get_lang_str(id).unwrap_or("unknown")
+                );
+            }
+        }
+
+        let failed = |e: whisper_rs::WhisperError| format!("Failed to read segments: {}", e);
+        // Ids from end-of-text up are timestamps and other control tokens.
+        let eot = context.token_eot();
+        let num_segments = state.full_n_segments().map_err(failed)?;
+        let mut segments = Vec::new();
+        for i in 0..num_segments {
+            let text = state.full_get_segment_text_lossy(i).map_err(failed)?;
+            // Whisper times are in centiseconds.
+            let start_ms = state.full_get_segment_t0(i).map_err(failed)? * 10;
+            let end_ms = state.full_get_segment_t1(i).map_err(failed)? * 10;
+
+            let mut words: Vec<(Vec<u8>, Vec<f32>)> = Vec::new();
+            let mut token_probabilities = Vec::new();
+            for j in 0..state.full_n_tokens(i).map_err(failed)? {
+                let id = state.full_get_token_id(i, j).map_err(failed)?;
+                if id >= eot {
+                    continue;
+                }
+                let bytes = context.token_to_cstr(id).map_err(failed)?.to_bytes();
+                let probability = state.full_get_token_prob(i, j).map_err(failed)?;
+                token_probabilities.push(probability);
+                // A leading space starts a new word; other tokens continue one.
+                match words.last_mut() {
+                    Some((word, probabilities)) if !bytes.starts_with(b" ") => {
+                        word.extend_from_slice(bytes);
+                        probabilities.push(probability);
+                    }
+                    _ => words.push((bytes.to_vec(), vec![probability])),
+                }
+            }
+            let words: Vec<Word> = words
+                .into_iter()
+                .map(|(bytes, probabilities)| Word {
+                    text: String::from_utf8_lossy(&bytes).trim().to_string(),
+                    probability: mean(&probabilities),
+                })
+                .filter(|word| !word.text.is_empty())
+                .collect();
+            segments.push(Segment {
+                start_ms,
+                end_ms,
+                text: text.trim().to_string(),
+                probability: mean(&token_probabilities),
+                words,
+            });
+        }
+        Ok(segments)
+    }
+}
+
+fn mean(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f32>() / values.len() as f32
+}
@@ -26,7 +26,7 @@ function AppRoutes() {
 
       {status === "RECORDING" && <VoiceOverlay />}
       {status === "THINKING" && <ThinkingOverlay />}
-      {draft && draft.intent === "INVOICE" && <InvoiceModal />}
+      {draft && (draft.intent === "INVOICE" || draft.intent === "ESTIMATE") && <InvoiceModal />}
 
       <Routes>
          <Route path="/" element={<Layout />}>
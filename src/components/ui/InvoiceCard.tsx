@@ -17,7 +17,7 @@ export default function InvoiceCard({ invoice }: { invoice: Invoice }) {
         </div>
         <div>
           <h3 className="font-bold text-slate-900">{invoice.client}</h3>
-          <p className="text-xs text-slate-500 font-medium">#{invoice.id}</p>
+          <p className="text-xs text-slate-500 font-medium">#{invoice.number ?? invoice.id}</p>
         </div>
       </div>
       <div className="text-right">
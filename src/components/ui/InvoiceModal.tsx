@@ -4,17 +4,23 @@ import { useApp } from "../../context/AppContext";
 export default function InvoiceModal() {
   const { draft, setDraft, handleApproveInvoice, isSaving } = useApp();
 
-  if (!draft || draft.intent !== "INVOICE") return null;
+  if (!draft || (draft.intent !== "INVOICE" && draft.intent !== "ESTIMATE")) return null;
+  const isEstimate = draft.intent === "ESTIMATE";
+  // Fields the voice note did not give us; they are left empty to be filled in.
+  const guessed: string[] = draft.guessed_fields || [];
+  const labelClass = (field: string) =>
+    `text-xs font-bold uppercase tracking-wider mb-1 block ${guessed.includes(field) ? "text-amber-500" : "text-slate-400"}`;
+  const missing = (field: string) => guessed.includes(field) && <span className="normal-case font-medium"> · not heard, please check</span>;
 
   return (
     <div className="absolute inset-0 z-[100] bg-white animate-in slide-in-from-bottom duration-300 flex flex-col">
       <div className="p-6 border-b border-slate-100 flex justify-between items-center bg-white/80 backdrop-blur-md">
-        <h2 className="text-xl font-bold text-slate-800">New Invoice</h2>
+        <h2 className="text-xl font-bold text-slate-800">{isEstimate ? "New Estimate" : "New Invoice"}</h2>
         <button onClick={() => setDraft(null)} className="p-2 text-slate-400 hover:text-red-500 bg-slate-50 rounded-full active:scale-95"><X size={20} /></button>
       </div>
       <div className="flex-1 overflow-y-auto p-6 space-y-6">
         <div>
-          <label className="text-xs font-bold text-slate-400 uppercase tracking-wider mb-1 block">Client</label>
+          <label className={labelClass("client")}>Client{missing("client")}</label>
           <input
             className="w-full text-2xl font-bold text-slate-800 bg-transparent border-b border-slate-200 focus:border-blue-500 outline-none pb-2 transition-colors placeholder:text-slate-300"
             value={draft.client}
@@ -25,13 +31,14 @@ export default function InvoiceModal() {
 
         <div className="grid grid-cols-2 gap-4">
           <div>
-            <label className="text-xs font-bold text-slate-400 uppercase tracking-wider mb-1 block">Amount</label>
+            <label className={labelClass("amount")}>Amount{missing("amount")}</label>
             <div className="relative">
               <span className="absolute left-0 top-1 text-xl font-bold text-slate-400">$</span>
               <input
                 type="number"
                 className="w-full text-2xl font-bold text-slate-800 bg-transparent border-b border-slate-200 focus:border-blue-500 outline-none pb-2 pl-6 transition-colors"
-                value={draft.amount}
+                value={draft.amount ?? ""}
+                placeholder="0.00"
                 onChange={e => setDraft({ ...draft, amount: parseFloat(e.target.value) || 0 })}
               />
             </div>
@@ -47,8 +54,20 @@ export default function InvoiceModal() {
           </div>
         </div>
 
+        {isEstimate && (
+          <div>
+            <label className="text-xs font-bold text-slate-400 uppercase tracking-wider mb-1 block">Valid Until</label>
+            <input
+              type="date"
+              className="w-full text-lg font-medium text-slate-800 bg-transparent border-b border-slate-200 focus:border-blue-500 outline-none pb-2 transition-colors"
+              value={draft.valid_until || ''}
+              onChange={e => setDraft({ ...draft, valid_until: e.target.value || null })}
+            />
+          </div>
+        )}
+
         <div>
-          <label className="text-xs font-bold text-slate-400 uppercase tracking-wider mb-1 block">Description</label>
+          <label className={labelClass("description")}>Description{missing("description")}</label>
           <textarea
             className="w-full h-32 text-lg text-slate-600 bg-slate-50 rounded-xl p-4 border border-slate-100 focus:border-blue-500 outline-none resize-none transition-all"
             value={draft.description}
@@ -69,7 +88,7 @@ export default function InvoiceModal() {
                 Processing...
               </>
             ) : (
-              <>Save & Send Invoice</>
+              <>{isEstimate ? "Save Estimate" : "Save & Send Invoice"}</>
             )}
           </button>
         </div>
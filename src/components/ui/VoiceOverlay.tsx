@@ -1,8 +1,29 @@
+import { useEffect, useState } from "react";
 import { X } from "lucide-react";
+import { listen } from "@tauri-apps/api/event";
 import { useApp } from "../../context/AppContext";
+import { TranscriptUpdate } from "../../types";
 
 export default function VoiceOverlay() {
   const { stopRecordingFlow } = useApp();
+  const [finalText, setFinalText] = useState("");
+  const [partialText, setPartialText] = useState("");
+
+  // Live transcript, when the recording was started with `live`.
+  useEffect(() => {
+    const unlistenFinal = listen<TranscriptUpdate>("transcript-final", (event) => {
+      setFinalText((previous) => `${previous} ${event.payload.text}`.trim());
+    });
+    const unlistenPartial = listen<TranscriptUpdate>("transcript-partial", (event) => {
+      setPartialText(event.payload.text);
+    });
+    return () => {
+      unlistenFinal.then((unlisten) => unlisten());
+      unlistenPartial.then((unlisten) => unlisten());
+    };
+  }, []);
+
+  const hasTranscript = finalText.length > 0 || partialText.length > 0;
 
   return (
     <div className="absolute inset-0 z-[60] bg-gradient-to-b from-blue-600 to-blue-500 flex flex-col items-center justify-center animate-in fade-in duration-300">
@@ -30,7 +51,13 @@ export default function VoiceOverlay() {
       <p className="text-blue-100 text-lg mb-8 font-medium">Speak your invoice...</p>
 
       <div className="w-[90%] bg-white/10 backdrop-blur-md rounded-3xl p-6 text-white text-center border border-white/20 shadow-inner">
-        <p className="italic opacity-80 leading-relaxed">"Invoice Jason Park for HVAC installation, 5 hours..."</p>
+        {hasTranscript ? (
+          <p className="leading-relaxed">
+            {finalText} <span className="opacity-60">{partialText}</span>
+          </p>
+        ) : (
+          <p className="italic opacity-80 leading-relaxed">"Invoice Jason Park for HVAC installation, 5 hours..."</p>
+        )}
       </div>
 
       <button onClick={() => stopRecordingFlow()} className="mt-12 bg-white text-blue-600 px-8 py-4 rounded-full font-bold shadow-xl hover:scale-105 transition-transform active:scale-95">
@@ -1,7 +1,6 @@
 import React, { createContext, useContext, useState, useEffect, useRef, ReactNode } from "react";
 import { invoke } from "../lib/tauri";
-import { useWavRecorder } from "../hooks/useWavRecorder";
-import { generateInvoicePDF } from "../pdfGenerator";
+import { generateEstimatePDF, generateInvoicePDF } from "../pdfGenerator";
 import { supabase } from "../lib/supabase";
 import {
   Invoice,
@@ -14,6 +13,14 @@ import {
   ActivityItem,
 } from "../types";
 
+const UUID = /^[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$/i;
+
+// Records are keyed by UUID and the backend rejects anything else, so drafts
+// get one here before they are confirmed.
+function recordId(id: unknown): string {
+  return typeof id === "string" && UUID.test(id) ? id : crypto.randomUUID();
+}
+
 interface AppContextType {
   // State
   invoices: Invoice[];
@@ -75,12 +82,16 @@ export function AppProvider({ children }: { children: ReactNode }) {
   const [draft, setDraft] = useState<any>(null);
   const [draftImage, setDraftImage] = useState<string | null>(null);
   const [isSaving, setIsSaving] = useState(false);
+  // Further drafts from the same voice note, shown once the current one is closed.
+  const pendingDrafts = useRef<any[]>([]);
   const [debugLogs, setDebugLogs] = useState<string[]>([]);
 
   // TOAST STATE
   const [toast, setToast] = useState<{ message: string; type: 'success' | 'error' } | null>(null);
 
-  const { isRecording, startRecording, stopRecording, isBusy } = useWavRecorder();
+  // RECORDER STATE
+  const [isRecording, setIsRecording] = useState(false);
+  const [isBusy, setIsBusy] = useState(false);
 
   // --- HELPERS ---
   const showToast = (message: string, type: 'success' | 'error') => {
@@ -117,11 +128,20 @@ export function AppProvider({ children }: { children: ReactNode }) {
 
         // Re-hydrate to be safe (or fetch fresh)
         refreshData();
-      } catch (e) { console.error(e); }
+      } catch (e) {
+        console.error(e);
+        showToast("Database Error: " + e, "error");
+      }
     }
     init();
   }, []);
 
+  useEffect(() => {
+    if (!draft && pendingDrafts.current.length > 0) {
+      setDraft(pendingDrafts.current.shift());
+    }
+  }, [draft]);
+
   // Persist State to LocalStorage
   useEffect(() => { localStorage.setItem("invoices", JSON.stringify(invoices)); }, [invoices]);
   useEffect(() => { localStorage.setItem("tasks", JSON.stringify(tasks)); }, [tasks]);
@@ -209,209 +229,95 @@ export function AppProvider({ children }: { children: ReactNode }) {
   }
 
   // --- RECORDING ---
+  // The native recorder; with `live`, VoiceOverlay shows the transcript while
+  // the user is still speaking.
   async function startRecordingFlow() {
-    if (isBusy) return;
+    if (isBusy || isRecording) return;
     addDebug("🎤 Initializing Recorder...");
+    setIsBusy(true);
     try {
-      addDebug("🎙️ Starting Recording (WAV)...");
-      await startRecording();
+      await invoke("start_recording", { live: true });
+      setIsRecording(true);
       addDebug("🔴 Recording STARTED");
       setStatus("RECORDING");
     } catch (err: any) {
-      addDebug("❌ Mic Error: " + err.message);
+      addDebug("❌ Mic Error: " + err);
       showToast("Mic Error: " + err, "error");
+    } finally {
+      setIsBusy(false);
     }
   }
 
   async function stopRecordingFlow() {
+    if (!isRecording) return;
     addDebug("⏹️ Recording Stopped.");
-    try {
-      const wavBlob = await stopRecording();
-      handleStopRecording(wavBlob, "audio/wav");
-    } catch (e: any) {
-      console.error("Stop Recording Error:", e);
-      addDebug("❌ Stop Failed: " + e.message);
-      setStatus("IDLE");
-    }
-  }
-
-  const handleStopRecording = async (blob: Blob, mimeType: string) => {
-    addDebug(`🔄 Analyzing... Size: ${blob.size}`);
+    setIsRecording(false);
     setIsProcessing(true);
 
     try {
-      const apiKey = import.meta.env.VITE_GROQ_API_KEY;
-      if (!apiKey) {
-        addDebug("❌ ERROR: No API Key found.");
-        throw new Error("Missing API Key");
-      }
-
-      // --- STEP 1: TRANSCRIBE (Offline via whisper-rs) ---
-      const audioData = await blobToUint8Array(blob);
-      const bytes = Array.from(audioData);
-
-      console.log("Creating invoice locally...");
-      const text = await invoke<string>("transcribe_audio", { audioData: bytes });
-
-      addDebug(`📝 Transcript (Local): "${text.substring(0, 20)}..."`);
-
-      if (!text || text.trim().length < 5) {
-        addDebug("⚠️ No voice detected or too short.");
-        showToast("No voice detected. Please speak closer.", "error");
-        setIsProcessing(false);
-        return;
-      }
-
-      // --- STEP 2: CLASSIFY & EXTRACT (Groq Llama 3) ---
+      // Saves the recording to the inbox, finishes the transcript and turns
+      // it into drafts with the configured AI provider.
       addDebug("🧠 Thinking (Intent Classification)...");
-      await processWithAI(text);
-
+      const drafts = await invoke<any[]>("stop_recording");
+      await handleVoiceDrafts(drafts);
     } catch (error: any) {
-      addDebug("❌ Process Failed: " + error.message);
-      showToast(`AI Error: ${error.message}`, "error");
+      const message = error?.message ?? String(error);
+      addDebug("❌ Process Failed: " + message);
+      showToast(`AI Error: ${message}`, "error");
     } finally {
       setIsProcessing(false);
     }
-  };
+  }
 
-  async function processWithAI(text: string) {
-    const apiKey = import.meta.env.VITE_GROQ_API_KEY;
-    if (!apiKey) throw new Error("Missing Groq API Key");
-
-    const systemPrompt = `You are the AI Operating System for a construction business.
-Input: Raw voice transcription (may contain phonetic typos: "Ingeauch"->"Invoice", "Skedule"->"Schedule").
-Output: Strictly formatted JSON.
-
-CLASSIFY INTENT (Pick One):
-'create_invoice': Billing, money, completed jobs.
-'create_calendar': Meetings, visits, appointments.
-'create_task': To-dos, lists, reminders.
-'create_client': Contact info (names, phones).
-
-RETURN JSON (Select structure):
-IF Invoice: { "intent": "create_invoice", "client_name": "String", "items": ["String"], "total": Number }
-IF Calendar: { "intent": "create_calendar", "title": "String", "start_time": "ISO String (Estimate future date from now)", "duration_minutes": Number }
-IF Task: { "intent": "create_task", "description": "String", "priority": "High" | "Medium" | "Low" }
-IF Client: { "intent": "create_client", "name": "String", "phone": "String or null", "address": "String or null" }`;
-
-    const response = await fetch("https://api.groq.com/openai/v1/chat/completions", {
-      method: "POST",
-      headers: {
-        "Authorization": `Bearer ${apiKey}`,
-        "Content-Type": "application/json",
-      },
-      body: JSON.stringify({
-        model: "llama-3.3-70b-versatile",
-        messages: [
-          { role: "system", content: systemPrompt },
-          { role: "user", content: `Current Date: ${new Date().toISOString()}\nTranscription: "${text}"` }
-        ],
-        response_format: { type: "json_object" }
-      }),
-    });
-
-    if (!response.ok) throw new Error("AI Brain Failed");
-    const data = await response.json();
-    const result = JSON.parse(data.choices[0].message.content);
-
-    console.log("🧠 AI Brain Result:", result);
-    addDebug(`🧠 Intent: ${result.intent}`);
-
-    // --- DISPATCHER ---
-    const id = Math.random().toString(36).substr(2, 9);
-
-    switch (result.intent) {
-      case 'create_invoice':
-        let clientName = result.client_name || "New Client";
-        let clientPhone = "";
-        let clientCompany = "";
-
-        const existingClient = clients.find(c => c.name.toLowerCase().includes(clientName.toLowerCase()));
-
-        if (existingClient) {
-          clientName = existingClient.name;
-          clientPhone = existingClient.phone || "";
-          clientCompany = existingClient.company || "";
-          addDebug(`🔗 Linked to existing client: ${clientName}`);
-        } else if (result.client_name) {
-          const newAutoClient: Client = {
-            id: `CLI-${Math.random().toString(36).substr(2, 9)}`,
-            name: clientName,
-            phone: "",
-            address: ""
-          };
-          setClients(prev => [newAutoClient, ...prev]);
-          addDebug(`👤 Created new client: ${clientName}`);
+  // Invoices and estimates wait for review one at a time; tasks, contacts
+  // and appointments are saved straight away.
+  async function handleVoiceDrafts(drafts: any[]) {
+    const reviews: any[] = [];
+    for (const voiceDraft of drafts) {
+      addDebug(`🧠 Intent: ${voiceDraft.intent}`);
+      switch (voiceDraft.intent) {
+        case "INVOICE":
+        case "ESTIMATE": {
+          // Fill in the client's details when the name matches a contact.
+          const match = (voiceDraft.suggested_contacts || []).find(
+            (c: any) => c.name.toLowerCase() === String(voiceDraft.client || "").toLowerCase()
+          );
+          reviews.push(match
+            ? { ...voiceDraft, client: match.name, client_phone: match.phone || "", client_company: match.company || "" }
+            : voiceDraft);
+          break;
         }
 
-        setDraft({
-          id: `INV-${Math.floor(Math.random() * 90000) + 10000}`,
-          intent: "INVOICE",
-          created_at: new Date().toISOString(),
-          client: clientName,
-          client_phone: clientPhone,
-          client_company: clientCompany,
-          amount: result.total || 0,
-          description: (result.items || []).join(", ") || "Services",
-          status: "draft",
-          items: []
-        });
-        // Navigation should be handled by consuming component if needed,
-        // or by router redirection if we had access to router here.
-        // For now, setting draft triggers the Modal in App.tsx.
-        break;
-
-      case 'create_calendar':
-        const newEvent: CalendarEvent = {
-          id: `EVT-${id}`,
-          title: result.title || "New Meeting",
-          start_time: result.start_time || new Date().toISOString(),
-          duration_minutes: result.duration_minutes || 60
-        };
-        setCalendarEvents(prev => [newEvent, ...prev]);
-        invoke("confirm_calendar_event", { event: newEvent }).catch(e => console.error(e));
-        addDebug(`📅 Event Created: ${newEvent.title}`);
-        // We can't easily switch tab from here without router context,
-        // but since we are standardizing, we might want to expose a navigation helper
-        // or just let the user navigate.
-        // Ideally we redirect. We'll handle this later or via a listener.
-        break;
-
-      case 'create_task':
-        const newTask: Task = {
-          id: `TSK-${id}`,
-          description: result.description || "New Task",
-          priority: (result.priority as any) || "Medium",
-          done: false,
-          due_date: null
-        };
-        setTasks(prev => [newTask, ...prev]);
-        invoke("confirm_task", { task: newTask }).catch(e => console.error(e));
-        addDebug(`✅ Task Created: ${newTask.description}`);
-        break;
-
-      case 'create_client':
-        const newClient: Client = {
-          id: `CLI-${id}`,
-          name: result.name || "Unknown",
-          phone: result.phone,
-          address: result.address
-        };
-        setClients(prev => [newClient, ...prev]);
-        invoke("confirm_contact", { contact: {
-          id: newClient.id,
-          name: newClient.name,
-          phone: newClient.phone || "",
-          company: newClient.company || null,
-          created_at: new Date().toISOString()
-        }}).catch(e => console.error(e));
-        addDebug(`👤 Client Saved: ${newClient.name}`);
-        break;
-
-      default:
-        addDebug("⚠️ Unknown Intent: " + result.intent);
-        showToast("Could not understand command.", "error");
+        case "TASK":
+          await invoke("confirm_task", { task: voiceDraft });
+          addDebug(`✅ Task Created: ${voiceDraft.description}`);
+          break;
+
+        case "CONTACT":
+          await invoke("confirm_contact", { contact: voiceDraft });
+          addDebug(`👤 Client Saved: ${voiceDraft.name}`);
+          break;
+
+        case "CALENDAR":
+          await invoke("confirm_calendar_event", { event: voiceDraft });
+          addDebug(`📅 Event Created: ${voiceDraft.title}`);
+          break;
+
+        case "NO_SPEECH":
+          addDebug("⚠️ No voice detected.");
+          showToast("No voice detected. Please speak closer.", "error");
+          break;
+
+        default:
+          addDebug("⚠️ Unknown Intent: " + (voiceDraft.error || voiceDraft.intent));
+          showToast("Could not understand command.", "error");
+      }
     }
+    if (reviews.length > 0) {
+      pendingDrafts.current.push(...reviews.slice(1));
+      setDraft(reviews[0]);
+    }
+    refreshData();
   }
 
   // --- IMAGE HANDLING ---
@@ -427,17 +333,6 @@ IF Client: { "intent": "create_client", "name": "String", "phone": "String or nu
     }
   }
 
-  function blobToUint8Array(blob: Blob): Promise<Uint8Array> {
-    return new Promise((resolve, reject) => {
-      const reader = new FileReader();
-      reader.onloadend = () => {
-        resolve(new Uint8Array(reader.result as ArrayBuffer));
-      };
-      reader.onerror = reject;
-      reader.readAsArrayBuffer(blob);
-    });
-  }
-
   async function handleApproveInvoice() {
     if (!draft) return;
     setIsSaving(true);
@@ -452,22 +347,33 @@ IF Client: { "intent": "create_client", "name": "String", "phone": "String or nu
 
     try {
       if (draft.intent === "INVOICE") {
-        const invoiceData = { ...draft };
+        const invoiceData = { ...draft, id: recordId(draft.id) };
 
-        // Step 1: PDF Generation
+        // Step 1: Local DB (the PDF is rendered from this record)
+        let savedLocally = false;
         try {
-          console.log("📄 Requesting PDF generation for:", invoiceData.id);
-          const pdfPath = await generateInvoicePDF(invoiceData);
-          showToast(`PDF Saved to: ${pdfPath}`, "success");
-          invoiceData.pdf_path = pdfPath;
-        } catch (pdfErr) {
-          console.error("⚠️ PDF GENERATION FAILED:", pdfErr);
-          showToast("⚠️ PDF Failed to generate, but Invoice was saved to Cloud.", "error");
-          invoiceData.pdf_path = null;
+          await invoke("confirm_invoice", { invoice: invoiceData });
+          savedLocally = true;
+        } catch (localErr) {
+          console.error("⚠️ Local DB Save Failed:", localErr);
+        }
+
+        // Step 2: PDF Generation
+        invoiceData.pdf_path = null;
+        if (savedLocally) {
+          try {
+            console.log("📄 Requesting PDF generation for:", invoiceData.id);
+            const pdfPath = await generateInvoicePDF(invoiceData.id);
+            showToast(`PDF Saved to: ${pdfPath}`, "success");
+            invoiceData.pdf_path = pdfPath;
+          } catch (pdfErr) {
+            console.error("⚠️ PDF GENERATION FAILED:", pdfErr);
+            showToast("⚠️ PDF Failed to generate, but Invoice was saved to Cloud.", "error");
+          }
         }
 
-        // Step 2: Supabase
-        console.log("☁️ Step 2: Saving to Supabase...");
+        // Step 3: Supabase
+        console.log("☁️ Step 3: Saving to Supabase...");
         const dbPayload = {
           id: invoiceData.id,
           client: invoiceData.client,
@@ -487,7 +393,7 @@ IF Client: { "intent": "create_client", "name": "String", "phone": "String or nu
           throw error;
         }
 
-        // Step 3: Webhook
+        // Step 4: Webhook
         const webhookUrl = localStorage.getItem("webhook_url");
         if (webhookUrl) {
           try {
@@ -503,18 +409,23 @@ IF Client: { "intent": "create_client", "name": "String", "phone": "String or nu
           }
         }
 
-        // Step 4: Local UI Update & Persistence
-        console.log("🔄 Step 4: Updating Local State & DB");
+        // Step 5: Local UI Update
+        console.log("🔄 Step 5: Updating Local State");
         setInvoices(prev => [invoiceData, ...prev]);
 
-        try {
-          await invoke("confirm_invoice", { invoice: invoiceData });
-        } catch (localErr) {
-          console.error("⚠️ Local DB Save Failed:", localErr);
-        }
-
         showToast("✅ Data Saved to Cloud & Local!", "success");
 
+      } else if (draft.intent === "ESTIMATE") {
+        // Estimates stay local until they are converted into an invoice.
+        const estimate = { ...draft, id: recordId(draft.id) };
+        const number = await invoke<string>("confirm_estimate", { estimate });
+        try {
+          const pdfPath = await generateEstimatePDF(estimate.id);
+          showToast(`Estimate ${number} saved to: ${pdfPath}`, "success");
+        } catch (pdfErr) {
+          console.error("⚠️ PDF GENERATION FAILED:", pdfErr);
+          showToast(`Estimate ${number} saved, but the PDF failed to generate.`, "error");
+        }
       } else if (draft.intent === "TASK") {
         await invoke("confirm_task", { task: draft });
       } else if (draft.intent === "CONTACT") {
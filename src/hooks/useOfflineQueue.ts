@@ -1,119 +1,58 @@
-import { useState, useEffect } from 'react';
-
-export interface PendingInvoice {
-    id: string; // Unique ID for queue management
-    audioBlob: Blob;
-    timestamp: number;
-    status: 'pending' | 'uploading' | 'failed';
-    retryCount?: number;
-}
-
-// Serializable version for LocalStorage
-interface StoredPendingInvoice {
-    id: string;
-    base64Audio: string;
-    mimeType: string;
-    timestamp: number;
-    status: 'pending' | 'uploading' | 'failed';
-    retryCount?: number;
-}
-
-const STORAGE_KEY = 'offline_invoice_queue';
-
+import { useState, useEffect, useCallback } from 'react';
+import { listen } from '@tauri-apps/api/event';
+import { invoke } from '../lib/tauri';
+import { Job, JobProgress } from '../types';
+
+// The queue lives in the Rust backend (the `jobs` table), so recordings
+// survive restarts and are processed once a model or the network is back.
+// This hook mirrors it for the UI.
 export function useOfflineQueue() {
-    const [queue, setQueue] = useState<PendingInvoice[]>([]);
-
-    // Load from LocalStorage on mount
-    useEffect(() => {
-        loadQueue();
-    }, []);
-
-    // Save to LocalStorage whenever queue changes
-    useEffect(() => {
-        saveQueueToStorage(queue);
-    }, [queue]);
-
-    const loadQueue = async () => {
-        try {
-            const stored = localStorage.getItem(STORAGE_KEY);
-            if (stored) {
-                const parsed: StoredPendingInvoice[] = JSON.parse(stored);
-                const rehydrated = await Promise.all(parsed.map(async (item) => ({
-                    id: item.id,
-                    audioBlob: await base64ToBlob(item.base64Audio, item.mimeType),
-                    timestamp: item.timestamp,
-                    status: item.status,
-                    retryCount: item.retryCount || 0
-                })));
-                setQueue(rehydrated);
-            }
-        } catch (error) {
-            console.error("Failed to load offline queue:", error);
-        }
-    };
+    const [queue, setQueue] = useState<Job[]>([]);
+    const [online, setOnline] = useState<boolean | null>(null);
 
-    const saveQueueToStorage = async (currentQueue: PendingInvoice[]) => {
+    const refresh = useCallback(async () => {
         try {
-            const serializable = await Promise.all(currentQueue.map(async (item) => ({
-                id: item.id,
-                base64Audio: await blobToBase64(item.audioBlob),
-                mimeType: item.audioBlob.type,
-                timestamp: item.timestamp,
-                status: item.status,
-                retryCount: item.retryCount || 0
-            })));
-            localStorage.setItem(STORAGE_KEY, JSON.stringify(serializable));
+            setQueue((await invoke<Job[]>('get_jobs')) ?? []);
         } catch (error) {
-            console.error("Failed to save offline queue:", error);
+            console.error("Failed to load job queue:", error);
         }
-    };
+    }, []);
 
-    const addToQueue = (audioBlob: Blob) => {
-        const newItem: PendingInvoice = {
-            id: `queue_${Date.now()}_${Math.random().toString(36).substr(2, 9)}`,
-            audioBlob,
-            timestamp: Date.now(),
-            status: 'pending',
-            retryCount: 0
+    useEffect(() => {
+        refresh();
+        invoke<boolean>('get_connectivity').then(setOnline).catch(() => setOnline(null));
+        const unlistenProgress = listen<JobProgress>('job-progress', () => refresh());
+        const unlistenConnectivity = listen<{ online: boolean }>('connectivity-changed', (event) => {
+            setOnline(event.payload.online);
+        });
+        return () => {
+            unlistenProgress.then((unlisten) => unlisten());
+            unlistenConnectivity.then((unlisten) => unlisten());
         };
-        setQueue(prev => [...prev, newItem]);
+    }, [refresh]);
+
+    const addToQueue = async (audioBlob: Blob) => {
+        const audioData = Array.from(new Uint8Array(await audioBlob.arrayBuffer()));
+        const job = await invoke<Job>('queue_recording', { audioData });
+        await refresh();
+        return job;
     };
 
-    const removeFromQueue = (id: string) => {
-        setQueue(prev => prev.filter(item => item.id !== id));
+    const removeFromQueue = async (id: string) => {
+        await invoke('cancel_job', { id });
+        await refresh();
     };
 
-    const updateStatus = (id: string, status: 'pending' | 'uploading' | 'failed') => {
-        setQueue(prev => prev.map(item =>
-            item.id === id ? { ...item, status } : item
-        ));
+    const retry = async (id: string) => {
+        await invoke('retry_job', { id });
+        await refresh();
     };
 
     return {
         queue,
+        online,
         addToQueue,
         removeFromQueue,
-        updateStatus
+        retry
     };
 }
-
-// --- HELPERS ---
-
-function blobToBase64(blob: Blob): Promise<string> {
-    return new Promise((resolve, reject) => {
-        const reader = new FileReader();
-        reader.onloadend = () => {
-            const result = reader.result as string;
-            // Remove data URL prefix (e.g., "data:audio/webm;base64,")
-            const base64 = result.split(',')[1];
-            resolve(base64);
-        };
-        reader.onerror = reject;
-        reader.readAsDataURL(blob);
-    });
-}
-
-async function base64ToBlob(base64: string, mimeType: string): Promise<Blob> {
-    const res = await fetch(`data:${mimeType};base64,${base64}`);
-    return res.blob();
-}
@@ -1,10 +1,10 @@
-export type CommandIntent = 'create_invoice' | 'create_calendar' | 'create_task' | 'create_client';
 
 export interface CalendarEvent {
   id: string;
   title: string;
   start_time: string;
   duration_minutes: number;
+  contact_id?: string | null;
 }
 
 export interface Task {
@@ -24,12 +24,24 @@ export interface Client {
   company?: string;
 }
 
+export interface InvoiceItem {
+  description: string;
+  quantity: number;
+  unit?: string | null;
+  unit_price: number;
+  taxable?: boolean;
+}
+
 export interface Invoice {
   id: string;
   client: string;
   amount: number;
   status: string;
-  items: any[];
+  items: InvoiceItem[];
+  tax_rate?: number;
+  subtotal?: number;
+  tax?: number;
+  number?: string | null;
   description: string;
   client_phone?: string;
   client_company?: string;
@@ -38,6 +50,33 @@ export interface Invoice {
   created_at?: string;
   date?: string;
   type?: 'invoice'; // Added for UI grouping
+  // The estimate this invoice was converted from.
+  estimate_id?: string | null;
+}
+
+// A quote; `convert_estimate_to_invoice` turns an accepted one into an
+// invoice and links the two.
+export interface Estimate {
+  id: string;
+  number?: string | null;
+  client: string;
+  amount: number;
+  status: 'DRAFT' | 'SENT' | 'ACCEPTED' | 'DECLINED';
+  description: string;
+  items: InvoiceItem[];
+  tax_rate?: number;
+  subtotal?: number;
+  tax?: number;
+  client_phone?: string | null;
+  client_company?: string | null;
+  // YYYY-MM-DD, the last day the price holds.
+  valid_until?: string | null;
+  // Still DRAFT or SENT after `valid_until`.
+  expired?: boolean;
+  decided_at?: string | null;
+  invoice_id?: string | null;
+  pdf_path?: string | null;
+  created_at?: string;
 }
 
 export interface Contact {
@@ -47,6 +86,8 @@ export interface Contact {
   phone: string;
   company?: string | null;
   created_at: string;
+  // Document template for this client; the company default when null.
+  template_id?: string | null;
 }
 
 export interface Expense {
@@ -58,6 +99,7 @@ export interface Expense {
   date: string;
   image_path?: string;
   status: string;
+  created_at?: string | null;
 }
 
 export interface FinancialSummary {
@@ -76,3 +118,154 @@ export interface ActivityItem {
 }
 
 export type Tab = "DASHBOARD" | "INVOICES" | "CONTACTS" | "TASKS" | "CALENDAR" | "SETTINGS";
+
+export interface WhisperModel {
+  id: string;
+  size_mb: number;
+  multilingual: boolean;
+  quantized: boolean;
+  imported: boolean;
+  installed: boolean;
+  selected: boolean;
+}
+
+export interface TranscriptionSettings {
+  model: string;
+  language?: string | null; // null = detect automatically
+}
+
+// Trade terms fed to Whisper alongside contact names and companies.
+export interface VocabularySettings {
+  glossary: string[];
+}
+
+// Payload of the "model-download-progress" event.
+export interface ModelDownloadProgress {
+  model: string;
+  state: 'downloading' | 'verifying' | 'done' | 'failed';
+  downloaded_bytes: number;
+  total_bytes?: number | null;
+  error?: string | null;
+}
+
+export interface InputDevice {
+  name: string;
+  is_default: boolean;
+}
+
+// Payload of the "recording-level" event, both 0.0 to 1.0.
+export interface RecordingLevel {
+  rms: number;
+  peak: number;
+}
+
+// A voice recording in the inbox (or archive) and what became of it.
+export interface Recording {
+  id: string;
+  path: string;
+  duration_ms: number | null; // known once decoded on this device
+  transcript: string | null;
+  status: 'PENDING' | 'PROCESSING' | 'PROCESSED' | 'FAILED';
+  error: string | null;
+  created_at: string;
+  processed_at: string | null;
+  archived_at: string | null;
+  drafts: RecordingDraft[];
+}
+
+// Draft IDs become the invoice, task, contact or event ID once confirmed.
+export interface RecordingDraft {
+  draft_id: string;
+  intent: string;
+}
+
+// null turns a step off.
+export interface RetentionSettings {
+  archive_after_days: number | null;
+  purge_after_days: number | null;
+}
+
+// Background job from the backend queue. ANALYZE and TRANSCRIBE take
+// {recording_id}; SYNC takes {invoice_id}.
+export interface Job {
+  id: string;
+  kind: 'ANALYZE' | 'TRANSCRIBE' | 'SYNC';
+  payload: Record<string, unknown>;
+  status: 'PENDING' | 'RUNNING' | 'DONE' | 'FAILED';
+  attempts: number;
+  last_error: string | null;
+  result: unknown; // drafts for ANALYZE, the transcription for TRANSCRIBE
+  run_after: number; // unix ms
+  created_at: string;
+  updated_at: string;
+}
+
+// Payload of the "job-progress" event.
+export interface JobProgress {
+  id: string;
+  kind: Job['kind'];
+  state: 'queued' | 'running' | 'done' | 'retrying' | 'failed';
+  attempts: number;
+  error: string | null;
+  result: unknown;
+  retry_at: number | null;
+}
+
+export interface SyncSettings {
+  webhook_url: string | null;
+}
+
+// Stored by `update_company_profile`; the logo is set with `set_company_logo`.
+export interface CompanyProfile {
+  name: string;
+  logo_path: string | null;
+  licence_number: string | null;
+  address: string | null;
+  tax_id: string | null;
+  payment_terms: string | null;
+  footer: string | null;
+  // Default template for clients without their own.
+  template_id: string | null;
+}
+
+// Handlebars layout for invoice and estimate PDFs (markup described in
+// src-tauri/src/templates.rs). Built-in templates cannot be edited.
+export interface DocumentTemplate {
+  id: string;
+  name: string;
+  body: string;
+  built_in: boolean;
+  updated_at: string | null;
+}
+
+// Payload of the "transcript-partial" and "transcript-final" events sent
+// during live recording. A partial replaces the previous partial; finals are
+// appended in order.
+export interface TranscriptUpdate {
+  text: string;
+  segments: TranscriptSegment[];
+}
+
+// Result of `transcribe_audio`; "no_speech" when the recording held only
+// silence or background noise.
+export type Transcription =
+  | { status: "speech"; text: string; segments: TranscriptSegment[] }
+  | { status: "no_speech" };
+
+// `transcribe_audio` also returns the ID the transcript was stored under.
+export type TranscribedRecording = Transcription & { recording_id: string };
+
+// Probabilities are Whisper's token probabilities (0-1); low values mark
+// words worth highlighting for review.
+export interface TranscriptSegment {
+  start_ms: number;
+  end_ms: number;
+  text: string;
+  probability: number;
+  words: TranscriptWord[];
+}
+
+export interface TranscriptWord {
+  text: string;
+  probability: number;
+}